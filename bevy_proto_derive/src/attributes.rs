@@ -13,7 +13,9 @@ pub(crate) enum ProtoCompAttr {
     ///
     /// Generates the following code:
     /// ```rust
-    /// let component: ActualComponent = self.clone().into();
+    /// let mut cloned = self.clone();
+    /// commands.map_entity_refs(&mut cloned);
+    /// let component: ActualComponent = cloned.into();
     /// commands.insert(component);
     /// ```
     Into(Ident),
@@ -51,7 +53,8 @@ impl ToTokens for ProtoCompAttr {
         match self {
             Self::Into(ident) => {
                 let into_ident = quote! {
-                    let cloned = self.clone();
+                    let mut cloned = self.clone();
+                    commands.map_entity_refs(&mut cloned);
                     let component: #ident = cloned.into();
                     commands.insert(component);
                 };
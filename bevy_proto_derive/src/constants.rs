@@ -16,3 +16,4 @@ impl PartialEq<Symbol> for syn::Path {
 
 pub(crate) const WITH_IDENT: Symbol = Symbol("with");
 pub(crate) const INTO_IDENT: Symbol = Symbol("into");
+pub(crate) const SCHEMA_IDENT: Symbol = Symbol("schema");
@@ -5,6 +5,7 @@ use quote::quote;
 use syn::*;
 
 use crate::attributes::ProtoCompAttr;
+use crate::constants::SCHEMA_IDENT;
 
 mod attributes;
 mod constants;
@@ -34,11 +35,30 @@ mod constants;
 /// //    commands: &mut bevy_proto::ProtoCommands,
 /// // 	  asset_server: &bevy::prelude::Res<bevy::prelude::AssetServer>,
 /// //  ) {
-/// //      let component = self.clone();
+/// //      let mut component = self.clone();
+/// //      commands.map_entity_refs(&mut component);
 /// //      commands.insert(component);
 /// //    }
+/// //
+/// //  fn remove_self(&self, commands: &mut bevy_proto::ProtoCommands) {
+/// //      commands.raw_commands().remove::<#ident>();
+/// //    }
 /// //  }
 /// ```
+///
+/// The generated `remove_self` (used when hot reloading live entities) removes the inserted
+/// component, except with `#[proto_comp(with = "...")]`, whose inserted components are unknown.
+///
+/// `bevy_proto::MapEntityRefs` is implemented as well, resolving the `ProtoEntityRef`s among the
+/// fields (see its docs) before the clone is inserted. A custom `with` function has to resolve
+/// them itself, with `ProtoCommands::map_entity_refs`.
+///
+/// With the `typetag` feature, the `type` name is also submitted as a
+/// `bevy_proto::registry::TypetagName`, so that every derived component's name can be listed.
+///
+/// With the `schema` feature of `bevy_proto`, adding `#[proto_comp(schema)]` also registers the
+/// struct's [`JsonSchema`](https://docs.rs/schemars) as the schema of its `value`
+/// (see `bevy_proto::schema`). Without that feature, the attribute is a compile error.
 #[proc_macro_derive(ProtoComponent, attributes(proto_comp))]
 pub fn proto_comp_derive(input: TokenStream) -> TokenStream {
     let DeriveInput {
@@ -46,21 +66,67 @@ pub fn proto_comp_derive(input: TokenStream) -> TokenStream {
     } = parse_macro_input!(input);
 
     let mut generator = None;
+    let mut schema = None;
     for attr in attrs {
+        if attr.path.is_ident("proto_comp") {
+            if let Ok(path) = attr.parse_args::<Path>() {
+                if path == SCHEMA_IDENT {
+                    let name = ident.to_string();
+                    schema = Some(if cfg!(feature = "schema") {
+                        quote! {
+                            bevy_proto::schema::inventory::submit! {
+                                bevy_proto::schema::ComponentSchema::new::<#ident>(#name)
+                            }
+                        }
+                    } else {
+                        syn::Error::new_spanned(
+                            path,
+                            "`#[proto_comp(schema)]` requires the `schema` feature of bevy_proto",
+                        )
+                        .to_compile_error()
+                    });
+                    continue;
+                }
+            }
+        }
+
+        if generator.is_some() {
+            continue;
+        }
+
         let struct_attr: Result<ProtoCompAttr> = attr.parse_args();
         if let Ok(struct_attr) = struct_attr {
-            generator = Some(quote! { #struct_attr });
-            break;
+            generator = Some(struct_attr);
         }
     }
 
+    // Components inserted by a custom function can't be removed automatically
+    let remover = match &generator {
+        Some(ProtoCompAttr::With(..)) => quote! {},
+        Some(ProtoCompAttr::Into(into)) => quote! {
+            commands.raw_commands().remove::<#into>();
+        },
+        None => quote! {
+            commands.raw_commands().remove::<Self>();
+        },
+    };
+
+    let (map_refs, visit_refs) = match &data {
+        Data::Struct(..) | Data::Enum(..) => (
+            field_arms(&data, quote! { __map_entity_refs(__entities) }),
+            field_arms(&data, quote! { __visit_entity_refs(__visitor) }),
+        ),
+        _ => (quote! {}, quote! {}),
+    };
+
     let generator = if let Some(generator) = generator {
-        generator
+        quote! { #generator }
     } else {
         match data {
             Data::Struct(..) | Data::Enum(..) => {
                 quote! {
-                    let component = self.clone();
+                    let mut component = self.clone();
+                    commands.map_entity_refs(&mut component);
                     commands.insert(component);
                 }
             }
@@ -72,8 +138,22 @@ pub fn proto_comp_derive(input: TokenStream) -> TokenStream {
         }
     };
 
+    let (typetag, typetag_name) = if cfg!(feature = "typetag") {
+        let name = ident.to_string();
+        (
+            quote! { #[typetag::serde] },
+            quote! {
+                bevy_proto::registry::inventory::submit! {
+                    bevy_proto::registry::TypetagName::new(#name)
+                }
+            },
+        )
+    } else {
+        (quote! {}, quote! {})
+    };
+
     let output = quote! {
-        #[typetag::serde]
+        #typetag
         impl bevy_proto::prelude::ProtoComponent for #ident {
             fn insert_self(
                 &self,
@@ -82,8 +162,84 @@ pub fn proto_comp_derive(input: TokenStream) -> TokenStream {
             ) {
                 #generator;
             }
+
+            fn remove_self(&self, commands: &mut bevy_proto::prelude::ProtoCommands) {
+                #remover
+            }
+
+            fn visit_entity_refs(
+                &self,
+                visitor: &mut dyn FnMut(&bevy_proto::prelude::ProtoEntityRef),
+            ) {
+                bevy_proto::prelude::MapEntityRefs::visit_entity_refs(self, visitor);
+            }
+        }
+
+        impl bevy_proto::prelude::MapEntityRefs for #ident {
+            #[allow(unused_imports, unused_variables)]
+            fn map_entity_refs(&mut self, __entities: &bevy_proto::prelude::ProtoEntityMap) {
+                use bevy_proto::__private::{Field, MapField, SkipMapField};
+                match self {
+                    #map_refs
+                }
+            }
+
+            #[allow(unused_imports, unused_variables)]
+            fn visit_entity_refs(
+                &self,
+                __visitor: &mut dyn FnMut(&bevy_proto::prelude::ProtoEntityRef),
+            ) {
+                use bevy_proto::__private::{Field, SkipVisitField, VisitField};
+                match self {
+                    #visit_refs
+                }
+            }
         }
+
+        #typetag_name
+        #schema
     };
 
     output.into()
 }
+
+/// Generates a match arm for the struct (or each variant of the enum) calling the given method on
+/// each of its fields
+///
+/// Each field is wrapped in `bevy_proto::__private::Field`, so that the method resolves to a
+/// no-op for fields which don't implement `MapEntityRefs`.
+fn field_arms(data: &Data, call: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+    let arm = |path: proc_macro2::TokenStream, fields: &Fields| {
+        let bindings = fields
+            .iter()
+            .enumerate()
+            .map(|(index, field)| match &field.ident {
+                Some(ident) => ident.clone(),
+                None => Ident::new(&format!("field_{}", index), Span::call_site()),
+            })
+            .collect::<Vec<_>>();
+        let pattern = match fields {
+            Fields::Named(..) => quote! { #path { #(#bindings),* } },
+            Fields::Unnamed(..) => quote! { #path ( #(#bindings),* ) },
+            Fields::Unit => quote! { #path },
+        };
+        quote! {
+            #pattern => {
+                #(Field(#bindings).#call;)*
+            }
+        }
+    };
+
+    match data {
+        Data::Struct(data) => arm(quote! { Self }, &data.fields),
+        Data::Enum(data) => data
+            .variants
+            .iter()
+            .map(|variant| {
+                let ident = &variant.ident;
+                arm(quote! { Self::#ident }, &variant.fields)
+            })
+            .collect(),
+        Data::Union(..) => quote! {},
+    }
+}
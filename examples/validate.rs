@@ -0,0 +1,55 @@
+//! This example demonstrates how to validate prototype files from the command line.
+//!
+//! A game can add a tiny binary like this one to check its prototypes in CI. It only needs to
+//! define (or link in) the game's `ProtoComponent` types and call `bevy_proto::validate::run`.
+//!
+//! Try running it with:
+//!
+//! ```text
+//! cargo run --example validate --features validate
+//! cargo run --example validate --features validate -- --format json assets/prototypes/templates
+//! ```
+//!
+//! The process exits with a nonzero status if any errors are found, which will fail a CI job.
+//!
+
+use std::process::ExitCode;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use bevy_proto::data::ProtoDataOptions;
+use bevy_proto::prelude::*;
+
+// These are the components used by the prototypes in `assets/prototypes/templates/`.
+// In a real game, these would be imported from the game's own crate instead.
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, Serialize, Deserialize, ProtoComponent, Component)]
+struct NPC;
+
+#[derive(Clone, Serialize, Deserialize, ProtoComponent, Component)]
+struct Occupation(OccupationType);
+
+#[derive(Serialize, Deserialize, Copy, Clone, Debug)]
+enum OccupationType {
+    Unemployed,
+    Miner,
+    Shopkeeper,
+}
+
+#[derive(Clone, Serialize, Deserialize, ProtoComponent, Component)]
+struct Health {
+    max: u16,
+}
+
+#[derive(Clone, Serialize, Deserialize, ProtoComponent, Component)]
+struct Named(String);
+
+fn main() -> ExitCode {
+    bevy_proto::validate::run(ProtoDataOptions {
+        // Used unless other directories are given on the command line
+        directories: vec![String::from("assets/prototypes/templates")],
+        ..ProtoDataOptions::default()
+    })
+}
@@ -0,0 +1,206 @@
+//! Contains [`PrototypeAsset`], which allows prototype files to be loaded through the
+//! [`AssetServer`](bevy::asset::AssetServer).
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use bevy::asset::{
+    AssetEvent, AssetLoader, Assets, BoxedFuture, Handle, HandleId, LoadContext, LoadedAsset,
+};
+use bevy::ecs::event::{Events, ManualEventReader};
+use bevy::ecs::system::{Local, Resource};
+use bevy::prelude::{Mut, World};
+use bevy::reflect::TypeUuid;
+use bevy::utils::HashMap;
+
+use crate::data::{check_errors, ProtoDataOptions, ProtoSource};
+use crate::errors::{ProtoDeserializeError, ProtoLoadError, ProtoLoadErrorKind, ProtoLoadErrors};
+use crate::prelude::ProtoData;
+use crate::prototype::Prototypical;
+
+/// An asset containing every prototype defined in a single prototype file.
+///
+/// Files with one of the [`asset_extensions`](ProtoDataOptions::asset_extensions) can be loaded as a
+/// [`PrototypeAsset`]. Loading one with the [`AssetServer`](bevy::asset::AssetServer) also adds its
+/// prototypes to the [`ProtoData`] resource. They remain there until the asset is unloaded, and are
+/// updated whenever the asset is modified (such as through Bevy's own hot reloading).
+///
+/// # Examples
+///
+/// ```
+/// use bevy::prelude::*;
+/// use bevy_proto::prelude::*;
+///
+/// #[derive(Resource)]
+/// struct LevelPrototypes(Handle<PrototypeAsset>);
+///
+/// fn load_level(mut commands: Commands, asset_server: Res<AssetServer>) {
+///     let handle = asset_server.load("prototypes/level_1.proto.yaml");
+///     commands.insert_resource(LevelPrototypes(handle));
+/// }
+/// ```
+#[derive(TypeUuid)]
+#[uuid = "7a2c6bf4-4a2e-4c3b-9a0e-3f6d1c8b5e21"]
+pub struct PrototypeAsset {
+    /// The path of the file this asset was loaded from, including the asset folder (as it's recorded
+    /// in each prototype's [`ProtoSource`]).
+    pub path: PathBuf,
+    /// The prototypes defined in the file.
+    pub prototypes: Vec<Arc<dyn Prototypical>>,
+    /// The errors for any prototypes in the file that could not be deserialized.
+    pub errors: Vec<ProtoDeserializeError>,
+    /// The index within the file of each prototype in `prototypes`
+    documents: Vec<usize>,
+}
+
+/// Loads [`PrototypeAsset`] files using the deserializers configured in [`ProtoDataOptions`].
+pub(crate) struct PrototypeAssetLoader {
+    pub(crate) options: ProtoDataOptions,
+    /// The folder asset paths are relative to
+    pub(crate) asset_folder: PathBuf,
+}
+
+impl AssetLoader for PrototypeAssetLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let data = std::str::from_utf8(bytes)?;
+            // Recorded as it would be when loading the configured directories
+            let path = self.asset_folder.join(load_context.path());
+            let path = path.as_path();
+
+            let mut asset = PrototypeAsset {
+                path: path.to_path_buf(),
+                prototypes: Vec::new(),
+                errors: Vec::new(),
+                documents: Vec::new(),
+            };
+
+            let mut results = self.options.deserialize_file(path, data);
+            self.options.identify(path, &mut results);
+            for (document, result) in results.into_iter().enumerate() {
+                match result {
+                    Ok(proto) => {
+                        asset.prototypes.push(Arc::from(proto));
+                        asset.documents.push(document);
+                    }
+                    Err(err) => asset.errors.push(err),
+                }
+            }
+
+            load_context.set_default_asset(LoadedAsset::new(asset));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &self.options.asset_extensions
+    }
+}
+
+/// Tracks the path of each [`PrototypeAsset`] and the prototypes it added to [`ProtoData`].
+#[derive(Resource, Default)]
+pub(crate) struct PrototypeAssetNames(HashMap<HandleId, (PathBuf, Vec<String>)>);
+
+/// Keeps [`ProtoData`] in sync with the currently loaded [`PrototypeAsset`]s.
+pub(crate) fn sync_prototype_assets(
+    world: &mut World,
+    mut reader: Local<ManualEventReader<AssetEvent<PrototypeAsset>>>,
+) {
+    let events = world.resource::<Events<AssetEvent<PrototypeAsset>>>();
+    let events = reader
+        .iter(events)
+        .map(|event| match event {
+            AssetEvent::Created { handle } | AssetEvent::Modified { handle } => {
+                (handle.clone_weak(), false)
+            }
+            AssetEvent::Removed { handle } => (handle.clone_weak(), true),
+        })
+        .collect::<Vec<(Handle<PrototypeAsset>, bool)>>();
+
+    if events.is_empty() {
+        return;
+    }
+
+    world.resource_scope(|world, mut data: Mut<ProtoData>| {
+        let previous_checks = check_errors(&data);
+        for (handle, removed) in events {
+            let previous = world
+                .resource_mut::<PrototypeAssetNames>()
+                .0
+                .remove(&handle.id());
+            if let Some((path, names)) = previous {
+                world.resource_mut::<ProtoLoadErrors>().clear_path(&path);
+                // The names may have since been taken by prototypes from elsewhere
+                for name in names {
+                    if data
+                        .source_of(&name)
+                        .is_some_and(|source| source.path == path)
+                    {
+                        data.remove_prototype(&name);
+                    }
+                }
+            }
+
+            if removed {
+                continue;
+            }
+
+            let asset = match world.resource::<Assets<PrototypeAsset>>().get(&handle) {
+                Some(asset) => asset,
+                None => continue,
+            };
+            let path = asset.path.clone();
+            let prototypes = asset
+                .prototypes
+                .iter()
+                .cloned()
+                .zip(asset.documents.iter().copied())
+                .collect::<Vec<_>>();
+            let asset_errors = asset.errors.clone();
+
+            let options = world.resource::<ProtoDataOptions>().clone();
+            let names = world.resource_scope(|world, mut errors: Mut<ProtoLoadErrors>| {
+                for err in asset_errors {
+                    errors.push(ProtoLoadError::new(
+                        path.clone(),
+                        ProtoLoadErrorKind::Deserialize(err),
+                    ));
+                }
+
+                let mut names = Vec::with_capacity(prototypes.len());
+                for (proto, document) in prototypes {
+                    let name = proto.name().to_string();
+                    let source = ProtoSource::new(path.clone(), document)
+                        .with_layer(options.layer_of(&path));
+                    if data.insert_from_source(
+                        world,
+                        &mut errors,
+                        Box::new(proto),
+                        source,
+                        &options,
+                    ) {
+                        names.push(name);
+                    }
+                }
+                data.apply_patches(world, &mut errors, &options, None);
+                data.resolve_merges(world, &mut errors);
+                names
+            });
+
+            world
+                .resource_mut::<PrototypeAssetNames>()
+                .0
+                .insert(handle.id(), (path, names));
+        }
+        // Tables and entity references may have been broken (or fixed) by the changed prototypes
+        world
+            .resource_mut::<ProtoLoadErrors>()
+            .replace_checks(previous_checks, check_errors(&data));
+
+        #[cfg(feature = "analysis")]
+        crate::data::analyze_deps(&data);
+    });
+}
@@ -0,0 +1,472 @@
+//! Contains [`ProtoChild`] and [`ProtoChildren`], used to spawn a prototype's child entities.
+use std::fmt::Formatter;
+
+use bevy::ecs::system::EntityCommands;
+use bevy::prelude::{AssetServer, BuildChildren, Commands, Component, Entity, Res};
+use indexmap::IndexSet;
+use serde::de::{self, DeserializeSeed, MapAccess, SeqAccess, Visitor};
+#[cfg(feature = "typetag")]
+use serde::Deserialize;
+use serde::{Deserializer, Serialize, Serializer};
+
+use crate::data::ProtoData;
+use crate::entity_ref::ProtoEntityMap;
+use crate::instance;
+use crate::params::{self, ResolvedParams};
+use crate::prototype::{spawn_internal, Prototype, PrototypeSeed, Prototypical};
+use crate::registry::ProtoComponentRegistry;
+
+/// A single entry of a prototype's `children`.
+///
+/// Each child is either the name of another prototype or a prototype declared inline:
+///
+/// ```yaml
+/// name: Knight
+/// children:
+///   - Nameplate
+///   - name: WeaponMount
+///     templates: Mount
+///     components:
+///       - type: Offset
+///         value: [0.5, 1.0]
+/// ```
+///
+/// Children are spawned (along with their own children) each time their parent is, and are
+/// attached to it with Bevy's `Parent` and `Children` components. A prototype also spawns the
+/// children of its templates, before its own.
+///
+/// Each child uses the defaults of its own [`ProtoParams`](crate::ProtoParams), rather than those
+/// of its parent.
+pub enum ProtoChild {
+    /// A child spawned from the prototype with the given name.
+    Reference(String),
+    /// A child spawned from a prototype declared inline.
+    Inline(Box<Prototype>),
+}
+
+impl ProtoChild {
+    /// Get the prototype this child is spawned from
+    ///
+    /// # Arguments
+    ///
+    /// * `data`: The prototype data containing any referenced prototype
+    ///
+    /// returns: Option<&dyn Prototypical> which is `None` if the referenced prototype doesn't exist
+    pub fn prototype<'a>(&'a self, data: &'a ProtoData) -> Option<&'a dyn Prototypical> {
+        match self {
+            Self::Reference(name) => data.get_prototype(name),
+            Self::Inline(proto) => Some(proto.as_ref()),
+        }
+    }
+}
+
+impl Serialize for ProtoChild {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Reference(name) => serializer.serialize_str(name),
+            Self::Inline(proto) => proto.serialize(serializer),
+        }
+    }
+}
+
+/// Deserializes a child whose components are registered with `typetag` (see [`Prototype`]'s
+/// `Deserialize` impl)
+#[cfg(feature = "typetag")]
+impl<'de> Deserialize<'de> for ProtoChild {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        ProtoChildSeed(PrototypeSeed::new(&ProtoComponentRegistry::default()))
+            .deserialize(deserializer)
+    }
+}
+
+/// Deserializes a [`ProtoChild`], using the given seed for an inline prototype
+#[derive(Copy, Clone)]
+struct ProtoChildSeed<'a>(PrototypeSeed<'a>);
+
+impl<'a, 'de> DeserializeSeed<'de> for ProtoChildSeed<'a> {
+    type Value = ProtoChild;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'a, 'de> Visitor<'de> for ProtoChildSeed<'a> {
+    type Value = ProtoChild;
+
+    fn expecting(&self, formatter: &mut Formatter) -> std::fmt::Result {
+        formatter.write_str("the name of a prototype or an inline prototype")
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        Ok(ProtoChild::Reference(v.to_string()))
+    }
+
+    fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let proto = self.0.visit_map(map)?;
+        Ok(ProtoChild::Inline(Box::new(proto)))
+    }
+}
+
+/// Deserializes a prototype's `children`, using the given [`ProtoComponentRegistry`] for any
+/// inline prototypes
+#[derive(Copy, Clone)]
+pub(crate) struct ChildrenSeed<'a>(ProtoChildSeed<'a>);
+
+impl<'a> ChildrenSeed<'a> {
+    pub(crate) fn new(registry: &'a ProtoComponentRegistry) -> Self {
+        Self(ProtoChildSeed(PrototypeSeed::new(registry)))
+    }
+}
+
+impl<'a, 'de> DeserializeSeed<'de> for ChildrenSeed<'a> {
+    type Value = Vec<ProtoChild>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'a, 'de> Visitor<'de> for ChildrenSeed<'a> {
+    type Value = Vec<ProtoChild>;
+
+    fn expecting(&self, formatter: &mut Formatter) -> std::fmt::Result {
+        formatter.write_str("a list of children")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut children = Vec::with_capacity(seq.size_hint().unwrap_or_default());
+        while let Some(child) = seq.next_element_seed(self.0)? {
+            children.push(child);
+        }
+        Ok(children)
+    }
+}
+
+/// The child entities spawned for a prototype (see [`Prototypical::spawn_hierarchy`]).
+///
+/// When [`ProtoDataOptions::reload_instances`](crate::data::ProtoDataOptions::reload_instances)
+/// is enabled, this is also inserted into the entity the prototype was spawned into, so that
+/// reloading it can resolve [`ProtoEntityRef`](crate::ProtoEntityRef)s to the same children.
+///
+/// # Examples
+///
+/// ```
+/// use bevy::prelude::*;
+/// use bevy_proto::prelude::*;
+///
+/// #[derive(Component)]
+/// struct Hitbox;
+///
+/// fn spawn_knight(mut commands: Commands, data: Res<ProtoData>, asset_server: Res<AssetServer>) {
+///     let proto = data.get_prototype("Knight").expect("Prototype doesn't exist!");
+///
+///     let (knight, children) = proto.spawn_hierarchy(&mut commands, &data, &asset_server);
+///     info!("Spawned {:?} with {} children", knight.id(), children.len());
+///
+///     if let Some(hitbox) = children.find("Hitbox") {
+///         commands.entity(hitbox).insert(Hitbox);
+///     }
+/// }
+/// ```
+#[derive(Component, Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProtoChildren {
+    children: Vec<SpawnedChild>,
+}
+
+/// A single child entity spawned for a prototype.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpawnedChild {
+    /// The name of the prototype the child was spawned from (which may be empty for an inline
+    /// prototype without a name).
+    pub name: String,
+    /// The local name of the child (see [`Prototype::local`]), if it has one.
+    pub local: Option<String>,
+    /// The child entity.
+    pub entity: Entity,
+    /// The children spawned for this child.
+    pub children: ProtoChildren,
+}
+
+impl ProtoChildren {
+    /// Get an iterator over the direct children (in spawn order)
+    pub fn iter(&self) -> impl Iterator<Item = &SpawnedChild> {
+        self.children.iter()
+    }
+
+    /// Get an iterator over every descendant entity, depth-first
+    pub fn entities(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.children.iter().flat_map(|child| {
+            std::iter::once(child.entity)
+                .chain(Box::new(child.children.entities()) as Box<dyn Iterator<Item = Entity>>)
+        })
+    }
+
+    /// Get the first direct child spawned from the prototype with the given name
+    pub fn get(&self, name: &str) -> Option<Entity> {
+        self.children
+            .iter()
+            .find(|child| child.name == name)
+            .map(|child| child.entity)
+    }
+
+    /// Get the first descendant spawned from the prototype with the given name, searching
+    /// depth-first
+    pub fn find(&self, name: &str) -> Option<Entity> {
+        self.children.iter().find_map(|child| {
+            if child.name == name {
+                Some(child.entity)
+            } else {
+                child.children.find(name)
+            }
+        })
+    }
+
+    /// Get the descendant with the given local name (see [`Prototype::local`]), searching
+    /// depth-first
+    pub fn local(&self, local: &str) -> Option<Entity> {
+        self.children.iter().find_map(|child| {
+            if child.local.as_deref() == Some(local) {
+                Some(child.entity)
+            } else {
+                child.children.local(local)
+            }
+        })
+    }
+
+    /// The number of direct children
+    pub fn len(&self) -> usize {
+        self.children.len()
+    }
+
+    /// Returns true if no children were spawned
+    pub fn is_empty(&self) -> bool {
+        self.children.is_empty()
+    }
+
+    /// Records the local name of every descendant in the given map, in the order they were
+    /// spawned (so that the same entity keeps each name)
+    #[cfg(feature = "hot_reloading")]
+    pub(crate) fn extend_entity_map(&self, entities: &mut ProtoEntityMap) {
+        for child in &self.children {
+            if let Some(local) = &child.local {
+                entities.insert(local, child.entity);
+            }
+            child.children.extend_entity_map(entities);
+        }
+    }
+}
+
+/// Inserts the given prototype's components (with the given params) and spawns its children
+///
+/// Every entity in the hierarchy is spawned before any components are inserted, so that
+/// [`ProtoEntityRef`](crate::ProtoEntityRef)s can resolve to any of them.
+///
+/// `ancestors` contains the names of the prototypes being spawned above this one, so that a
+/// prototype can't (eventually) spawn itself as a child.
+pub(crate) fn insert_hierarchy<'w, 's, 'a, P: Prototypical + ?Sized>(
+    proto: &P,
+    mut entity: EntityCommands<'w, 's, 'a>,
+    data: &Res<ProtoData>,
+    asset_server: &Res<AssetServer>,
+    resolved: &ResolvedParams,
+    ancestors: &mut Vec<String>,
+) -> (EntityCommands<'w, 's, 'a>, ProtoChildren) {
+    // === Spawn Entities === //
+    let mut locals = ProtoEntityMap::default();
+    if let Some(local) = proto.local() {
+        locals.insert(local, entity.id());
+    }
+
+    let mut nodes = Vec::new();
+    let id = entity.id();
+    let spawned = spawn_children(
+        proto,
+        id,
+        entity.commands(),
+        data,
+        ancestors,
+        &mut locals,
+        &mut nodes,
+    );
+
+    // === Insert Components === //
+    // Inserted first, so that the prototype's own components take precedence
+    instance::mark_instance(proto, &mut entity, data);
+    let mut proto_commands = proto.create_commands(entity, data);
+    proto_commands.resolved = Some(resolved);
+    proto_commands.entities = Some(&locals);
+    spawn_internal(
+        proto,
+        &mut proto_commands,
+        data,
+        asset_server,
+        &mut IndexSet::default(),
+        &[],
+    );
+
+    let mut entity: EntityCommands = proto_commands.into();
+    #[cfg(feature = "hot_reloading")]
+    if data.reload_instances {
+        entity.insert(spawned.clone());
+    }
+    for (child_proto, child_entity) in nodes {
+        let resolved = params::resolve_defaults(child_proto, data);
+        let mut child_entity = entity.commands().entity(child_entity);
+        instance::mark_instance(child_proto, &mut child_entity, data);
+        let mut proto_commands = child_proto.create_commands(child_entity, data);
+        proto_commands.resolved = Some(&resolved);
+        proto_commands.entities = Some(&locals);
+        spawn_internal(
+            child_proto,
+            &mut proto_commands,
+            data,
+            asset_server,
+            &mut IndexSet::default(),
+            &[],
+        );
+    }
+
+    (entity, spawned)
+}
+
+/// Spawns an empty entity for each child of the given prototype (recursively), recording their
+/// local names in `locals` and the prototypes to insert into them in `nodes`
+fn spawn_children<'a, P: Prototypical + ?Sized>(
+    proto: &'a P,
+    entity: Entity,
+    commands: &mut Commands,
+    data: &'a ProtoData,
+    ancestors: &mut Vec<String>,
+    locals: &mut ProtoEntityMap,
+    nodes: &mut Vec<(&'a dyn Prototypical, Entity)>,
+) -> ProtoChildren {
+    let mut spawned = ProtoChildren::default();
+
+    let mut children = Vec::new();
+    collect_children(proto, data, &mut IndexSet::default(), &mut children);
+    if children.is_empty() {
+        return spawned;
+    }
+
+    ancestors.push(proto.name().to_string());
+    for child in children {
+        let child_proto = match child.prototype(data) {
+            Some(child_proto) => child_proto,
+            None => {
+                bevy::log::warn!(
+                    "Could not spawn child `{}` of prototype `{}`: the prototype does not exist",
+                    name_of(child),
+                    proto.name()
+                );
+                continue;
+            }
+        };
+
+        // Inline prototypes without a name can't be referenced, so they can't form a cycle
+        let name = child_proto.name();
+        if !name.is_empty() && ancestors.iter().any(|ancestor| ancestor == name) {
+            bevy::log::error!(
+                "Prototype `{}` is its own ancestor (through `{}`). The child has been skipped.",
+                name,
+                ancestors.join("` -> `")
+            );
+            continue;
+        }
+
+        let child_entity = commands.spawn_empty().id();
+        commands.entity(entity).add_child(child_entity);
+
+        // The first entity given a local name keeps it
+        let local = child_proto.local().map(str::to_string);
+        if let Some(local) = &local {
+            locals.insert(local, child_entity);
+        }
+        nodes.push((child_proto, child_entity));
+
+        let children = spawn_children(
+            child_proto,
+            child_entity,
+            commands,
+            data,
+            ancestors,
+            locals,
+            nodes,
+        );
+        spawned.children.push(SpawnedChild {
+            name: name.to_string(),
+            local,
+            entity: child_entity,
+            children,
+        });
+    }
+    ancestors.pop();
+
+    spawned
+}
+
+/// Collects the children of the given prototype, starting with those of its templates
+fn collect_children<'a, P: Prototypical + ?Sized>(
+    proto: &'a P,
+    data: &'a ProtoData,
+    traversed: &mut IndexSet<&'a str>,
+    children: &mut Vec<&'a ProtoChild>,
+) {
+    traversed.insert(proto.name());
+
+    for template in proto.templates_rev() {
+        // Cycles are reported when spawning components
+        if traversed.contains(template.as_str()) {
+            continue;
+        }
+
+        if let Some(parent) = data.get_prototype(template) {
+            collect_children(parent, data, traversed, children);
+        }
+    }
+
+    children.extend(proto.children());
+}
+
+/// The name used to describe the given child in logs
+fn name_of(child: &ProtoChild) -> &str {
+    match child {
+        ProtoChild::Reference(name) => name,
+        ProtoChild::Inline(proto) => &proto.name,
+    }
+}
+
+/// Get the inline child at the given path of indices into each prototype's `children`
+/// (or the prototype itself for an empty path)
+pub(crate) fn inline_child<'a>(
+    proto: &'a dyn Prototypical,
+    path: &[usize],
+) -> Option<&'a dyn Prototypical> {
+    match path.split_first() {
+        None => Some(proto),
+        Some((index, rest)) => match proto.children().get(*index)? {
+            ProtoChild::Inline(child) => inline_child(child.as_ref(), rest),
+            ProtoChild::Reference(_) => None,
+        },
+    }
+}
+
+/// Calls the given function on every inline child of the given prototype (recursively), along
+/// with its path (see [`inline_child`])
+pub(crate) fn for_each_inline<'a>(
+    proto: &'a dyn Prototypical,
+    path: &mut Vec<usize>,
+    func: &mut dyn FnMut(&'a Prototype, &[usize]),
+) {
+    for (index, child) in proto.children().iter().enumerate() {
+        if let ProtoChild::Inline(child) = child {
+            path.push(index);
+            func(child, path);
+            for_each_inline(child.as_ref(), path, func);
+            path.pop();
+        }
+    }
+}
@@ -1,7 +1,10 @@
 //! Contains the [`ProtoComponent`] trait.
+use std::any::Any;
+
 use bevy::prelude::{AssetServer, Res, World};
 
 use crate::data::{ProtoCommands, ProtoData};
+use crate::entity_ref::ProtoEntityRef;
 use crate::prototype::Prototypical;
 
 /// Specifies how a type inserts components into an entity.
@@ -73,11 +76,24 @@ use crate::prototype::Prototypical;
 /// Implementations of insert_self can arbitrarily insert zero, one, or many components or bundles.
 ///
 ///  This trait allows components to be used within [`Prototypical`](crate::prototype::Prototypical) structs.
-#[typetag::serde(tag = "type", content = "value")]
-pub trait ProtoComponent: Send + Sync + 'static {
+#[cfg_attr(feature = "typetag", typetag::serde(tag = "type", content = "value"))]
+pub trait ProtoComponent: Any + Send + Sync + 'static {
     /// Defines how this struct inserts components and/or bundles into an entity.
     fn insert_self(&self, commands: &mut ProtoCommands, asset_server: &Res<AssetServer>);
+    /// Defines how this struct removes the components and/or bundles it inserts.
+    ///
+    /// This is used when hot reloading updates an entity whose prototype no longer declares this
+    /// component (see `ProtoDataOptions::reload_instances`). By default, nothing is removed.
+    #[allow(unused_variables)]
+    fn remove_self(&self, commands: &mut ProtoCommands) {}
     /// Defines how this struct creates and inserts asset handles for later use.
     #[allow(unused_variables)]
     fn prepare(&self, world: &mut World, prototype: &dyn Prototypical, data: &mut ProtoData) {}
+    /// Calls the given function on each [`ProtoEntityRef`] this struct contains.
+    ///
+    /// This is used to report references to local names that aren't spawned along with the
+    /// component when loading. Derived implementations visit the same references as their
+    /// [`MapEntityRefs`](crate::MapEntityRefs) implementation. By default, nothing is visited.
+    #[allow(unused_variables)]
+    fn visit_entity_refs(&self, visitor: &mut dyn FnMut(&ProtoEntityRef)) {}
 }
@@ -1,20 +1,36 @@
 //! Provides resource and deserialization for prototype data.
 use std::any::{Any, TypeId};
 use std::ffi::OsStr;
+use std::fmt::{Display, Formatter};
 use std::ops::{Deref, DerefMut};
+use std::path::{Component, Path, PathBuf};
 
 use bevy::asset::{Asset, HandleId, HandleUntyped};
 use bevy::ecs::prelude::World;
 use bevy::ecs::system::{EntityCommands, Resource};
-use bevy::prelude::{FromWorld, Handle};
+use bevy::prelude::{FromWorld, Handle, Mut};
 use bevy::reflect::Uuid;
 use bevy::utils::HashMap;
+#[cfg(any(feature = "analysis", feature = "validate"))]
+use bevy::utils::HashSet;
 use dyn_clone::DynClone;
+#[cfg(any(feature = "analysis", feature = "validate"))]
 use indexmap::IndexSet;
 use serde::{Deserialize, Serialize};
 
-use crate::plugin::DefaultProtoDeserializer;
-use crate::{components::ProtoComponent, prototype::Prototypical, utils::handle_cycle};
+use crate::deserializers::{default_deserializers, YamlProtoDeserializer};
+use crate::entity_ref::{find_missing_refs, MapEntityRefs, ProtoEntityMap};
+use crate::errors::{ProtoDeserializeError, ProtoLoadError, ProtoLoadErrorKind, ProtoLoadErrors};
+use crate::loading::{ProtoDataLoaded, ProtoLoadState, ProtoLoadTasks};
+use crate::params::ResolvedParams;
+use crate::random::ProtoRng;
+use crate::table::{find_invalid_tables, is_table_path, table_name, ProtoTable};
+use crate::{
+    children::{for_each_inline, inline_child, ProtoChild},
+    components::ProtoComponent,
+    prototype::{resolve_template, Prototypical},
+    registry::ProtoComponentRegistry,
+};
 
 /// A String newtype for a handle's asset path
 #[derive(Serialize, Deserialize, Clone, Hash, Eq, PartialEq, Debug)]
@@ -36,6 +52,49 @@ impl From<&HandlePath> for HandleId {
 
 type UuidHandleMap = HashMap<Uuid, HandleUntyped>;
 
+/// The location a prototype was loaded from.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ProtoSource {
+    /// The path of the file containing the prototype.
+    pub path: PathBuf,
+    /// The index of the prototype within its file.
+    ///
+    /// This is the index of the document for multi-document YAML files, or the prototype's
+    /// position in a list of prototypes. Files containing a single prototype always use `0`.
+    pub document: usize,
+    /// The index of the directory (in [`ProtoDataOptions::directories`]) containing the file.
+    ///
+    /// See [`ProtoDataOptions::layered`] for how layers are used.
+    pub layer: usize,
+}
+
+impl ProtoSource {
+    /// Creates a new [`ProtoSource`] for the prototype at the given index of a file.
+    pub fn new(path: impl Into<PathBuf>, document: usize) -> Self {
+        Self {
+            path: path.into(),
+            document,
+            layer: 0,
+        }
+    }
+
+    /// Sets the layer the file belongs to
+    pub fn with_layer(mut self, layer: usize) -> Self {
+        self.layer = layer;
+        self
+    }
+}
+
+impl Display for ProtoSource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.path.display())?;
+        if self.document > 0 {
+            write!(f, ", document {}", self.document)?;
+        }
+        Ok(())
+    }
+}
+
 /// A resource containing data for all prototypes that need data stored
 #[derive(Resource)]
 pub struct ProtoData {
@@ -47,7 +106,33 @@ pub struct ProtoData {
             HashMap<HandleId, UuidHandleMap>,
         >,
     >,
+    /// Maps Inline Prototype Address -> Key of its handles (the name of the prototype declaring it
+    /// followed by its path among the children, such as `Knight/0/1`)
+    inline_keys: HashMap<usize, String>,
     pub(crate) prototypes: HashMap<String, Box<dyn Prototypical>>,
+    /// Maps Prototype Name -> Source (for prototypes loaded from a file)
+    pub(crate) sources: HashMap<String, ProtoSource>,
+    /// Maps Prototype Name -> Source of each component (for prototypes loaded from a file)
+    pub(crate) component_sources: HashMap<String, Vec<ProtoSource>>,
+    /// Patches waiting for the rest of their layer to be loaded
+    pending_patches: Vec<(Box<dyn Prototypical>, ProtoSource)>,
+    /// Maps Prototype Name -> Source of each patch applied to it (or targeting it, if it's missing)
+    pub(crate) patch_sources: HashMap<String, Vec<ProtoSource>>,
+    /// The registry used to name components (such as when they're excluded)
+    pub(crate) registry: ProtoComponentRegistry,
+    /// The generator used to evaluate random values (shared with the [`ProtoRng`] resource)
+    pub(crate) rng: ProtoRng,
+    /// Maps Table Name -> Table
+    pub(crate) tables: HashMap<String, ProtoTable>,
+    /// Maps Table Name -> Source (for tables loaded from a file)
+    pub(crate) table_sources: HashMap<String, ProtoSource>,
+    /// Whether to mark spawned entities with a [`ProtoInstance`](crate::ProtoInstance)
+    pub(crate) mark_instances: bool,
+    /// Whether to insert a `Name` into spawned entities
+    pub(crate) name_instances: bool,
+    /// Whether spawned entities are updated when their prototype is reloaded
+    #[cfg(feature = "hot_reloading")]
+    pub(crate) reload_instances: bool,
 }
 
 impl ProtoData {
@@ -55,10 +140,28 @@ impl ProtoData {
     pub fn empty() -> Self {
         Self {
             handles: HashMap::default(),
+            inline_keys: HashMap::default(),
             prototypes: HashMap::default(),
+            sources: HashMap::default(),
+            component_sources: HashMap::default(),
+            pending_patches: Vec::new(),
+            patch_sources: HashMap::default(),
+            registry: ProtoComponentRegistry::default(),
+            rng: ProtoRng::default(),
+            tables: HashMap::default(),
+            table_sources: HashMap::default(),
+            mark_instances: false,
+            name_instances: false,
+            #[cfg(feature = "hot_reloading")]
+            reload_instances: false,
         }
     }
 
+    /// Get the generator used to evaluate random values when spawning prototypes
+    pub fn rng(&self) -> &ProtoRng {
+        &self.rng
+    }
+
     /// Get a loaded prototype with the given name
     ///
     /// # Arguments
@@ -66,8 +169,708 @@ impl ProtoData {
     /// * `name`: The name of the prototype
     ///
     /// returns: Option<&Prototype>
+    ///
+    /// When using [`ProtoDataOptions::namespacing`], the name may also be given as a path,
+    /// such as `/templates/npc` or `templates/npc.yaml`.
     pub fn get_prototype(&self, name: &str) -> Option<&dyn Prototypical> {
-        self.prototypes.get(name).map(|b| b.as_ref())
+        if let Some(proto) = self.prototypes.get(name) {
+            return Some(proto.as_ref());
+        }
+
+        // Try interpreting the name as a path
+        let id = name.trim_start_matches('/');
+        self.prototypes
+            .get(id)
+            .or_else(|| {
+                let (id, _extension) = id.rsplit_once('.')?;
+                self.prototypes.get(id)
+            })
+            .map(|b| b.as_ref())
+    }
+
+    /// Get the location a loaded prototype was defined in
+    ///
+    /// Prototypes added at runtime (such as with [`ProtoData::insert_prototype`]) have no source.
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: The name of the prototype
+    ///
+    /// returns: Option<&ProtoSource>
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bevy::prelude::*;
+    /// use bevy_proto::prelude::*;
+    ///
+    /// fn print_source(data: Res<ProtoData>) {
+    ///     if let Some(source) = data.source_of("Alice") {
+    ///         println!("Alice is defined in {}", source);
+    ///     }
+    /// }
+    /// ```
+    pub fn source_of(&self, name: &str) -> Option<&ProtoSource> {
+        self.sources.get(name)
+    }
+
+    /// Get the source of each of a loaded prototype's components, in the same order as
+    /// [`Prototypical::iter_components`]
+    ///
+    /// Components added by a [patch](crate::Prototype::patch) have the source (and layer) of that
+    /// patch, which makes it possible to tell which layer contributed each component.
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: The name of the prototype
+    ///
+    /// returns: Option<&[ProtoSource]>
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bevy::prelude::*;
+    /// use bevy_proto::prelude::*;
+    ///
+    /// fn list_layers(data: Res<ProtoData>) {
+    ///     for source in data.component_sources("Goblin").unwrap_or_default() {
+    ///         println!("Added by layer {} ({})", source.layer, source);
+    ///     }
+    /// }
+    /// ```
+    pub fn component_sources(&self, name: &str) -> Option<&[ProtoSource]> {
+        self.component_sources.get(name).map(Vec::as_slice)
+    }
+
+    /// Checks whether the `type` name of the given component is one of the `excluded` names
+    pub(crate) fn is_excluded(&self, component: &dyn ProtoComponent, excluded: &[&str]) -> bool {
+        self.registry
+            .name_of(component)
+            .is_some_and(|name| excluded.contains(&name.as_str()))
+    }
+
+    /// Formats the given prototype name along with its source (if it has one), for use in diagnostics
+    pub(crate) fn describe(&self, name: &str) -> String {
+        match self.source_of(name) {
+            Some(source) => format!("'{}' ({})", name, source),
+            None => format!("'{}'", name),
+        }
+    }
+
+    /// Adds a prototype, replacing any existing prototype with the same name
+    ///
+    /// The prototype's components are prepared (see [`ProtoComponent::prepare`]) and, if the
+    /// `analysis` feature is enabled, the template hierarchy is checked for cycles again.
+    ///
+    /// # Arguments
+    ///
+    /// * `world`: The world used to prepare the prototype's components
+    /// * `prototype`: The prototype to add
+    ///
+    /// returns: Option<Box<dyn Prototypical, Global>>
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bevy::prelude::*;
+    /// use bevy_proto::prelude::*;
+    ///
+    /// fn add_prototype(world: &mut World) {
+    ///     let proto = Prototype {
+    ///         name: String::from("Generated"),
+    ///         templates: Vec::default(),
+    ///         components: Vec::default(),
+    ///         ..Default::default()
+    ///     };
+    ///
+    ///     world.resource_scope(|world, mut data: Mut<ProtoData>| {
+    ///         data.insert_prototype(world, Box::new(proto));
+    ///     });
+    /// }
+    /// ```
+    pub fn insert_prototype(
+        &mut self,
+        world: &mut World,
+        prototype: Box<dyn Prototypical>,
+    ) -> Option<Box<dyn Prototypical>> {
+        let previous = self.prepare_and_insert(world, prototype);
+        self.resolve_merges_in(world);
+
+        #[cfg(feature = "analysis")]
+        analyze_deps(self);
+
+        previous
+    }
+
+    /// Removes the prototype with the given name, along with any handles stored for it (or for
+    /// its inline children)
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: The name of the prototype
+    ///
+    /// returns: Option<Box<dyn Prototypical, Global>>
+    pub fn remove_prototype(&mut self, name: &str) -> Option<Box<dyn Prototypical>> {
+        self.remove_handles(name);
+        self.sources.remove(name);
+        self.component_sources.remove(name);
+        self.patch_sources.remove(name);
+        self.prototypes.remove(name)
+    }
+
+    /// Get a [`ProtoTable`] by name
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: The name of the table
+    ///
+    /// returns: Option<&ProtoTable>
+    pub fn get_table(&self, name: &str) -> Option<&ProtoTable> {
+        self.tables.get(name)
+    }
+
+    /// Get an iterator over all tables
+    pub fn iter_tables(&self) -> impl Iterator<Item = &ProtoTable> {
+        self.tables.values()
+    }
+
+    /// Adds the given table, replacing (and returning) any table with the same name
+    ///
+    /// Unlike tables loaded from a file, the prototypes and tables it references aren't checked.
+    ///
+    /// # Arguments
+    ///
+    /// * `table`: The table to add
+    ///
+    /// returns: Option<ProtoTable>
+    pub fn insert_table(&mut self, table: ProtoTable) -> Option<ProtoTable> {
+        self.table_sources.remove(&table.name);
+        self.tables.insert(table.name.clone(), table)
+    }
+
+    /// Removes the table with the given name
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: The name of the table
+    ///
+    /// returns: Option<ProtoTable>
+    pub fn remove_table(&mut self, name: &str) -> Option<ProtoTable> {
+        self.table_sources.remove(name);
+        self.tables.remove(name)
+    }
+
+    /// Deserializes and adds every prototype in the given string
+    ///
+    /// The string is deserialized with the deserializer registered for the given format in
+    /// [`ProtoDataOptions`]. If any prototype fails to deserialize, none of them are added.
+    ///
+    /// # Arguments
+    ///
+    /// * `world`: The world used to prepare the prototypes' components
+    /// * `data`: The prototype data
+    /// * `format`: The format of the data, given as a file extension (such as `"yaml"` or `"json"`)
+    ///
+    /// returns: Result<Vec<String, Global>, ProtoDeserializeError>
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bevy::prelude::*;
+    /// use bevy_proto::prelude::*;
+    ///
+    /// fn load_server_prototypes(world: &mut World) {
+    ///     let config = r#"
+    ///     name: "Server Boss"
+    ///     templates: Boss
+    ///     "#;
+    ///
+    ///     world.resource_scope(|world, mut data: Mut<ProtoData>| {
+    ///         let names = data
+    ///             .load_from_str(world, config, "yaml")
+    ///             .expect("config should be valid");
+    ///     });
+    /// }
+    /// ```
+    pub fn load_from_str(
+        &mut self,
+        world: &mut World,
+        data: &str,
+        format: &str,
+    ) -> Result<Vec<String>, ProtoDeserializeError> {
+        let options = world
+            .get_resource::<ProtoDataOptions>()
+            .expect("Expected options for ProtoData");
+        let prototypes = options
+            .deserializer_for_extension(format)
+            .deserialize_many(data, &options.registry)
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if prototypes.iter().any(|proto| proto.name().is_empty()) {
+            return Err(ProtoDeserializeError::new("prototype is missing a `name`"));
+        }
+
+        let mut names = Vec::with_capacity(prototypes.len());
+        for proto in prototypes {
+            names.push(proto.name().to_string());
+            self.prepare_and_insert(world, proto);
+        }
+        self.resolve_merges_in(world);
+
+        #[cfg(feature = "analysis")]
+        analyze_deps(self);
+
+        Ok(names)
+    }
+
+    /// Removes every prototype and loads them again from the directories in [`ProtoDataOptions`]
+    ///
+    /// This also removes any prototypes that were added at runtime. The [`ProtoLoadErrors`] and
+    /// [`ProtoLoadState`] resources are replaced with the results of the reload and a
+    /// [`ProtoDataLoaded`] event is sent.
+    ///
+    /// Unlike the initial load, this always runs synchronously.
+    ///
+    /// # Arguments
+    ///
+    /// * `world`: The world used to prepare the prototypes' components
+    ///
+    /// returns: ()
+    pub fn reload_all(&mut self, world: &mut World) {
+        let options = world
+            .get_resource::<ProtoDataOptions>()
+            .expect("Expected options for ProtoData")
+            .clone();
+
+        self.handles.clear();
+        self.inline_keys.clear();
+        self.prototypes.clear();
+        self.sources.clear();
+        self.component_sources.clear();
+        self.pending_patches.clear();
+        self.patch_sources.clear();
+        self.tables.clear();
+        self.table_sources.clear();
+
+        let errors = self.load_directories(world, &options);
+        let state = ProtoLoadState::from_errors(&errors);
+        world.insert_resource(errors);
+        world.insert_resource(state);
+        world.send_event(ProtoDataLoaded { state });
+    }
+
+    /// Removes the handles stored for the prototype with the given name and for its inline children
+    fn remove_handles(&mut self, name: &str) {
+        self.handles.remove(name);
+
+        let Self {
+            handles,
+            inline_keys,
+            prototypes,
+            ..
+        } = self;
+        if let Some(proto) = prototypes.get(name) {
+            for_each_inline(proto.as_ref(), &mut Vec::new(), &mut |child, _| {
+                if let Some(key) = inline_keys.remove(&address_of(child)) {
+                    handles.remove(&key);
+                }
+            });
+        }
+    }
+
+    /// Get the key of the handles stored for the given prototype
+    ///
+    /// Inline children are keyed by their path within the prototype declaring them, since their
+    /// own names may be empty or shared.
+    fn handle_key<'a>(&'a self, prototype: &'a dyn Prototypical) -> &'a str {
+        self.inline_keys
+            .get(&address_of(prototype))
+            .map_or(prototype.name(), String::as_str)
+    }
+
+    /// Prepares the given prototype's components and adds it, dropping the handles of any
+    /// prototype it replaces
+    pub(crate) fn prepare_and_insert(
+        &mut self,
+        world: &mut World,
+        prototype: Box<dyn Prototypical>,
+    ) -> Option<Box<dyn Prototypical>> {
+        self.remove_handles(prototype.name());
+        self.sources.remove(prototype.name());
+        self.component_sources.remove(prototype.name());
+
+        for component in prototype.iter_components() {
+            component.prepare(world, prototype.as_ref(), self);
+        }
+        for_each_inline(prototype.as_ref(), &mut Vec::new(), &mut |child, path| {
+            let key = std::iter::once(prototype.name().to_string())
+                .chain(path.iter().map(usize::to_string))
+                .collect::<Vec<_>>()
+                .join("/");
+            self.inline_keys.insert(address_of(child), key);
+            for component in child.iter_components() {
+                component.prepare(world, child, self);
+            }
+        });
+
+        self.prototypes
+            .insert(prototype.name().to_string(), prototype)
+    }
+
+    /// Adds a prototype loaded from the given source, following the [`DuplicatePolicy`] if another
+    /// file already defined a prototype with the same name
+    ///
+    /// Patches are held until every prototype in their layer has been added (see [`ProtoData::apply_patches`]).
+    ///
+    /// Returns true if the prototype was added.
+    pub(crate) fn insert_from_source(
+        &mut self,
+        world: &mut World,
+        errors: &mut ProtoLoadErrors,
+        prototype: Box<dyn Prototypical>,
+        source: ProtoSource,
+        options: &ProtoDataOptions,
+    ) -> bool {
+        // Anything from a later layer must see the patches of the earlier ones
+        self.apply_patches(world, errors, options, Some(source.layer));
+
+        if prototype.patch().is_some() {
+            self.pending_patches.push((prototype, source));
+            return false;
+        }
+
+        let name = prototype.name().to_string();
+
+        let overrides_layer = options.layered
+            && self
+                .sources
+                .get(&name)
+                .is_some_and(|original| original.layer < source.layer);
+
+        if let Some(original) = self.sources.get(&name).filter(|_| !overrides_layer) {
+            match options.duplicate_policy {
+                DuplicatePolicy::Error => {
+                    errors.push(ProtoLoadError::new(
+                        source.path,
+                        ProtoLoadErrorKind::Duplicate {
+                            name,
+                            original: original.clone(),
+                        },
+                    ));
+                    return false;
+                }
+                DuplicatePolicy::WarnKeepFirst => {
+                    bevy::log::warn!(
+                        "Prototype `{}` in {} was already defined in {}. Keeping the original.",
+                        name,
+                        source,
+                        original
+                    );
+                    return false;
+                }
+                DuplicatePolicy::WarnKeepLast => {
+                    bevy::log::warn!(
+                        "Prototype `{}` in {} was already defined in {}. Replacing the original.",
+                        name,
+                        source,
+                        original
+                    );
+                }
+                DuplicatePolicy::Override => {}
+            }
+        }
+
+        let component_sources = vec![source.clone(); prototype.iter_components().len()];
+        self.prepare_and_insert(world, prototype);
+        self.component_sources
+            .insert(name.clone(), component_sources);
+        // Patches to any prototype this replaces no longer apply
+        self.patch_sources.remove(&name);
+        self.sources.insert(name, source);
+        true
+    }
+
+    /// Applies the pending patches from layers before the given one (or every pending patch if `None`)
+    pub(crate) fn apply_patches(
+        &mut self,
+        world: &mut World,
+        errors: &mut ProtoLoadErrors,
+        options: &ProtoDataOptions,
+        before_layer: Option<usize>,
+    ) {
+        let (ready, pending) = std::mem::take(&mut self.pending_patches)
+            .into_iter()
+            .partition::<Vec<_>, _>(|(_, source)| {
+                before_layer.map_or(true, |layer| source.layer < layer)
+            });
+        self.pending_patches = pending;
+
+        for (patch, source) in ready {
+            self.apply_patch(world, errors, options, patch, source);
+        }
+    }
+
+    /// Applies a single patch to its target prototype
+    fn apply_patch(
+        &mut self,
+        world: &mut World,
+        errors: &mut ProtoLoadErrors,
+        options: &ProtoDataOptions,
+        mut patch: Box<dyn Prototypical>,
+        source: ProtoSource,
+    ) {
+        let target = patch.patch().unwrap_or_default().to_string();
+        let name = match self.get_prototype(&target) {
+            Some(proto) => proto.name().to_string(),
+            None => {
+                self.patch_sources
+                    .entry(target.clone())
+                    .or_default()
+                    .push(source.clone());
+                errors.push(ProtoLoadError::new(
+                    source.path,
+                    ProtoLoadErrorKind::MissingPatchTarget { target },
+                ));
+                return;
+            }
+        };
+
+        self.patch_sources
+            .entry(name.clone())
+            .or_default()
+            .push(source.clone());
+
+        let mut proto = self.prototypes.remove(&name).unwrap();
+        let original = self.sources.remove(&name);
+        let mut sources = self.component_sources.remove(&name).unwrap_or_default();
+
+        let (components, added) = match (proto.components_mut(), patch.components_mut()) {
+            (Some(components), Some(added)) => (components, std::mem::take(added)),
+            _ => {
+                errors.push(ProtoLoadError::new(
+                    source.path,
+                    ProtoLoadErrorKind::UnpatchableTarget { target },
+                ));
+                self.prototypes.insert(name.clone(), proto);
+                self.component_sources.insert(name.clone(), sources);
+                if let Some(original) = original {
+                    self.sources.insert(name, original);
+                }
+                return;
+            }
+        };
+        // Prototypes added at runtime don't have sources for their components
+        sources.resize(
+            components.len(),
+            original.clone().unwrap_or_else(|| source.clone()),
+        );
+
+        // === Add or Replace === //
+        for component in added {
+            match components.iter().position(|existing| {
+                same_type(&options.registry, existing.as_ref(), component.as_ref())
+            }) {
+                Some(index) => {
+                    // Merged components are merged into the component they patch
+                    let component = match crate::merge::as_merged(component.as_ref()) {
+                        Some(merged) => match crate::merge::merge_into(
+                            components[index].as_ref(),
+                            merged,
+                            &options.registry,
+                        ) {
+                            Ok(component) => component,
+                            Err(err) => {
+                                errors.push(ProtoLoadError::new(
+                                    source.path.clone(),
+                                    ProtoLoadErrorKind::Deserialize(err),
+                                ));
+                                continue;
+                            }
+                        },
+                        None => component,
+                    };
+                    components[index] = component;
+                    sources[index] = source.clone();
+                }
+                None => {
+                    components.push(component);
+                    sources.push(source.clone());
+                }
+            }
+        }
+
+        // === Remove === //
+        for removed in patch.removed_components() {
+            let count = components.len();
+            let mut index = 0;
+            while index < components.len() {
+                if options
+                    .registry
+                    .name_of(components[index].as_ref())
+                    .as_ref()
+                    == Some(removed)
+                {
+                    components.remove(index);
+                    sources.remove(index);
+                } else {
+                    index += 1;
+                }
+            }
+
+            if components.len() == count {
+                bevy::log::warn!(
+                    "Patch in {} removes component `{}` from prototype `{}`, which does not have it.",
+                    source,
+                    removed,
+                    name
+                );
+            }
+        }
+
+        // The patched prototype is prepared again to prepare any added components
+        self.prepare_and_insert(world, proto);
+        self.component_sources.insert(name.clone(), sources);
+        if let Some(original) = original {
+            self.sources.insert(name, original);
+        }
+    }
+
+    /// Deserializes every component declared with `merge` from its value merged with the one it
+    /// inherits, then prepares it
+    ///
+    /// This is done once all the prototypes in a batch have been added, since a prototype may be
+    /// added before its templates.
+    pub(crate) fn resolve_merges(&mut self, world: &mut World, errors: &mut ProtoLoadErrors) {
+        for (name, path, index, result) in crate::merge::resolve_all(self) {
+            let component = match result {
+                Ok(component) => Some(component),
+                Err(err) => {
+                    match self.sources.get(&name) {
+                        Some(source) => {
+                            // Skip errors that were already reported by an earlier batch
+                            let reported = errors.for_path(&source.path).any(|error| {
+                                matches!(&error.kind, ProtoLoadErrorKind::Deserialize(existing) if *existing == err)
+                            });
+                            if !reported {
+                                errors.push(ProtoLoadError::new(
+                                    source.path.clone(),
+                                    ProtoLoadErrorKind::Deserialize(err),
+                                ));
+                            }
+                        }
+                        None => bevy::log::error!("Prototype `{}` {}", name, err),
+                    }
+                    None
+                }
+            };
+
+            let proto = match self.prototypes.remove(&name) {
+                Some(proto) => proto,
+                None => continue,
+            };
+            let target = match inline_child(proto.as_ref(), &path) {
+                Some(target) => target,
+                None => {
+                    self.prototypes.insert(name, proto);
+                    continue;
+                }
+            };
+            if let Some(component) = &component {
+                component.prepare(world, target, self);
+            }
+            if let Some(merged) = target
+                .iter_components()
+                .nth(index)
+                .and_then(|component| crate::merge::as_merged(component.as_ref()))
+            {
+                merged.set_resolved(component);
+            }
+            self.prototypes.insert(name, proto);
+        }
+    }
+
+    /// Resolves every merged component (see [`ProtoData::resolve_merges`]), recording any errors
+    /// in the world's [`ProtoLoadErrors`]
+    fn resolve_merges_in(&mut self, world: &mut World) {
+        if world.contains_resource::<ProtoLoadErrors>() {
+            world.resource_scope(|world, mut errors: Mut<ProtoLoadErrors>| {
+                self.resolve_merges(world, &mut errors);
+            });
+        } else {
+            self.resolve_merges(world, &mut ProtoLoadErrors::default());
+        }
+    }
+
+    /// Synchronously loads every prototype file in the configured directories
+    /// (or the configured pack, if there is one)
+    fn load_directories(
+        &mut self,
+        world: &mut World,
+        options: &ProtoDataOptions,
+    ) -> ProtoLoadErrors {
+        #[cfg(feature = "pack")]
+        if let Some(pack) = &options.pack {
+            return self.load_pack(world, options, Path::new(pack));
+        }
+
+        let mut errors = ProtoLoadErrors::default();
+
+        let mut paths = Vec::new();
+        let mut dir_errors = Vec::new();
+        for directory in &options.directories {
+            collect_paths(options, Path::new(directory), &mut paths, &mut dir_errors);
+        }
+        for err in dir_errors {
+            errors.push(err);
+        }
+
+        for path in paths {
+            let result = read_file(options, &path);
+            register_file(world, self, &mut errors, options, path, result);
+        }
+        self.apply_patches(world, &mut errors, options, None);
+        self.resolve_merges(world, &mut errors);
+        check_tables(self, &mut errors);
+        check_entity_refs(self, &mut errors);
+
+        #[cfg(feature = "analysis")]
+        analyze_deps(self);
+
+        errors
+    }
+
+    /// Synchronously loads every prototype and table in the given [`ProtoPack`](crate::pack::ProtoPack) file
+    #[cfg(feature = "pack")]
+    fn load_pack(
+        &mut self,
+        world: &mut World,
+        options: &ProtoDataOptions,
+        path: &Path,
+    ) -> ProtoLoadErrors {
+        let mut errors = ProtoLoadErrors::default();
+
+        match crate::pack::ProtoPack::load(path, &options.registry) {
+            Ok(pack) => {
+                let (entries, tables) = pack.into_parts();
+                for (source, proto) in entries {
+                    self.insert_from_source(world, &mut errors, proto, source, options);
+                }
+                for (source, table) in tables {
+                    insert_table_from_source(self, &mut errors, options, table, source);
+                }
+                self.apply_patches(world, &mut errors, options, None);
+                self.resolve_merges(world, &mut errors);
+                check_tables(self, &mut errors);
+                check_entity_refs(self, &mut errors);
+            }
+            Err(err) => errors.push(ProtoLoadError::new(path, ProtoLoadErrorKind::Pack(err))),
+        }
+
+        #[cfg(feature = "analysis")]
+        analyze_deps(self);
+
+        errors
     }
 
     /// Store a handle
@@ -99,7 +902,8 @@ impl ProtoData {
     ///     let proto = Prototype {
     ///         name: String::from("My Prototype"),
     ///         templates: Vec::default(),
-    ///         components: vec![Box::new(comp.clone())]
+    ///         components: vec![Box::new(comp.clone())],
+    ///         ..Default::default()
     ///     };
     ///
     ///     let handle: Handle<Image> = asset_server.load(comp.texture_path.0.as_str());
@@ -113,14 +917,10 @@ impl ProtoData {
         component: &dyn ProtoComponent,
         handle: Handle<T>,
     ) {
-        let proto_map = self
-            .handles
-            .entry(prototype.name().to_string())
-            .or_insert_with(HashMap::default);
-        let comp_map = proto_map
-            .entry(component.type_id())
-            .or_insert_with(HashMap::default);
-        let path_map = comp_map.entry(handle.id()).or_insert_with(HashMap::default);
+        let key = self.handle_key(prototype).to_string();
+        let proto_map = self.handles.entry(key).or_default();
+        let comp_map = proto_map.entry(component.type_id()).or_default();
+        let path_map = comp_map.entry(handle.id()).or_default();
         path_map.insert(T::TYPE_UUID, handle.clone_untyped());
     }
 
@@ -183,7 +983,7 @@ impl ProtoData {
         id: HandleId,
         asset_type: Uuid,
     ) -> Option<&HandleUntyped> {
-        let proto_map = self.handles.get(protoytpe.name())?;
+        let proto_map = self.handles.get(self.handle_key(protoytpe))?;
         let comp_map = proto_map.get(&component.type_id())?;
         let path_map = comp_map.get(&id)?;
         path_map.get(&asset_type)
@@ -206,6 +1006,8 @@ impl ProtoData {
             commands,
             prototype,
             data: self,
+            resolved: None,
+            entities: None,
         }
     }
 
@@ -222,112 +1024,478 @@ impl ProtoData {
 
 impl FromWorld for ProtoData {
     fn from_world(world: &mut World) -> Self {
-        let mut myself = Self {
-            handles: Default::default(),
-            prototypes: HashMap::default(),
-        };
+        let mut myself = Self::empty();
 
         let options = world
             .get_resource::<ProtoDataOptions>()
             .expect("Expected options for ProtoData")
             .clone();
+        myself.registry = options.registry.clone();
+        myself.rng = world.get_resource_or_insert_with(ProtoRng::default).clone();
+        myself.mark_instances = options.marks_instances();
+        myself.name_instances = options.name_instances;
+        #[cfg(feature = "hot_reloading")]
+        {
+            myself.reload_instances = options.reload_instances;
+        }
 
-        for directory in options.directories {
-            process_path(
-                world,
-                &options.extensions,
-                options.deserializer.as_ref(),
-                &mut myself,
-                &directory,
-                options.recursive_loading,
-            );
+        if options.async_loading && !options.uses_pack() {
+            world.insert_resource(ProtoLoadTasks::new(options));
+            world.insert_resource(ProtoLoadState::Loading);
+            world.insert_resource(ProtoLoadErrors::default());
+            return myself;
         }
 
-        #[cfg(feature = "analysis")]
-        analyze_deps(&myself);
+        let errors = myself.load_directories(world, &options);
+        let state = ProtoLoadState::from_errors(&errors);
+        world.insert_resource(errors);
+        world.insert_resource(state);
+        world.send_event(ProtoDataLoaded { state });
 
         myself
     }
 }
 
-/// Helper function to populate our ProtoData.
-fn process_path(
+/// The address of the given prototype, identifying an inline child for as long as it's stored
+fn address_of(prototype: &dyn Prototypical) -> usize {
+    prototype as *const dyn Prototypical as *const () as usize
+}
+
+/// Checks whether the given components have the same `type` name (or, if they aren't named,
+/// the same concrete type)
+fn same_type(
+    registry: &ProtoComponentRegistry,
+    a: &dyn ProtoComponent,
+    b: &dyn ProtoComponent,
+) -> bool {
+    match (registry.name_of(a), registry.name_of(b)) {
+        (Some(a), Some(b)) => a == b,
+        (None, None) => {
+            let (a, b): (&dyn Any, &dyn Any) = (a, b);
+            a.type_id() == b.type_id()
+        }
+        _ => false,
+    }
+}
+
+/// The prototypes read from a single file, or the error that prevented it from being read.
+pub(crate) type PathResult =
+    Result<Vec<Result<Box<dyn Prototypical>, ProtoDeserializeError>>, std::io::Error>;
+
+/// Helper function to find every prototype file within the given directory.
+///
+/// Any directory that cannot be read is recorded in `errors`.
+pub(crate) fn collect_paths(
+    options: &ProtoDataOptions,
+    directory: &Path,
+    paths: &mut Vec<PathBuf>,
+    errors: &mut Vec<ProtoLoadError>,
+) {
+    let dir = match std::fs::read_dir(directory) {
+        Ok(dir) => dir,
+        Err(err) => {
+            errors.push(ProtoLoadError::new(directory, ProtoLoadErrorKind::Io(err)));
+            return;
+        }
+    };
+
+    // Sort the entries so that files are always loaded in the same order
+    let mut entries = dir
+        .filter_map(|file_info| file_info.ok())
+        .map(|file_info| file_info.path())
+        .collect::<Vec<_>>();
+    entries.sort();
+
+    for path in entries {
+        if path.is_dir() {
+            if options.recursive_loading {
+                collect_paths(options, &path, paths, errors);
+            }
+            continue;
+        }
+
+        if options.matches_extension(&path) {
+            paths.push(path);
+        }
+    }
+}
+
+/// Helper function to read and deserialize the prototypes in a single file.
+pub(crate) fn read_path(options: &ProtoDataOptions, path: &Path) -> PathResult {
+    let data = std::fs::read_to_string(path)?;
+    let mut results = options.deserialize_file(path, &data);
+    options.identify(path, &mut results);
+    Ok(results)
+}
+
+/// Helper function to populate our ProtoData with the prototypes read from a single file.
+///
+/// Any prototype that failed to load is recorded in `errors`.
+pub(crate) fn register_path(
     world: &mut World,
-    extensions: &Option<Vec<&str>>,
-    deserializer: &(dyn ProtoDeserializer + Send + Sync),
     myself: &mut ProtoData,
-    directory: &str,
-    recursive: bool,
+    errors: &mut ProtoLoadErrors,
+    options: &ProtoDataOptions,
+    path: PathBuf,
+    result: PathResult,
 ) {
-    if let Ok(dir) = std::fs::read_dir(directory) {
-        for file_info in dir {
-            if file_info.is_err() {
-                continue;
+    let results = match result {
+        Ok(results) => results,
+        Err(err) => {
+            errors.push(ProtoLoadError::new(path, ProtoLoadErrorKind::Io(err)));
+            return;
+        }
+    };
+
+    let layer = options.layer_of(&path);
+    for (document, result) in results.into_iter().enumerate() {
+        match result {
+            Ok(proto) => {
+                let source = ProtoSource::new(path.clone(), document).with_layer(layer);
+                myself.insert_from_source(world, errors, proto, source, options);
             }
-            let file_info = file_info.unwrap();
-
-            let path = file_info.path();
-
-            if recursive && path.is_dir() {
-                process_path(
-                    world,
-                    extensions,
-                    deserializer,
-                    myself,
-                    &path.to_str().unwrap().to_string(),
-                    recursive,
-                );
+            Err(err) => {
+                errors.push(ProtoLoadError::new(
+                    path.clone(),
+                    ProtoLoadErrorKind::Deserialize(err),
+                ));
             }
+        }
+    }
+}
 
-            if let Some(filters) = &extensions {
-                if let Some(ext) = path.extension().and_then(OsStr::to_str) {
-                    if !filters.iter().any(|filter| filter == &ext) {
-                        continue;
-                    }
-                }
+/// The tables read from a single file, or the error that prevented it from being read.
+pub(crate) type TablesResult =
+    Result<Vec<Result<ProtoTable, ProtoDeserializeError>>, std::io::Error>;
+
+/// The contents of a single file in the prototype directories.
+pub(crate) enum FileResult {
+    /// A prototype file.
+    Prototypes(PathResult),
+    /// A [`ProtoTable`] file (such as `goblin_loot.table.yaml`).
+    Tables(TablesResult),
+}
+
+/// Helper function to read either the prototypes or the tables in a single file,
+/// depending on its name.
+pub(crate) fn read_file(options: &ProtoDataOptions, path: &Path) -> FileResult {
+    if is_table_path(path) {
+        FileResult::Tables(read_tables(options, path))
+    } else {
+        FileResult::Prototypes(read_path(options, path))
+    }
+}
+
+/// Helper function to populate our ProtoData with the contents of a single file.
+pub(crate) fn register_file(
+    world: &mut World,
+    myself: &mut ProtoData,
+    errors: &mut ProtoLoadErrors,
+    options: &ProtoDataOptions,
+    path: PathBuf,
+    result: FileResult,
+) {
+    match result {
+        FileResult::Prototypes(result) => {
+            register_path(world, myself, errors, options, path, result)
+        }
+        FileResult::Tables(result) => register_tables(myself, errors, options, path, result),
+    }
+}
+
+/// Helper function to read and deserialize the tables in a single file.
+///
+/// A table without a name is named after the file, if it's the only table in it.
+pub(crate) fn read_tables(options: &ProtoDataOptions, path: &Path) -> TablesResult {
+    let data = std::fs::read_to_string(path)?;
+    let mut results = options.deserializer_for(path).deserialize_tables(&data);
+    let single = results.len() == 1;
+    for result in results.iter_mut() {
+        match result {
+            Ok(table) if table.name.is_empty() && single => table.name = table_name(path),
+            Ok(table) if table.name.is_empty() => {
+                *result = Err(ProtoDeserializeError::new(
+                    "table is missing a `name` (required in files containing several tables)",
+                ));
             }
+            _ => {}
+        }
+    }
+    Ok(results)
+}
 
-            if let Ok(data) = std::fs::read_to_string(path) {
-                if let Some(proto) = deserializer.deserialize(&data) {
-                    for component in proto.iter_components() {
-                        component.prepare(world, proto.as_ref(), myself);
-                    }
+/// Helper function to populate our ProtoData with the tables read from a single file.
+///
+/// Duplicate tables follow the [`DuplicatePolicy`] in the same way as prototypes. Any table that
+/// failed to load is recorded in `errors`.
+pub(crate) fn register_tables(
+    myself: &mut ProtoData,
+    errors: &mut ProtoLoadErrors,
+    options: &ProtoDataOptions,
+    path: PathBuf,
+    result: TablesResult,
+) {
+    let results = match result {
+        Ok(results) => results,
+        Err(err) => {
+            errors.push(ProtoLoadError::new(path, ProtoLoadErrorKind::Io(err)));
+            return;
+        }
+    };
 
-                    myself.prototypes.insert(proto.name().to_string(), proto);
-                }
+    let layer = options.layer_of(&path);
+    for (document, result) in results.into_iter().enumerate() {
+        let table = match result {
+            Ok(table) => table,
+            Err(err) => {
+                errors.push(ProtoLoadError::new(
+                    path.clone(),
+                    ProtoLoadErrorKind::Deserialize(err),
+                ));
+                continue;
+            }
+        };
+
+        let source = ProtoSource::new(path.clone(), document).with_layer(layer);
+        insert_table_from_source(myself, errors, options, table, source);
+    }
+}
+
+/// Helper function to add a single table loaded from the given source, following the
+/// [`DuplicatePolicy`] (or layers) for tables that were already loaded.
+pub(crate) fn insert_table_from_source(
+    myself: &mut ProtoData,
+    errors: &mut ProtoLoadErrors,
+    options: &ProtoDataOptions,
+    table: ProtoTable,
+    source: ProtoSource,
+) {
+    let overrides_layer = options.layered
+        && myself
+            .table_sources
+            .get(&table.name)
+            .is_some_and(|original| original.layer < source.layer);
+
+    if let Some(original) = myself
+        .table_sources
+        .get(&table.name)
+        .filter(|_| !overrides_layer)
+    {
+        match options.duplicate_policy {
+            DuplicatePolicy::Error => {
+                let reason = format!("was already defined in {}", original);
+                errors.push(ProtoLoadError::new(
+                    source.path,
+                    ProtoLoadErrorKind::InvalidTable {
+                        table: table.name,
+                        reason,
+                    },
+                ));
+                return;
             }
+            DuplicatePolicy::WarnKeepFirst => {
+                bevy::log::warn!(
+                    "Table `{}` in {} was already defined in {}. Keeping the original.",
+                    table.name,
+                    source,
+                    original
+                );
+                return;
+            }
+            DuplicatePolicy::WarnKeepLast => {
+                bevy::log::warn!(
+                    "Table `{}` in {} was already defined in {}. Replacing the original.",
+                    table.name,
+                    source,
+                    original
+                );
+            }
+            DuplicatePolicy::Override => {}
         }
     }
+
+    myself.table_sources.insert(table.name.clone(), source);
+    myself.tables.insert(table.name.clone(), table);
+}
+
+/// Checks every table once all prototypes and tables have been loaded, recording any
+/// problems in `errors`
+pub(crate) fn check_tables(data: &ProtoData, errors: &mut ProtoLoadErrors) {
+    for error in table_errors(data) {
+        errors.push(error);
+    }
+}
+
+/// Checks every entity reference once all prototypes have been loaded (and merged), recording
+/// any that can't be resolved in `errors`
+pub(crate) fn check_entity_refs(data: &ProtoData, errors: &mut ProtoLoadErrors) {
+    for error in entity_ref_errors(data) {
+        errors.push(error);
+    }
+}
+
+/// Finds every error that [`check_tables`] and [`check_entity_refs`] would record
+pub(crate) fn check_errors(data: &ProtoData) -> Vec<ProtoLoadError> {
+    table_errors(data).chain(entity_ref_errors(data)).collect()
+}
+
+fn table_errors(data: &ProtoData) -> impl Iterator<Item = ProtoLoadError> + '_ {
+    find_invalid_tables(data)
+        .into_iter()
+        .map(|(table, reason)| {
+            let path = data
+                .table_sources
+                .get(table)
+                .map(|source| source.path.clone())
+                .unwrap_or_default();
+            ProtoLoadError::new(
+                path,
+                ProtoLoadErrorKind::InvalidTable {
+                    table: table.to_string(),
+                    reason,
+                },
+            )
+        })
+}
+
+fn entity_ref_errors(data: &ProtoData) -> impl Iterator<Item = ProtoLoadError> + '_ {
+    find_missing_refs(data)
+        .into_iter()
+        .map(|(prototype, local)| {
+            let path = data
+                .sources
+                .get(prototype)
+                .map(|source| source.path.clone())
+                .unwrap_or_default();
+            ProtoLoadError::new(
+                path,
+                ProtoLoadErrorKind::MissingEntityRef {
+                    prototype: prototype.to_string(),
+                    local,
+                },
+            )
+        })
 }
 
 /// Performs some analysis on the given [`ProtoData`] resource
-fn analyze_deps(data: &ProtoData) {
+#[cfg(feature = "analysis")]
+// With `no_cycles`, the first cycle found panics
+#[cfg_attr(feature = "no_cycles", allow(clippy::never_loop))]
+pub(crate) fn analyze_deps(data: &ProtoData) {
+    use crate::utils::handle_cycle;
+
+    for (template, traversed) in find_cycles(data) {
+        let traversed = &traversed;
+        handle_cycle!(template, traversed, data);
+    }
+
+    for (name, excluded) in find_unused_exclusions(data) {
+        bevy::log::warn!(
+            "Prototype {} excludes `{}`, but none of its templates provide that component",
+            data.describe(name),
+            excluded
+        );
+    }
+}
+
+/// Finds every circular dependency in the template hierarchies of the given [`ProtoData`]
+///
+/// returns: Vec<(&str, IndexSet<&str>)> containing the offending template of each cycle
+/// along with the prototypes traversed to reach it
+#[cfg(any(feature = "analysis", feature = "validate"))]
+pub(crate) fn find_cycles(data: &ProtoData) -> Vec<(&str, IndexSet<&str>)> {
+    let mut cycles = Vec::new();
+
     // === Perform Analysis === //
     for proto in data.iter() {
-        check_for_cycles(proto.as_ref(), data, &mut IndexSet::default());
+        check_for_cycles(proto.as_ref(), data, &mut IndexSet::default(), &mut cycles);
     }
 
+    return cycles;
+
     // === Analysis Functions === //
     fn check_for_cycles<'a>(
         proto: &'a dyn Prototypical,
         data: &'a ProtoData,
         traversed: &mut IndexSet<&'a str>,
+        cycles: &mut Vec<(&'a str, IndexSet<&'a str>)>,
     ) {
         traversed.insert(proto.name());
 
         for template in proto.templates_rev() {
             if traversed.contains(template.as_str()) {
                 // ! --- Found Circular Dependency --- ! //
-                handle_cycle!(template, traversed);
+                cycles.push((template.as_str(), traversed.clone()));
 
                 continue;
             }
 
             if let Some(parent) = data.get_prototype(template) {
                 // --- Check Template --- //
-                check_for_cycles(parent, data, traversed);
+                check_for_cycles(parent, data, traversed, cycles);
+            }
+        }
+    }
+}
+
+/// Finds every excluded component which isn't provided by any of the excluding prototype's templates
+///
+/// returns: Vec<(&str, &str)> containing the name of each prototype along with its unused exclusion
+#[cfg(any(feature = "analysis", feature = "validate"))]
+pub(crate) fn find_unused_exclusions(data: &ProtoData) -> Vec<(&str, &str)> {
+    let mut unused = Vec::new();
+
+    // === Perform Analysis === //
+    for proto in data.iter() {
+        if proto.excluded_components().is_empty() {
+            continue;
+        }
+
+        let mut traversed = IndexSet::default();
+        traversed.insert(proto.name());
+        let inherited = inherited_components(proto.as_ref(), data, &mut traversed);
+
+        for excluded in proto.excluded_components() {
+            if !inherited.contains(excluded) {
+                unused.push((proto.name(), excluded.as_str()));
+            }
+        }
+    }
+
+    return unused;
+
+    // === Analysis Functions === //
+    /// The `type` names of every component the given prototype inherits from its templates
+    fn inherited_components<'a>(
+        proto: &'a dyn Prototypical,
+        data: &'a ProtoData,
+        traversed: &mut IndexSet<&'a str>,
+    ) -> HashSet<String> {
+        let mut inherited = HashSet::default();
+
+        for template in proto.templates_rev() {
+            if traversed.contains(template.as_str()) {
+                // Cycles are reported separately
+                continue;
+            }
+
+            if let Some(parent) = data.get_prototype(template) {
+                traversed.insert(parent.name());
+                let mut provided = inherited_components(parent, data, traversed);
+                traversed.pop();
+
+                // A template's own exclusions only apply to what it inherits
+                provided.retain(|name| !parent.excluded_components().contains(name));
+                provided.extend(
+                    parent
+                        .iter_components()
+                        .filter_map(|component| data.registry.name_of(component.as_ref())),
+                );
+                inherited.extend(provided);
             }
         }
+
+        inherited
     }
 }
 
@@ -341,6 +1509,10 @@ pub struct ProtoCommands<'w, 's, 'a, 'p> {
     prototype: &'p dyn Prototypical,
     /// The [`ProtoData`] resource
     data: &'p ProtoData,
+    /// The components resolved from the prototype's params when spawning (if any)
+    pub(crate) resolved: Option<&'p ResolvedParams>,
+    /// The entities spawned along with the prototype, by their local name (if any)
+    pub(crate) entities: Option<&'p ProtoEntityMap>,
 }
 
 impl<'w, 's, 'a, 'p> ProtoCommands<'w, 's, 'a, 'p> {
@@ -358,6 +1530,31 @@ impl<'w, 's, 'a, 'p> ProtoCommands<'w, 's, 'a, 'p> {
         self.data
     }
 
+    /// Get the components resolved from the prototype's params when spawning (if any)
+    pub(crate) fn resolved_params(&self) -> Option<&'p ResolvedParams> {
+        self.resolved
+    }
+
+    /// Get the entities spawned along with the prototype, by their local name
+    ///
+    /// This is `None` for commands which aren't part of spawning a prototype's hierarchy (such as
+    /// those from [`Prototypical::create_commands`]).
+    pub fn entity_map(&self) -> Option<&'p ProtoEntityMap> {
+        self.entities
+    }
+
+    /// Resolves the [`ProtoEntityRef`](crate::ProtoEntityRef)s within the given value to the
+    /// entities spawned along with the prototype
+    ///
+    /// This should be called on a component before inserting it (as the derived
+    /// [`ProtoComponent::insert_self`] does). Without an [`entity_map`](Self::entity_map), the
+    /// references are left as they are.
+    pub fn map_entity_refs<T: MapEntityRefs + ?Sized>(&self, value: &mut T) {
+        if let Some(entities) = self.entities {
+            value.map_entity_refs(entities);
+        }
+    }
+
     /// Get a cloned handle
     ///
     /// # Arguments
@@ -437,23 +1634,72 @@ pub trait ProtoDeserializer: DynClone {
     /// # Arguments
     ///
     /// * `data`: The file data as a string
+    /// * `registry`: The registry of the components the prototype may contain
     ///
-    /// returns: Option<Box<dyn Prototypical, Global>>
+    /// returns: Result<Box<dyn Prototypical, Global>, ProtoDeserializeError>
     ///
     /// # Examples
     ///
     /// ```
-    /// // The default implementation:
-    /// use bevy_proto::{Prototype, Prototypical};
-    /// fn example_deserialize(data: &str) -> Option<Box<dyn Prototypical>> {
-    ///     if let Ok(value) = serde_yaml::from_str::<Prototype>(data) {
-    ///         Some(Box::new(value))
-    ///     } else {
-    ///         None
-    ///    }
+    /// // A minimal implementation:
+    /// use bevy_proto::{ProtoComponentRegistry, PrototypeSeed, Prototypical};
+    /// use bevy_proto::errors::ProtoDeserializeError;
+    /// use serde::de::DeserializeSeed;
+    /// fn example_deserialize(
+    ///     data: &str,
+    ///     registry: &ProtoComponentRegistry,
+    /// ) -> Result<Box<dyn Prototypical>, ProtoDeserializeError> {
+    ///     let deserializer = serde_yaml::Deserializer::from_str(data);
+    ///     let value = PrototypeSeed::new(registry).deserialize(deserializer)?;
+    ///     Ok(Box::new(value))
     /// }
     /// ```
-    fn deserialize(&self, data: &str) -> Option<Box<dyn Prototypical>>;
+    fn deserialize(
+        &self,
+        data: &str,
+        registry: &ProtoComponentRegistry,
+    ) -> Result<Box<dyn Prototypical>, ProtoDeserializeError>;
+
+    /// Deserializes file input (as a string) that may contain any number of [`Prototypical`] objects
+    ///
+    /// Each entry in the returned list is either a successfully deserialized prototype or the error
+    /// that prevented one from being deserialized, so that a single malformed entry does not
+    /// prevent the rest of the file from loading.
+    ///
+    /// By default, this expects the entire input to be a single prototype and simply defers to
+    /// [`deserialize`](ProtoDeserializer::deserialize).
+    ///
+    /// # Arguments
+    ///
+    /// * `data`: The file data as a string
+    /// * `registry`: The registry of the components the prototypes may contain
+    ///
+    /// returns: Vec<Result<Box<dyn Prototypical, Global>, ProtoDeserializeError>>
+    fn deserialize_many(
+        &self,
+        data: &str,
+        registry: &ProtoComponentRegistry,
+    ) -> Vec<Result<Box<dyn Prototypical>, ProtoDeserializeError>> {
+        vec![self.deserialize(data, registry)]
+    }
+
+    /// Deserializes file input (as a string) that may contain any number of [`ProtoTable`] objects
+    ///
+    /// As with [`deserialize_many`](ProtoDeserializer::deserialize_many), each entry in the
+    /// returned list is either a successfully deserialized table or the error that prevented one
+    /// from being deserialized.
+    ///
+    /// By default, this reads the input as YAML (which also covers JSON), with each document
+    /// containing either a single table or a list of them.
+    ///
+    /// # Arguments
+    ///
+    /// * `data`: The file data as a string
+    ///
+    /// returns: Vec<Result<ProtoTable, ProtoDeserializeError>>
+    fn deserialize_tables(&self, data: &str) -> Vec<Result<ProtoTable, ProtoDeserializeError>> {
+        crate::deserializers::deserialize_yaml_tables(data)
+    }
 }
 
 dyn_clone::clone_trait_object!(ProtoDeserializer);
@@ -462,6 +1708,11 @@ dyn_clone::clone_trait_object!(ProtoDeserializer);
 #[derive(Clone, Resource)]
 pub struct ProtoDataOptions {
     /// Directories containing prototype data.
+    ///
+    /// These are read directly from the filesystem, rather than through the
+    /// [`AssetServer`](bevy::asset::AssetServer). On platforms without one (such as wasm or
+    /// Android), or to use a custom asset source, load prototype files as assets instead (see
+    /// [`asset_extensions`](Self::asset_extensions)).
     pub directories: Vec<String>,
     /// Whether to load files recursively from the specified top-level directories.
     ///
@@ -480,7 +1731,28 @@ pub struct ProtoDataOptions {
     /// ```
     pub recursive_loading: bool,
     /// A custom deserializer for prototypes.
+    ///
+    /// This is used for any file whose extension has no entry in [`deserializers`](Self::deserializers).
     pub deserializer: Box<dyn ProtoDeserializer + Send + Sync>,
+    /// Deserializers for specific file extensions. These do __not__ have a dot ('.') prepended to them.
+    ///
+    /// By default, this contains a deserializer for every format enabled through cargo features
+    /// (`json`, `ron`, and `toml`).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bevy_proto::data::ProtoDataOptions;
+    /// use bevy_proto::deserializers::YamlProtoDeserializer;
+    ///
+    /// let mut opts = ProtoDataOptions {
+    ///     extensions: Some(vec!["yaml", "yml"]),
+    ///     ..Default::default()
+    /// };
+    /// // Also read `.yml` files as YAML
+    /// opts.deserializers.insert("yml", Box::new(YamlProtoDeserializer));
+    /// ```
+    pub deserializers: HashMap<&'static str, Box<dyn ProtoDeserializer + Send + Sync>>,
     /// An optional collection of file extensions to filter prototypes. These do __not__
     /// have a dot ('.') prepended to them.
     ///
@@ -498,6 +1770,225 @@ pub struct ProtoDataOptions {
     /// };
     /// ```
     pub extensions: Option<Vec<&'static str>>,
+    /// Whether to load prototype files in the background rather than while the plugin is built.
+    ///
+    /// When enabled, [`ProtoData`] starts out empty and is filled as files finish loading. Check the
+    /// [`ProtoLoadState`] resource or listen for [`ProtoDataLoaded`] to know when loading has finished.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bevy_proto::data::ProtoDataOptions;
+    ///
+    /// let opts = ProtoDataOptions {
+    ///     directories: vec![String::from("assets/prototypes")],
+    ///     async_loading: true,
+    ///     ..Default::default()
+    /// };
+    /// ```
+    pub async_loading: bool,
+    /// The extensions of the files the [`AssetServer`](bevy::asset::AssetServer) loads as a
+    /// [`PrototypeAsset`](crate::PrototypeAsset). These do __not__ have a dot ('.') prepended to them.
+    ///
+    /// This is empty by default, so loading prototypes as assets is opt-in: until an extension is
+    /// added here, no [`PrototypeAsset`](crate::PrototypeAsset) is ever loaded, and the app's other
+    /// `.yaml` or `.json` assets aren't claimed as prototypes. Dedicated extensions (such as
+    /// `proto.yaml`) keep the two apart, and are read with the deserializer for their last part
+    /// (`yaml`), and the whole extension is left out when naming a prototype after its file.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bevy_proto::data::ProtoDataOptions;
+    ///
+    /// let opts = ProtoDataOptions {
+    ///     // Allows loading `level_1.proto.yaml` as a `PrototypeAsset`
+    ///     asset_extensions: vec!["proto.yaml", "proto.json"],
+    ///     ..Default::default()
+    /// };
+    /// ```
+    pub asset_extensions: Vec<&'static str>,
+    /// How to handle prototypes loaded from files that share a name with a previously loaded prototype.
+    ///
+    /// Directories are loaded in the order they are listed, and the files within each directory
+    /// are loaded in alphabetical order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bevy_proto::data::{DuplicatePolicy, ProtoDataOptions};
+    ///
+    /// // Allow mods to replace prototypes from the base game
+    /// let opts = ProtoDataOptions {
+    ///     directories: vec![String::from("assets/prototypes"), String::from("mods")],
+    ///     duplicate_policy: DuplicatePolicy::Override,
+    ///     ..Default::default()
+    /// };
+    /// ```
+    pub duplicate_policy: DuplicatePolicy,
+    /// Whether to identify prototypes loaded from files by their path rather than by name alone.
+    ///
+    /// When enabled, a prototype's ID is made from the path of its file (relative to the configured
+    /// directory containing it) without the extension. For example, the prototype in
+    /// `assets/prototypes/templates/npc.yaml` is identified as `templates/npc`. Files containing
+    /// several prototypes instead append each prototype's name, such as `items/weapons/Sword`.
+    ///
+    /// Template references starting with `./` or `../` are resolved relative to the referencing
+    /// file's directory, while all others are treated as fully qualified IDs
+    /// (see [`resolve_template`](crate::resolve_template)).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bevy_proto::data::ProtoDataOptions;
+    ///
+    /// let opts = ProtoDataOptions {
+    ///     directories: vec![String::from("assets/prototypes")],
+    ///     recursive_loading: true,
+    ///     namespacing: true,
+    ///     ..Default::default()
+    /// };
+    /// ```
+    pub namespacing: bool,
+    /// Whether to treat each of the [`directories`](Self::directories) as a separate layer, such as
+    /// the base game followed by each of its mods.
+    ///
+    /// When enabled, a prototype from a later layer silently replaces one with the same name from an
+    /// earlier layer. Prototypes sharing a name within the same layer still follow the
+    /// [`duplicate_policy`](Self::duplicate_policy).
+    ///
+    /// Regardless of this setting, a layer may also [patch](crate::Prototype::patch) the prototypes of
+    /// earlier layers (or its own) rather than replacing them outright. The layer that contributed each
+    /// component can be found with [`ProtoData::component_sources`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bevy_proto::data::ProtoDataOptions;
+    ///
+    /// let opts = ProtoDataOptions {
+    ///     // Mods are loaded after (and override) the base game
+    ///     directories: vec![
+    ///         String::from("assets/prototypes"),
+    ///         String::from("mods/tougher_goblins"),
+    ///     ],
+    ///     layered: true,
+    ///     ..Default::default()
+    /// };
+    /// ```
+    pub layered: bool,
+    /// A [`ProtoPack`](crate::pack::ProtoPack) file to load the prototypes and tables from, instead
+    /// of the files in [`directories`](Self::directories).
+    ///
+    /// Packs are always loaded synchronously, regardless of [`async_loading`](Self::async_loading).
+    ///
+    /// This requires the `pack` feature.
+    #[cfg(feature = "pack")]
+    pub pack: Option<String>,
+    /// The components that can be used by prototypes, in addition to those registered with `typetag`.
+    ///
+    /// When added with the [`ProtoPlugin`](crate::ProtoPlugin), this is combined with the app's
+    /// [`ProtoComponentRegistry`] resource, which is filled by
+    /// [`register_proto_component`](crate::RegisterProtoComponent::register_proto_component).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bevy::prelude::*;
+    /// use bevy_proto::prelude::*;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Clone, Serialize, Deserialize, ProtoComponent, Component)]
+    /// struct Health(u16);
+    ///
+    /// let opts = ProtoDataOptions::default();
+    /// opts.registry.register::<Health>("Health");
+    /// ```
+    pub registry: ProtoComponentRegistry,
+    /// Whether to insert a [`ProtoInstance`](crate::ProtoInstance) into each entity spawned from a
+    /// prototype, recording which prototype it came from.
+    ///
+    /// This also keeps the [`ProtoInstances`](crate::ProtoInstances) index up to date.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bevy_proto::data::ProtoDataOptions;
+    ///
+    /// let opts = ProtoDataOptions {
+    ///     directories: vec![String::from("assets/prototypes")],
+    ///     mark_instances: true,
+    ///     name_instances: true,
+    ///     ..Default::default()
+    /// };
+    /// ```
+    pub mark_instances: bool,
+    /// Whether to insert a Bevy `Name` (the name of the prototype) into each entity spawned from a
+    /// prototype, such as for inspectors and debugging.
+    ///
+    /// A `Name` given by the prototype's own components takes precedence.
+    pub name_instances: bool,
+    /// Whether hot reloading a prototype also updates the live entities spawned from it (or from
+    /// any prototype using it as a template).
+    ///
+    /// Each such entity has the new set of components inserted, and the components its prototype
+    /// no longer declares removed (see [`ProtoComponent::remove_self`]). Components are inserted
+    /// with the default params, and children aren't respawned. Any
+    /// [`ProtoEntityRef`](crate::ProtoEntityRef) resolves to the same entity it did when spawned
+    /// (through the [`ProtoChildren`](crate::ProtoChildren) of the entity the hierarchy was spawned
+    /// into). Entities are found through their
+    /// [`ProtoInstance`](crate::ProtoInstance), so this also enables
+    /// [`mark_instances`](Self::mark_instances).
+    ///
+    /// This requires the `hot_reloading` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bevy_proto::data::ProtoDataOptions;
+    ///
+    /// let opts = ProtoDataOptions {
+    ///     directories: vec![String::from("assets/prototypes")],
+    ///     reload_instances: true,
+    ///     // Keep the current health of everything already spawned
+    ///     preserve_on_reload: vec![String::from("Health")],
+    ///     ..Default::default()
+    /// };
+    /// ```
+    #[cfg(feature = "hot_reloading")]
+    pub reload_instances: bool,
+    /// The `type` names of the components which live entities keep as they are when their prototype
+    /// is reloaded (see [`reload_instances`](Self::reload_instances)), such as runtime state like
+    /// current health.
+    ///
+    /// These components are neither inserted nor removed by a reload.
+    ///
+    /// This requires the `hot_reloading` feature.
+    #[cfg(feature = "hot_reloading")]
+    pub preserve_on_reload: Vec<String>,
+}
+
+/// Describes how to handle a prototype whose name is already used by a prototype from another file.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum DuplicatePolicy {
+    /// Record an error in [`ProtoLoadErrors`] and keep the original prototype.
+    Error,
+    /// Log a warning and keep the original prototype.
+    WarnKeepFirst,
+    /// Log a warning and replace the original prototype.
+    #[default]
+    WarnKeepLast,
+    /// Silently replace the original prototype.
+    ///
+    /// This is intended for setups where overriding prototypes is deliberate, such as mods.
+    Override,
+}
+
+impl DuplicatePolicy {
+    /// Returns true if a duplicate prototype replaces the original
+    pub fn replaces_original(&self) -> bool {
+        matches!(self, Self::WarnKeepLast | Self::Override)
+    }
 }
 
 impl Default for ProtoDataOptions {
@@ -505,8 +1996,612 @@ impl Default for ProtoDataOptions {
         Self {
             directories: Default::default(),
             recursive_loading: Default::default(),
-            deserializer: Box::new(DefaultProtoDeserializer),
+            deserializer: Box::new(YamlProtoDeserializer),
+            deserializers: default_deserializers(),
             extensions: Default::default(),
+            async_loading: false,
+            asset_extensions: Vec::new(),
+            duplicate_policy: DuplicatePolicy::default(),
+            namespacing: false,
+            layered: false,
+            #[cfg(feature = "pack")]
+            pack: None,
+            registry: ProtoComponentRegistry::default(),
+            mark_instances: false,
+            name_instances: false,
+            #[cfg(feature = "hot_reloading")]
+            reload_instances: false,
+            #[cfg(feature = "hot_reloading")]
+            preserve_on_reload: Vec::new(),
+        }
+    }
+}
+
+impl ProtoDataOptions {
+    /// Returns true if spawned entities are marked with a [`ProtoInstance`](crate::ProtoInstance)
+    /// (either for their own sake or so they can be reloaded)
+    pub(crate) fn marks_instances(&self) -> bool {
+        #[cfg(feature = "hot_reloading")]
+        if self.reload_instances {
+            return true;
+        }
+        self.mark_instances
+    }
+
+    /// Get the deserializer used for the file at the given path
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: The path of the prototype file
+    ///
+    /// returns: &dyn ProtoDeserializer
+    pub fn deserializer_for(&self, path: &Path) -> &(dyn ProtoDeserializer + Send + Sync) {
+        match path.extension().and_then(OsStr::to_str) {
+            Some(ext) => self.deserializer_for_extension(ext),
+            None => self.deserializer.as_ref(),
+        }
+    }
+
+    /// Deserializes the given file data with the deserializer for the file's extension,
+    /// using this [`registry`](Self::registry)
+    pub(crate) fn deserialize_file(
+        &self,
+        path: &Path,
+        data: &str,
+    ) -> Vec<Result<Box<dyn Prototypical>, ProtoDeserializeError>> {
+        self.deserializer_for(path)
+            .deserialize_many(data, &self.registry)
+    }
+
+    /// Get the deserializer used for files with the given extension
+    ///
+    /// # Arguments
+    ///
+    /// * `extension`: The file extension, without a dot ('.') prepended to it
+    ///
+    /// returns: &dyn ProtoDeserializer
+    pub fn deserializer_for_extension(
+        &self,
+        extension: &str,
+    ) -> &(dyn ProtoDeserializer + Send + Sync) {
+        self.deserializers
+            .get(extension)
+            .unwrap_or(&self.deserializer)
+            .as_ref()
+    }
+
+    /// Returns true if the prototypes are loaded from a [`ProtoPack`](crate::pack::ProtoPack)
+    pub(crate) fn uses_pack(&self) -> bool {
+        #[cfg(feature = "pack")]
+        return self.pack.is_some();
+        #[cfg(not(feature = "pack"))]
+        false
+    }
+
+    /// Names the prototypes read from the file at the given path
+    ///
+    /// A prototype without a name is named after the file stem if it's the only prototype in the
+    /// file, while one among several is replaced with an error (as they would all share the same
+    /// name). If [`namespacing`](Self::namespacing) is enabled, every prototype is also given its
+    /// path-qualified ID and has its relative template references resolved.
+    pub(crate) fn identify(
+        &self,
+        path: &Path,
+        results: &mut [Result<Box<dyn Prototypical>, ProtoDeserializeError>],
+    ) {
+        let stem = self
+            .without_extension(path)
+            .file_name()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let single = results.len() == 1;
+        let file_id = if self.namespacing {
+            Some(self.file_id(path))
+        } else {
+            None
+        };
+
+        for result in results.iter_mut() {
+            if matches!(result, Ok(proto) if proto.name().is_empty() && !single) {
+                *result = Err(ProtoDeserializeError::new(
+                    "prototype is missing a `name` (required in files containing several prototypes)",
+                ));
+            }
+
+            let proto = match result {
+                Ok(proto) => proto,
+                Err(_) => continue,
+            };
+            if proto.name().is_empty() {
+                proto.rename(stem.clone());
+            }
+
+            let file_id = match &file_id {
+                Some(file_id) => file_id,
+                None => continue,
+            };
+
+            let id = if single {
+                file_id.clone()
+            } else {
+                format!("{}/{}", file_id, proto.name())
+            };
+            proto.rename(id);
+
+            let directory = file_id.rsplit_once('/').map_or("", |(dir, _)| dir);
+            if let Some(templates) = proto.templates_mut() {
+                for template in templates.iter_mut() {
+                    *template = resolve_template(template, directory);
+                }
+            }
+            if let Some(target) = proto.patch_mut() {
+                *target = resolve_template(target, directory);
+            }
+            if let Some(children) = proto.children_mut() {
+                resolve_children(children, directory);
+            }
+        }
+
+        /// Resolves the references made by the given children (and their own children)
+        fn resolve_children(children: &mut [ProtoChild], directory: &str) {
+            for child in children {
+                match child {
+                    ProtoChild::Reference(name) => *name = resolve_template(name, directory),
+                    ProtoChild::Inline(proto) => {
+                        for template in &mut proto.templates {
+                            *template = resolve_template(template, directory);
+                        }
+                        resolve_children(&mut proto.children, directory);
+                    }
+                }
+            }
+        }
+    }
+
+    /// The ID of the file at the given path, used as the base of its prototypes' IDs
+    ///
+    /// This is the path relative to the innermost configured directory containing it
+    /// (or the whole path if there is none), without its extension.
+    fn file_id(&self, path: &Path) -> String {
+        let relative = self
+            .locate(path)
+            .map_or_else(|| path.to_path_buf(), |(_, relative)| relative);
+
+        self.without_extension(&relative)
+            .components()
+            .filter_map(|component| match component {
+                Component::Normal(segment) => Some(segment.to_string_lossy()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    /// The given path without its extension
+    ///
+    /// This removes the whole of any matching [`asset_extensions`](Self::asset_extensions)
+    /// (such as `proto.yaml`), or the last extension otherwise.
+    fn without_extension(&self, path: &Path) -> PathBuf {
+        let name = path.file_name().map(OsStr::to_string_lossy);
+        let stripped = self.asset_extensions.iter().find_map(|extension| {
+            let name = name
+                .as_deref()?
+                .strip_suffix(extension)?
+                .strip_suffix('.')?;
+            (!name.is_empty()).then(|| path.with_file_name(name))
+        });
+        stripped.unwrap_or_else(|| path.with_extension(""))
+    }
+
+    /// The layer of the file at the given path
+    ///
+    /// This is the index of the innermost configured directory containing it (or `0` if there is none).
+    pub(crate) fn layer_of(&self, path: &Path) -> usize {
+        self.locate(path).map_or(0, |(layer, _)| layer)
+    }
+
+    /// Returns true if the file at the given path passes the [`extensions`](Self::extensions) filter
+    pub(crate) fn matches_extension(&self, path: &Path) -> bool {
+        match (&self.extensions, path.extension().and_then(OsStr::to_str)) {
+            (Some(filters), Some(ext)) => filters.iter().any(|filter| filter == &ext),
+            _ => true,
+        }
+    }
+
+    /// Get the given path (such as one reported by a file watcher) as it would be found when
+    /// loading the configured directories, if it would be loaded at all
+    #[cfg(feature = "hot_reloading")]
+    pub(crate) fn loadable_path(&self, path: &Path) -> Option<PathBuf> {
+        // Removed files can't be canonicalized, but the directory containing them usually can
+        let canonical = path.canonicalize().ok().or_else(|| {
+            let parent = path.parent()?.canonicalize().ok()?;
+            Some(parent.join(path.file_name()?))
+        });
+        let (directory, relative) = self
+            .directories
+            .iter()
+            .filter_map(|directory| {
+                let relative = match path.strip_prefix(directory) {
+                    Ok(relative) => relative,
+                    Err(_) => {
+                        let canonical_dir = Path::new(directory).canonicalize().ok()?;
+                        canonical.as_deref()?.strip_prefix(canonical_dir).ok()?
+                    }
+                };
+                Some((directory, relative.to_path_buf()))
+            })
+            .min_by_key(|(_, relative)| relative.components().count())?;
+
+        let path = Path::new(directory).join(&relative);
+        let depth = relative.components().count();
+        if path.is_dir() {
+            // Only the configured directories themselves are loaded without recursion
+            if !self.recursive_loading && depth > 0 {
+                return None;
+            }
+        } else if (!self.recursive_loading && depth > 1) || !self.matches_extension(&path) {
+            return None;
+        }
+        Some(path)
+    }
+
+    /// Finds the innermost configured directory containing the given path
+    ///
+    /// returns: The index of the directory and the path relative to it
+    fn locate(&self, path: &Path) -> Option<(usize, PathBuf)> {
+        let canonical = path.canonicalize().ok();
+        self.directories
+            .iter()
+            .enumerate()
+            .filter_map(|(index, directory)| {
+                let relative = path.strip_prefix(directory).ok().or_else(|| {
+                    let directory = Path::new(directory).canonicalize().ok()?;
+                    canonical.as_deref()?.strip_prefix(directory).ok()
+                })?;
+                Some((index, relative.to_path_buf()))
+            })
+            .min_by_key(|(_, relative)| relative.components().count())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::slice::Iter;
+
+    use bevy::prelude::{AppTypeRegistry, AssetServer, Component, ReflectComponent, Res};
+    use bevy::reflect::{FromReflect, Reflect, TypeUuid};
+
+    use super::*;
+    use crate::children::ProtoChild;
+    use crate::prototype::Prototype;
+    use crate::reflect::as_reflected;
+
+    #[derive(TypeUuid)]
+    #[uuid = "1d9b2a5e-4c3f-4b8a-9a57-3f2c6d0e8b11"]
+    struct TestAsset;
+
+    #[derive(Clone, Serialize, Deserialize)]
+    struct TestSprite(String);
+
+    #[cfg_attr(feature = "typetag", typetag::serde)]
+    impl ProtoComponent for TestSprite {
+        fn insert_self(&self, _: &mut ProtoCommands, _: &Res<AssetServer>) {}
+
+        fn prepare(&self, _: &mut World, prototype: &dyn Prototypical, data: &mut ProtoData) {
+            let handle = Handle::<TestAsset>::weak(HandleId::from(self.0.as_str()));
+            data.insert_handle(prototype, self, handle);
+        }
+    }
+
+    /// Creates a prototype with a single unnamed inline child using the given sprite
+    fn with_inline_sprite(name: &str, sprite: &str) -> Box<dyn Prototypical> {
+        let child = Prototype {
+            components: vec![Box::new(TestSprite(sprite.to_string()))],
+            ..Default::default()
+        };
+        Box::new(Prototype {
+            name: name.to_string(),
+            children: vec![ProtoChild::Inline(Box::new(child))],
+            ..Default::default()
+        })
+    }
+
+    /// Get the handle stored for the sprite of the given prototype's inline child
+    fn inline_handle(data: &ProtoData, proto: &dyn Prototypical) -> Option<Handle<TestAsset>> {
+        let child = proto.children()[0].prototype(data)?;
+        let sprite = child.iter_components().next()?;
+        let id = HandleId::from(sprite_path(sprite.as_ref()));
+        data.get_handle(child, sprite.as_ref(), id)
+    }
+
+    fn sprite_path(component: &dyn ProtoComponent) -> &str {
+        let component: &dyn Any = component;
+        &component.downcast_ref::<TestSprite>().unwrap().0
+    }
+
+    #[test]
+    fn inline_handles_are_kept_per_prototype() {
+        let mut world = World::new();
+        let mut data = ProtoData::empty();
+        data.insert_prototype(&mut world, with_inline_sprite("A", "a.png"));
+        data.insert_prototype(&mut world, with_inline_sprite("B", "b.png"));
+
+        let a = data.get_prototype("A").unwrap();
+        let b = data.get_prototype("B").unwrap();
+        assert!(inline_handle(&data, a).is_some());
+        assert!(inline_handle(&data, b).is_some());
+        assert_eq!(data.handles.len(), 2);
+        assert!(data.handles.contains_key("A/0"));
+
+        // Replacing and removing a prototype drops the handles of its inline children
+        data.insert_prototype(&mut world, with_inline_sprite("A", "c.png"));
+        assert_eq!(data.handles.len(), 2);
+        let removed = data.remove_prototype("A").unwrap();
+        assert!(inline_handle(&data, removed.as_ref()).is_none());
+        assert_eq!(data.handles.len(), 1);
+        assert!(data.handles.contains_key("B/0"));
+    }
+
+    #[derive(Clone, Serialize, Deserialize)]
+    struct TestHealth(u32);
+
+    #[cfg_attr(feature = "typetag", typetag::serde)]
+    impl ProtoComponent for TestHealth {
+        fn insert_self(&self, _: &mut ProtoCommands, _: &Res<AssetServer>) {}
+    }
+
+    #[derive(Clone, Serialize, Deserialize)]
+    struct TestArmor(u32);
+
+    #[cfg_attr(feature = "typetag", typetag::serde)]
+    impl ProtoComponent for TestArmor {
+        fn insert_self(&self, _: &mut ProtoCommands, _: &Res<AssetServer>) {}
+    }
+
+    #[derive(Component, Reflect, FromReflect, Default)]
+    #[reflect(Component)]
+    struct TestScale(f32);
+
+    /// A prototype whose components can't be modified
+    struct Fixed;
+
+    impl Prototypical for Fixed {
+        fn name(&self) -> &str {
+            "Fixed"
+        }
+
+        fn iter_components(&self) -> Iter<'_, Box<dyn ProtoComponent>> {
+            [].iter()
         }
+
+        fn create_commands<'w, 's, 'a, 'p>(
+            &'p self,
+            entity: EntityCommands<'w, 's, 'a>,
+            data: &'p Res<ProtoData>,
+        ) -> ProtoCommands<'w, 's, 'a, 'p> {
+            data.get_commands(self, entity)
+        }
+    }
+
+    /// Writes the files of each layer into its own directory, returning the options which load
+    /// them (in order)
+    fn write_layers(name: &str, layers: &[&[(&str, &str)]]) -> ProtoDataOptions {
+        let dir = std::env::temp_dir()
+            .join(format!("bevy_proto_{}", std::process::id()))
+            .join(name);
+        let _ = fs::remove_dir_all(&dir);
+
+        let mut directories = Vec::new();
+        for (index, files) in layers.iter().enumerate() {
+            let layer = dir.join(index.to_string());
+            fs::create_dir_all(&layer).unwrap();
+            for (file, contents) in files.iter() {
+                fs::write(layer.join(file), contents).unwrap();
+            }
+            directories.push(layer.to_string_lossy().to_string());
+        }
+
+        let options = ProtoDataOptions {
+            directories,
+            layered: true,
+            ..Default::default()
+        };
+        options.registry.register::<TestHealth>("TestHealth");
+        options.registry.register::<TestArmor>("TestArmor");
+        let types = AppTypeRegistry::default();
+        types.write().register::<TestScale>();
+        options.registry.set_type_registry(types);
+        options
+    }
+
+    /// Loads the files for the given options into the given data
+    fn load(
+        world: &mut World,
+        data: &mut ProtoData,
+        options: &ProtoDataOptions,
+    ) -> ProtoLoadErrors {
+        data.registry = options.registry.clone();
+        data.load_directories(world, options)
+    }
+
+    /// Get the component of the given type from the prototype with the given name
+    fn component<'a, T: 'static>(data: &'a ProtoData, name: &str) -> Option<&'a T> {
+        data.get_prototype(name)?
+            .iter_components()
+            .find_map(|component| {
+                let component: &dyn Any = component.as_ref();
+                component.downcast_ref::<T>()
+            })
+    }
+
+    #[test]
+    fn patch_missing_target() {
+        let options = write_layers(
+            "patch_missing_target",
+            &[&[(
+                "ghost.yaml",
+                "patch: Ghost\ncomponents:\n  - type: TestHealth\n    value: 1\n",
+            )]],
+        );
+        let errors = load(&mut World::new(), &mut ProtoData::empty(), &options);
+
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            &errors.iter().next().unwrap().kind,
+            ProtoLoadErrorKind::MissingPatchTarget { target } if target == "Ghost"
+        ));
+    }
+
+    #[test]
+    fn patch_unpatchable_target() {
+        let options = write_layers(
+            "patch_unpatchable_target",
+            &[&[(
+                "fixed.yaml",
+                "patch: Fixed\ncomponents:\n  - type: TestHealth\n    value: 1\n",
+            )]],
+        );
+        let mut world = World::new();
+        let mut data = ProtoData::empty();
+        data.insert_prototype(&mut world, Box::new(Fixed));
+        let errors = load(&mut world, &mut data, &options);
+
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            &errors.iter().next().unwrap().kind,
+            ProtoLoadErrorKind::UnpatchableTarget { target } if target == "Fixed"
+        ));
+        assert_eq!(
+            data.get_prototype("Fixed").unwrap().iter_components().len(),
+            0
+        );
+    }
+
+    #[test]
+    fn patch_removes_reflected_components() {
+        let full_path = std::any::type_name::<TestScale>();
+        let removed_by_path = format!("patch: Orc\nremove: [ {} ]\n", full_path);
+        let options = write_layers(
+            "patch_removes_reflected_components",
+            &[
+                &[
+                    (
+                        "goblin.yaml",
+                        "name: Goblin\ncomponents:\n  - type: TestScale\n    value: [2.0]\n  - type: TestHealth\n    value: 1\n",
+                    ),
+                    (
+                        "orc.yaml",
+                        "name: Orc\ncomponents:\n  - type: TestScale\n    value: [3.0]\n",
+                    ),
+                ],
+                &[
+                    ("goblin.yaml", "patch: Goblin\nremove: [ TestScale ]\n"),
+                    ("orc.yaml", &removed_by_path),
+                ],
+            ],
+        );
+        let mut data = ProtoData::empty();
+        let errors = load(&mut World::new(), &mut data, &options);
+        assert!(errors.is_empty(), "{:?}", errors.iter().collect::<Vec<_>>());
+
+        for name in ["Goblin", "Orc"] {
+            let proto = data.get_prototype(name).unwrap();
+            assert!(proto
+                .iter_components()
+                .all(|component| as_reflected(component.as_ref()).is_none()));
+        }
+        assert!(component::<TestHealth>(&data, "Goblin").is_some());
+    }
+
+    #[test]
+    fn patches_from_several_layers() {
+        let options = write_layers(
+            "patches_from_several_layers",
+            &[
+                &[(
+                    "goblin.yaml",
+                    "name: Goblin\ncomponents:\n  - type: TestHealth\n    value: 10\n",
+                )],
+                &[(
+                    "goblin.yaml",
+                    "patch: Goblin\ncomponents:\n  - type: TestHealth\n    value: 20\n  - type: TestArmor\n    value: 1\n",
+                )],
+                &[(
+                    "goblin.yaml",
+                    "patch: Goblin\ncomponents:\n  - type: TestHealth\n    value: 30\n",
+                )],
+            ],
+        );
+        let mut data = ProtoData::empty();
+        let errors = load(&mut World::new(), &mut data, &options);
+        assert!(errors.is_empty(), "{:?}", errors.iter().collect::<Vec<_>>());
+
+        // Later layers are applied last
+        assert_eq!(component::<TestHealth>(&data, "Goblin").unwrap().0, 30);
+        assert_eq!(component::<TestArmor>(&data, "Goblin").unwrap().0, 1);
+        let layers = data
+            .component_sources("Goblin")
+            .unwrap()
+            .iter()
+            .map(|source| source.layer)
+            .collect::<Vec<_>>();
+        assert_eq!(layers, [2, 1]);
+        assert_eq!(data.patch_sources["Goblin"].len(), 2);
+    }
+
+    /// Loads two files defining `Goblin` with the given policy, returning the health it ends up
+    /// with along with any errors
+    fn load_duplicates(policy: DuplicatePolicy) -> (Option<u32>, ProtoLoadErrors) {
+        let name = format!("duplicates_{:?}", policy);
+        let goblin = |hp: u32| {
+            format!(
+                "name: Goblin\ncomponents:\n  - type: TestHealth\n    value: {}\n",
+                hp
+            )
+        };
+        let mut options = write_layers(&name, &[&[("a.yaml", &goblin(1)), ("b.yaml", &goblin(2))]]);
+        options.layered = false;
+        options.duplicate_policy = policy;
+
+        let mut data = ProtoData::empty();
+        let errors = load(&mut World::new(), &mut data, &options);
+        let hp = component::<TestHealth>(&data, "Goblin").map(|health| health.0);
+        (hp, errors)
+    }
+
+    #[test]
+    fn duplicate_error() {
+        let (hp, errors) = load_duplicates(DuplicatePolicy::Error);
+        assert_eq!(hp, Some(1));
+        assert_eq!(errors.len(), 1);
+        let error = errors.iter().next().unwrap();
+        assert!(error.path.ends_with("b.yaml"));
+        assert!(matches!(
+            &error.kind,
+            ProtoLoadErrorKind::Duplicate { name, original }
+                if name == "Goblin" && original.path.ends_with("a.yaml")
+        ));
+    }
+
+    #[test]
+    fn duplicate_warn_keep_first() {
+        let (hp, errors) = load_duplicates(DuplicatePolicy::WarnKeepFirst);
+        assert_eq!(hp, Some(1));
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn duplicate_warn_keep_last() {
+        let (hp, errors) = load_duplicates(DuplicatePolicy::WarnKeepLast);
+        assert_eq!(hp, Some(2));
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn duplicate_override() {
+        let (hp, errors) = load_duplicates(DuplicatePolicy::Override);
+        assert_eq!(hp, Some(2));
+        assert!(errors.is_empty());
     }
 }
@@ -0,0 +1,679 @@
+//! Provides the built-in [`ProtoDeserializer`] implementations for each supported file format.
+//!
+//! YAML is always available. The remaining formats are enabled with the `json`, `ron`,
+//! and `toml` cargo features respectively.
+//!
+//! Apart from TOML, each format accepts either a single prototype or a list of prototypes at
+//! the top level of a file. YAML files may also contain several `---`-separated documents.
+//! The same goes for [`ProtoTable`] files.
+use std::fmt::Formatter;
+use std::marker::PhantomData;
+
+use bevy::utils::HashMap;
+#[cfg(feature = "ron")]
+use serde::de::MapAccess;
+use serde::de::{self, DeserializeSeed, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer};
+
+use crate::{
+    data::ProtoDeserializer,
+    errors::ProtoDeserializeError,
+    prototype::{Prototype, PrototypeSeed, Prototypical},
+    registry::{ComponentSeed, ProtoComponentRegistry},
+    table::ProtoTable,
+};
+
+/// Deserializes [`Prototype`] objects from YAML.
+#[derive(Clone, Default)]
+pub struct YamlProtoDeserializer;
+
+impl ProtoDeserializer for YamlProtoDeserializer {
+    fn deserialize(
+        &self,
+        data: &str,
+        registry: &ProtoComponentRegistry,
+    ) -> Result<Box<dyn Prototypical>, ProtoDeserializeError> {
+        match PrototypeSeed::new(registry).deserialize(serde_yaml::Deserializer::from_str(data)) {
+            Ok(value) => Ok(Box::new(value)),
+            Err(err) => {
+                let component = serde_yaml::from_str(data)
+                    .ok()
+                    .and_then(|value| find_invalid_yaml_component(&value, registry));
+                Err(ProtoDeserializeError::from(err).with_component(component))
+            }
+        }
+    }
+
+    fn deserialize_many(
+        &self,
+        data: &str,
+        registry: &ProtoComponentRegistry,
+    ) -> Vec<Result<Box<dyn Prototypical>, ProtoDeserializeError>> {
+        let seed = PrototypeSeed::new(registry);
+        let mut results = Vec::new();
+
+        for (index, document) in serde_yaml::Deserializer::from_str(data).enumerate() {
+            let value = match serde_yaml::Value::deserialize(document) {
+                Ok(value) => value,
+                Err(err) => {
+                    // The document isn't even valid YAML, so no further documents can be read
+                    results.push(Err(ProtoDeserializeError::from(err)));
+                    break;
+                }
+            };
+
+            if let serde_yaml::Value::Sequence(entries) = value {
+                let entries = deserialize_entries(entries, seed, |entry| {
+                    find_invalid_yaml_component(entry, registry)
+                });
+                results.extend(entries.into_iter().enumerate().map(|(entry, result)| {
+                    boxed(result.map_err(|err| locate_yaml_entry(err, data, index, entry, seed)))
+                }));
+                continue;
+            }
+
+            let component = find_invalid_yaml_component(&value, registry);
+            let result = seed.deserialize(value).map_err(|err| {
+                // Values don't keep their location, so read the failed document again to find it
+                serde_yaml::Deserializer::from_str(data)
+                    .nth(index)
+                    .and_then(|document| seed.deserialize(document).err())
+                    .map_or_else(|| ProtoDeserializeError::from(err), ProtoDeserializeError::from)
+                    .with_component(component)
+            });
+            results.push(boxed(result));
+        }
+
+        results
+    }
+}
+
+/// Adds the location at which the given entry of the list in the given YAML document fails to
+/// deserialize to its error
+///
+/// Values don't keep their location, so the document is read again, skipping the entries before
+/// the failed one.
+fn locate_yaml_entry(
+    err: ProtoDeserializeError,
+    data: &str,
+    document: usize,
+    entry: usize,
+    seed: PrototypeSeed,
+) -> ProtoDeserializeError {
+    struct EntryVisitor<'a>(usize, PrototypeSeed<'a>);
+
+    impl<'a, 'de> Visitor<'de> for EntryVisitor<'a> {
+        type Value = ();
+
+        fn expecting(&self, formatter: &mut Formatter) -> std::fmt::Result {
+            formatter.write_str("a list of prototypes")
+        }
+
+        fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+        where
+            A: SeqAccess<'de>,
+        {
+            for _ in 0..self.0 {
+                seq.next_element::<de::IgnoredAny>()?;
+            }
+            seq.next_element_seed(self.1)?;
+            Ok(())
+        }
+    }
+
+    let location = serde_yaml::Deserializer::from_str(data)
+        .nth(document)
+        .and_then(|document| document.deserialize_seq(EntryVisitor(entry, seed)).err())
+        .and_then(|err| err.location());
+    match location {
+        Some(location) => err.with_location(location.line(), location.column()),
+        None => err,
+    }
+}
+
+/// Finds the `type` of the first component in the given YAML value that fails to deserialize.
+///
+/// The value may either be a single prototype or a list of prototypes.
+fn find_invalid_yaml_component(
+    value: &serde_yaml::Value,
+    registry: &ProtoComponentRegistry,
+) -> Option<String> {
+    if let Some(prototypes) = value.as_sequence() {
+        return prototypes
+            .iter()
+            .find_map(|value| find_invalid_yaml_component(value, registry));
+    }
+
+    let components = value.get("components")?.as_sequence()?;
+
+    components.iter().find_map(|component| {
+        ComponentSeed::new(registry)
+            .deserialize(component.clone())
+            .err()?;
+        component.get("type")?.as_str().map(String::from)
+    })
+}
+
+/// Deserializes [`Prototype`] objects from JSON.
+#[cfg(feature = "json")]
+#[derive(Clone, Default)]
+pub struct JsonProtoDeserializer;
+
+#[cfg(feature = "json")]
+impl ProtoDeserializer for JsonProtoDeserializer {
+    fn deserialize(
+        &self,
+        data: &str,
+        registry: &ProtoComponentRegistry,
+    ) -> Result<Box<dyn Prototypical>, ProtoDeserializeError> {
+        let mut deserializer = serde_json::Deserializer::from_str(data);
+        let result = PrototypeSeed::new(registry)
+            .deserialize(&mut deserializer)
+            .and_then(|value| deserializer.end().map(|_| value));
+        match result {
+            Ok(value) => Ok(Box::new(value)),
+            Err(err) => Err(json_error(data, err, registry)),
+        }
+    }
+
+    fn deserialize_many(
+        &self,
+        data: &str,
+        registry: &ProtoComponentRegistry,
+    ) -> Vec<Result<Box<dyn Prototypical>, ProtoDeserializeError>> {
+        match serde_json::from_str::<serde_json::Value>(data) {
+            Ok(serde_json::Value::Array(entries)) => {
+                let seed = PrototypeSeed::new(registry);
+                let entries = deserialize_entries(entries, seed, |entry| {
+                    find_invalid_json_component(entry, registry)
+                });
+                entries.into_iter().map(boxed).collect()
+            }
+            Ok(_) => vec![self.deserialize(data, registry)],
+            Err(err) => vec![Err(ProtoDeserializeError::from(err))],
+        }
+    }
+}
+
+/// Converts a [`serde_json::Error`] into a [`ProtoDeserializeError`] for the given JSON data.
+#[cfg(feature = "json")]
+fn json_error(
+    data: &str,
+    err: serde_json::Error,
+    registry: &ProtoComponentRegistry,
+) -> ProtoDeserializeError {
+    let component = serde_json::from_str(data)
+        .ok()
+        .and_then(|value| find_invalid_json_component(&value, registry));
+    ProtoDeserializeError::from(err).with_component(component)
+}
+
+/// Finds the `type` of the first component in the given JSON value that fails to deserialize.
+///
+/// The value may either be a single prototype or a list of prototypes.
+#[cfg(feature = "json")]
+fn find_invalid_json_component(
+    value: &serde_json::Value,
+    registry: &ProtoComponentRegistry,
+) -> Option<String> {
+    if let Some(prototypes) = value.as_array() {
+        return prototypes
+            .iter()
+            .find_map(|value| find_invalid_json_component(value, registry));
+    }
+
+    let components = value.get("components")?.as_array()?;
+
+    components.iter().find_map(|component| {
+        ComponentSeed::new(registry)
+            .deserialize(component.clone())
+            .err()?;
+        component.get("type")?.as_str().map(String::from)
+    })
+}
+
+/// Deserializes [`Prototype`] objects from [RON](https://github.com/ron-rs/ron).
+#[cfg(feature = "ron")]
+#[derive(Clone, Default)]
+pub struct RonProtoDeserializer;
+
+#[cfg(feature = "ron")]
+impl ProtoDeserializer for RonProtoDeserializer {
+    fn deserialize(
+        &self,
+        data: &str,
+        registry: &ProtoComponentRegistry,
+    ) -> Result<Box<dyn Prototypical>, ProtoDeserializeError> {
+        match ron::Options::default().from_str_seed(data, PrototypeSeed::new(registry)) {
+            Ok(value) => Ok(Box::new(value)),
+            Err(err) => Err(ron_error(data, err, registry)),
+        }
+    }
+
+    fn deserialize_many(
+        &self,
+        data: &str,
+        registry: &ProtoComponentRegistry,
+    ) -> Vec<Result<Box<dyn Prototypical>, ProtoDeserializeError>> {
+        let seed = PrototypeSeed::new(registry);
+        // RON values can't represent enum variants, so they're only used to find the failed entries
+        match ron::Options::default().from_str_seed(data, PrototypeListSeed(seed)) {
+            Ok(list) => list.into_iter().map(|proto| boxed(Ok(proto))).collect(),
+            Err(err) => match ron::from_str::<ron::Value>(data) {
+                Ok(ron::Value::Seq(entries)) => {
+                    let entries = deserialize_entries(entries, seed, |entry| {
+                        find_invalid_ron_component(entry, registry)
+                    });
+                    entries.into_iter().map(boxed).collect()
+                }
+                _ => vec![Err(ron_error(data, err, registry))],
+            },
+        }
+    }
+
+    fn deserialize_tables(&self, data: &str) -> Vec<Result<ProtoTable, ProtoDeserializeError>> {
+        match ron::from_str::<TableList>(data) {
+            Ok(list) => list.0.into_iter().map(Ok).collect(),
+            Err(err) => match ron::from_str::<ron::Value>(data) {
+                Ok(ron::Value::Seq(entries)) => deserialize_entries(entries, PhantomData, |_| None),
+                _ => vec![Err(ProtoDeserializeError::from(err))],
+            },
+        }
+    }
+}
+
+/// Converts a [`ron::error::SpannedError`] into a [`ProtoDeserializeError`] for the given RON data.
+#[cfg(feature = "ron")]
+fn ron_error(
+    data: &str,
+    err: ron::error::SpannedError,
+    registry: &ProtoComponentRegistry,
+) -> ProtoDeserializeError {
+    let component = ron::from_str(data)
+        .ok()
+        .and_then(|value| find_invalid_ron_component(&value, registry));
+    ProtoDeserializeError::from(err).with_component(component)
+}
+
+/// Finds the `type` of the first component in the given RON value that fails to deserialize.
+///
+/// The value may either be a single prototype or a list of prototypes.
+#[cfg(feature = "ron")]
+fn find_invalid_ron_component(
+    value: &ron::Value,
+    registry: &ProtoComponentRegistry,
+) -> Option<String> {
+    use ron::Value;
+
+    fn get<'a>(value: &'a Value, key: &str) -> Option<&'a Value> {
+        match value {
+            Value::Map(map) => map
+                .iter()
+                .find(|(k, _)| matches!(k, Value::String(k) if k == key))
+                .map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    let components = match value {
+        Value::Seq(prototypes) => {
+            return prototypes
+                .iter()
+                .find_map(|value| find_invalid_ron_component(value, registry))
+        }
+        value => match get(value, "components")? {
+            Value::Seq(components) => components,
+            _ => return None,
+        },
+    };
+
+    components.iter().find_map(|component| {
+        ComponentSeed::new(registry)
+            .deserialize(component.clone())
+            .err()?;
+        match get(component, "type")? {
+            Value::String(name) => Some(name.clone()),
+            _ => None,
+        }
+    })
+}
+
+/// Deserializes [`Prototype`] objects from [TOML](https://toml.io).
+#[cfg(feature = "toml")]
+#[derive(Clone, Default)]
+pub struct TomlProtoDeserializer;
+
+#[cfg(feature = "toml")]
+impl ProtoDeserializer for TomlProtoDeserializer {
+    fn deserialize(
+        &self,
+        data: &str,
+        registry: &ProtoComponentRegistry,
+    ) -> Result<Box<dyn Prototypical>, ProtoDeserializeError> {
+        match PrototypeSeed::new(registry).deserialize(toml::Deserializer::new(data)) {
+            Ok(value) => Ok(Box::new(value)),
+            Err(err) => {
+                let mut error = ProtoDeserializeError::new(err.message().trim_end());
+                if let Some(span) = err.span() {
+                    let (line, column) = line_column(data, span.start);
+                    error = error.with_location(line, column);
+                }
+                Err(error.with_component(find_invalid_toml_component(data, registry)))
+            }
+        }
+    }
+
+    fn deserialize_tables(&self, data: &str) -> Vec<Result<ProtoTable, ProtoDeserializeError>> {
+        match toml::from_str::<ProtoTable>(data) {
+            Ok(table) => vec![Ok(table)],
+            Err(err) => {
+                let mut error = ProtoDeserializeError::new(err.message().trim_end());
+                if let Some(span) = err.span() {
+                    let (line, column) = line_column(data, span.start);
+                    error = error.with_location(line, column);
+                }
+                vec![Err(error)]
+            }
+        }
+    }
+}
+
+/// Finds the `type` of the first component in the given TOML data that fails to deserialize.
+#[cfg(feature = "toml")]
+fn find_invalid_toml_component(data: &str, registry: &ProtoComponentRegistry) -> Option<String> {
+    let value: toml::Value = toml::from_str(data).ok()?;
+    let components = value.get("components")?.as_array()?;
+
+    components.iter().find_map(|component| {
+        ComponentSeed::new(registry)
+            .deserialize(component.clone())
+            .err()?;
+        component.get("type")?.as_str().map(String::from)
+    })
+}
+
+/// Converts a byte offset into the given data to a line and column (both starting at 1).
+#[cfg(feature = "toml")]
+fn line_column(data: &str, offset: usize) -> (usize, usize) {
+    let before = &data[..offset.min(data.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.len() - before.rfind('\n').map_or(0, |index| index + 1) + 1;
+    (line, column)
+}
+
+/// Deserializes each entry in a list on its own, so that a single malformed entry doesn't
+/// prevent the rest of the list from loading
+///
+/// # Arguments
+///
+/// * `entries`: The format's value for each entry in the list
+/// * `seed`: Deserializes a single entry
+/// * `find_component`: Finds the `type` of the component responsible for a failed entry
+///
+/// returns: Vec<Result<T, ProtoDeserializeError>>
+fn deserialize_entries<'de, S, V>(
+    entries: Vec<V>,
+    seed: S,
+    find_component: impl Fn(&V) -> Option<String>,
+) -> Vec<Result<S::Value, ProtoDeserializeError>>
+where
+    S: DeserializeSeed<'de> + Copy,
+    V: Deserializer<'de> + Clone,
+{
+    entries
+        .into_iter()
+        .enumerate()
+        .map(|(index, entry)| {
+            seed.deserialize(entry.clone()).map_err(|err| {
+                ProtoDeserializeError::new(format!("list entry {}: {}", index + 1, err))
+                    .with_component(find_component(&entry))
+            })
+        })
+        .collect()
+}
+
+/// Boxes the result of deserializing a single [`Prototype`].
+fn boxed(
+    result: Result<Prototype, ProtoDeserializeError>,
+) -> Result<Box<dyn Prototypical>, ProtoDeserializeError> {
+    result.map(|proto| Box::new(proto) as Box<dyn Prototypical>)
+}
+
+#[cfg(feature = "ron")]
+/// Deserializes either a single [`Prototype`] or a list of them.
+#[derive(Copy, Clone)]
+struct PrototypeListSeed<'a>(PrototypeSeed<'a>);
+
+#[cfg(feature = "ron")]
+impl<'a, 'de> DeserializeSeed<'de> for PrototypeListSeed<'a> {
+    type Value = Vec<Prototype>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(self)
+    }
+}
+
+#[cfg(feature = "ron")]
+impl<'a, 'de> Visitor<'de> for PrototypeListSeed<'a> {
+    type Value = Vec<Prototype>;
+
+    fn expecting(&self, formatter: &mut Formatter) -> std::fmt::Result {
+        formatter.write_str("a prototype or a list of prototypes")
+    }
+
+    fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        Ok(vec![self.0.visit_map(map)?])
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut prototypes = Vec::with_capacity(seq.size_hint().unwrap_or_default());
+        while let Some(proto) = seq.next_element_seed(self.0)? {
+            prototypes.push(proto);
+        }
+        Ok(prototypes)
+    }
+}
+
+/// Deserializes the [`ProtoTable`] objects in the given YAML (or JSON) data.
+///
+/// Like prototypes, each document may contain either a single table or a list of them.
+pub(crate) fn deserialize_yaml_tables(
+    data: &str,
+) -> Vec<Result<ProtoTable, ProtoDeserializeError>> {
+    let mut results = Vec::new();
+
+    for document in serde_yaml::Deserializer::from_str(data) {
+        match serde_yaml::Value::deserialize(document) {
+            Ok(serde_yaml::Value::Sequence(entries)) => {
+                results.extend(deserialize_entries(entries, PhantomData, |_| None));
+            }
+            Ok(value) => results.push(ProtoTable::deserialize(value).map_err(Into::into)),
+            Err(err) => {
+                // The document isn't even valid YAML, so no further documents can be read
+                results.push(Err(ProtoDeserializeError::from(err)));
+                break;
+            }
+        }
+    }
+
+    results
+}
+
+#[cfg(feature = "ron")]
+/// Either a single [`ProtoTable`] or a list of them.
+struct TableList(Vec<ProtoTable>);
+
+#[cfg(feature = "ron")]
+impl<'de> Deserialize<'de> for TableList {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct TableListVisitor;
+
+        impl<'de> Visitor<'de> for TableListVisitor {
+            type Value = TableList;
+
+            fn expecting(&self, formatter: &mut Formatter) -> std::fmt::Result {
+                formatter.write_str("a table or a list of tables")
+            }
+
+            fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                let table = ProtoTable::deserialize(de::value::MapAccessDeserializer::new(map))?;
+                Ok(TableList(vec![table]))
+            }
+
+            fn visit_seq<A>(self, seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                Deserialize::deserialize(de::value::SeqAccessDeserializer::new(seq)).map(TableList)
+            }
+        }
+
+        deserializer.deserialize_any(TableListVisitor)
+    }
+}
+
+/// The extensions handled by a built-in [`ProtoDeserializer`] with the currently enabled features.
+pub(crate) fn default_extensions() -> Vec<&'static str> {
+    #[allow(unused_mut)]
+    let mut extensions = vec!["yaml", "json"];
+    #[cfg(feature = "ron")]
+    extensions.push("ron");
+    #[cfg(feature = "toml")]
+    extensions.push("toml");
+    extensions
+}
+
+/// The built-in deserializers for each non-YAML format enabled by the current features.
+pub(crate) fn default_deserializers(
+) -> HashMap<&'static str, Box<dyn ProtoDeserializer + Send + Sync>> {
+    #[allow(unused_mut)]
+    let mut deserializers: HashMap<&'static str, Box<dyn ProtoDeserializer + Send + Sync>> =
+        HashMap::default();
+    #[cfg(feature = "json")]
+    deserializers.insert("json", Box::new(JsonProtoDeserializer));
+    #[cfg(feature = "ron")]
+    deserializers.insert("ron", Box::new(RonProtoDeserializer));
+    #[cfg(feature = "toml")]
+    deserializers.insert("toml", Box::new(TomlProtoDeserializer));
+    deserializers
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use bevy::asset::{AssetServer, FileAssetIo};
+    use bevy::prelude::{FromWorld, Res, World};
+    use serde::Serialize;
+
+    use super::*;
+    use crate::children::ProtoChild;
+    use crate::components::ProtoComponent;
+    use crate::data::{ProtoCommands, ProtoData, ProtoDataOptions};
+    use crate::errors::{ProtoLoadErrorKind, ProtoLoadErrors};
+
+    #[derive(Clone, Serialize, Deserialize)]
+    struct EntryHealth(u32);
+
+    #[cfg_attr(feature = "typetag", typetag::serde)]
+    impl ProtoComponent for EntryHealth {
+        fn insert_self(&self, _: &mut ProtoCommands, _: &Res<AssetServer>) {}
+    }
+
+    /// Loads the given file, returning the loaded data and any errors
+    fn load(name: &str, contents: &str) -> (ProtoData, ProtoLoadErrors) {
+        let dir = std::env::temp_dir()
+            .join(format!("bevy_proto_{}", std::process::id()))
+            .join(name);
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("entries.yaml"), contents).unwrap();
+
+        let mut world = World::new();
+        world.insert_resource(AssetServer::new(FileAssetIo::new(&dir, false)));
+        let options = ProtoDataOptions {
+            directories: vec![dir.to_string_lossy().to_string()],
+            ..Default::default()
+        };
+        options.registry.register::<EntryHealth>("EntryHealth");
+        world.insert_resource(options);
+        let data = ProtoData::from_world(&mut world);
+        let _ = fs::remove_dir_all(&dir);
+        (data, world.remove_resource::<ProtoLoadErrors>().unwrap())
+    }
+
+    /// Get the error recorded while deserializing the loaded file
+    fn deserialize_error(errors: &ProtoLoadErrors) -> &ProtoDeserializeError {
+        assert_eq!(errors.len(), 1);
+        match &errors.iter().next().unwrap().kind {
+            ProtoLoadErrorKind::Deserialize(err) => err,
+            kind => panic!("unexpected error: {}", kind),
+        }
+    }
+
+    #[test]
+    fn bad_document() {
+        let (data, errors) = load(
+            "bad_document",
+            "name: Goblin\n---\nname: Orc\ncomponents:\n  - type: EntryHealth\n    value: lots\n---\nname: Troll\n",
+        );
+
+        // Components are read as a whole, so the error points at the start of the failed one
+        let err = deserialize_error(&errors);
+        assert_eq!(err.line, Some(5));
+        assert_eq!(err.component.as_deref(), Some("EntryHealth"));
+        assert!(data.get_prototype("Goblin").is_some());
+        assert!(data.get_prototype("Orc").is_none());
+        assert!(data.get_prototype("Troll").is_some());
+    }
+
+    #[test]
+    fn bad_list_entry() {
+        let (data, errors) = load(
+            "bad_list_entry",
+            "name: Goblin\n---\n- name: Orc\n- name: Ogre\n  components:\n    - type: EntryHealth\n      value: lots\n- name: Troll\n",
+        );
+
+        let err = deserialize_error(&errors);
+        assert!(err.message.starts_with("list entry 2:"));
+        assert_eq!(err.line, Some(6));
+        assert_eq!(err.component.as_deref(), Some("EntryHealth"));
+        for name in ["Goblin", "Orc", "Troll"] {
+            assert!(data.get_prototype(name).is_some());
+        }
+        assert!(data.get_prototype("Ogre").is_none());
+    }
+
+    #[test]
+    fn registry_reaches_inline_children() {
+        let data = "name: Knight\nchildren:\n  - name: Shield\n    components:\n      - type: ChildHealth\n        value: 5\n";
+        // Named differently than by `typetag`, so only the registry knows it
+        let registry = ProtoComponentRegistry::default();
+        registry.register::<EntryHealth>("ChildHealth");
+
+        let knight = YamlProtoDeserializer.deserialize(data, &registry).unwrap();
+        match &knight.children()[0] {
+            ProtoChild::Inline(shield) => assert_eq!(shield.components.len(), 1),
+            ProtoChild::Reference(name) => panic!("unexpected reference to `{}`", name),
+        }
+
+        let unregistered = ProtoComponentRegistry::default();
+        let err = YamlProtoDeserializer.deserialize(data, &unregistered).err();
+        assert!(err.is_some());
+    }
+}
@@ -0,0 +1,448 @@
+//! Contains [`ProtoEntityRef`], used by components to reference other entities spawned by the same prototype.
+use std::fmt::Formatter;
+
+use bevy::prelude::Entity;
+use bevy::utils::{HashMap, HashSet};
+use indexmap::IndexSet;
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::children::ProtoChild;
+use crate::components::ProtoComponent;
+use crate::data::ProtoData;
+use crate::prototype::Prototypical;
+
+/// The prefix of a serialized [`ProtoEntityRef`]
+const PREFIX: char = '@';
+
+/// A reference to another entity spawned by the same prototype, by its local name.
+///
+/// Entities are given a local name with [`local`](crate::Prototype::local), either on the
+/// prototype being spawned or on any of its [`children`](crate::Prototype::children) (and their
+/// children, and so on). Components can then reference them by that name, written as `"@name"`:
+///
+/// ```yaml
+/// name: Turret
+/// components:
+///   - type: Aim
+///     value:
+///       target: "@barrel"
+/// children:
+///   - local: barrel
+///     templates: Barrel
+/// ```
+///
+/// A reference is resolved when its component is inserted, through [`MapEntityRefs`]: components
+/// deriving [`ProtoComponent`](bevy_proto_derive::ProtoComponent) resolve the references in their
+/// fields to the entities that were actually spawned. References to names that none of the
+/// prototypes using the component declare are reported when loading (as found by
+/// [`ProtoComponent::visit_entity_refs`]).
+///
+/// # Examples
+///
+/// ```
+/// use bevy::prelude::*;
+/// use bevy_proto::prelude::*;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Clone, Serialize, Deserialize, ProtoComponent, Component)]
+/// struct Aim {
+///     target: ProtoEntityRef,
+/// }
+///
+/// fn aim(turrets: Query<&Aim>, transforms: Query<&GlobalTransform>) {
+///     for aim in &turrets {
+///         if let Ok(barrel) = transforms.get(aim.target.entity()) {
+///             // ...
+///         }
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ProtoEntityRef {
+    local: String,
+    entity: Entity,
+}
+
+impl ProtoEntityRef {
+    /// Creates an unresolved reference to the entity with the given local name
+    ///
+    /// # Arguments
+    ///
+    /// * `local`: The local name of the entity, without the `@`
+    ///
+    /// returns: ProtoEntityRef
+    pub fn new(local: impl Into<String>) -> Self {
+        Self {
+            local: local.into(),
+            entity: Entity::PLACEHOLDER,
+        }
+    }
+
+    /// The local name of the referenced entity (without the `@`)
+    pub fn local(&self) -> &str {
+        &self.local
+    }
+
+    /// The referenced entity
+    ///
+    /// This is [`Entity::PLACEHOLDER`] until the reference is resolved (see [`ProtoEntityRef`]).
+    pub fn entity(&self) -> Entity {
+        self.entity
+    }
+
+    /// Returns true if this reference has been resolved to a spawned entity
+    pub fn is_resolved(&self) -> bool {
+        self.entity != Entity::PLACEHOLDER
+    }
+}
+
+impl Serialize for ProtoEntityRef {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&format_args!("{}{}", PREFIX, self.local))
+    }
+}
+
+impl<'de> Deserialize<'de> for ProtoEntityRef {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ProtoEntityRefVisitor;
+
+        impl<'de> Visitor<'de> for ProtoEntityRefVisitor {
+            type Value = ProtoEntityRef;
+
+            fn expecting(&self, formatter: &mut Formatter) -> std::fmt::Result {
+                formatter.write_str("an entity reference (such as \"@barrel\")")
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                match v.strip_prefix(PREFIX) {
+                    Some(local) if !local.is_empty() => Ok(ProtoEntityRef::new(local)),
+                    _ => Err(E::invalid_value(de::Unexpected::Str(v), &self)),
+                }
+            }
+        }
+
+        deserializer.deserialize_str(ProtoEntityRefVisitor)
+    }
+}
+
+/// The entities spawned along with a prototype, by their local name (see [`ProtoEntityRef`]).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProtoEntityMap {
+    /// Maps Local Name -> Entity
+    locals: HashMap<String, Entity>,
+}
+
+impl ProtoEntityMap {
+    /// Get the entity with the given local name
+    pub fn get(&self, local: &str) -> Option<Entity> {
+        self.locals.get(local).copied()
+    }
+
+    /// The number of named entities
+    pub fn len(&self) -> usize {
+        self.locals.len()
+    }
+
+    /// Returns true if none of the spawned entities has a local name
+    pub fn is_empty(&self) -> bool {
+        self.locals.is_empty()
+    }
+
+    /// Records the entity with the given local name, unless an entity already has that name
+    pub(crate) fn insert(&mut self, local: &str, entity: Entity) {
+        self.locals.entry(local.to_string()).or_insert(entity);
+    }
+}
+
+/// Describes how to resolve the [`ProtoEntityRef`]s contained within a type.
+///
+/// This is implemented by every type deriving [`ProtoComponent`](bevy_proto_derive::ProtoComponent),
+/// whose generated `insert_self` resolves the references in its clone (with
+/// [`ProtoCommands::map_entity_refs`](crate::data::ProtoCommands::map_entity_refs)) before
+/// inserting it. The derived implementation handles fields which are a [`ProtoEntityRef`], any
+/// other type implementing this trait, or an `Option`, `Vec`, `Box` or array of those. Other
+/// fields are left as they are, so a plain struct nesting a reference has to implement this trait
+/// itself.
+///
+/// # Examples
+///
+/// ```
+/// use bevy::prelude::*;
+/// use bevy_proto::prelude::*;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Clone, Serialize, Deserialize)]
+/// struct Link {
+///     to: ProtoEntityRef,
+///     strength: f32,
+/// }
+///
+/// impl MapEntityRefs for Link {
+///     fn map_entity_refs(&mut self, entities: &ProtoEntityMap) {
+///         self.to.map_entity_refs(entities);
+///     }
+///
+///     fn visit_entity_refs(&self, visitor: &mut dyn FnMut(&ProtoEntityRef)) {
+///         self.to.visit_entity_refs(visitor);
+///     }
+/// }
+///
+/// #[derive(Clone, Serialize, Deserialize, ProtoComponent, Component)]
+/// struct Links(Vec<Link>);
+/// ```
+pub trait MapEntityRefs {
+    /// Resolves each reference to the entity with its local name in the given map
+    ///
+    /// References to names missing from the map are left as they are (with a warning).
+    fn map_entity_refs(&mut self, entities: &ProtoEntityMap);
+    /// Calls the given function on each reference
+    fn visit_entity_refs(&self, visitor: &mut dyn FnMut(&ProtoEntityRef));
+}
+
+impl MapEntityRefs for ProtoEntityRef {
+    fn map_entity_refs(&mut self, entities: &ProtoEntityMap) {
+        match entities.get(&self.local) {
+            Some(entity) => self.entity = entity,
+            None => bevy::log::warn!(
+                "Could not resolve entity reference `@{}`: no entity with that local name was spawned",
+                self.local
+            ),
+        }
+    }
+
+    fn visit_entity_refs(&self, visitor: &mut dyn FnMut(&ProtoEntityRef)) {
+        visitor(self);
+    }
+}
+
+impl<T: MapEntityRefs> MapEntityRefs for Option<T> {
+    fn map_entity_refs(&mut self, entities: &ProtoEntityMap) {
+        if let Some(value) = self {
+            value.map_entity_refs(entities);
+        }
+    }
+
+    fn visit_entity_refs(&self, visitor: &mut dyn FnMut(&ProtoEntityRef)) {
+        if let Some(value) = self {
+            value.visit_entity_refs(visitor);
+        }
+    }
+}
+
+impl<T: MapEntityRefs + ?Sized> MapEntityRefs for Box<T> {
+    fn map_entity_refs(&mut self, entities: &ProtoEntityMap) {
+        self.as_mut().map_entity_refs(entities);
+    }
+
+    fn visit_entity_refs(&self, visitor: &mut dyn FnMut(&ProtoEntityRef)) {
+        self.as_ref().visit_entity_refs(visitor);
+    }
+}
+
+impl<T: MapEntityRefs> MapEntityRefs for [T] {
+    fn map_entity_refs(&mut self, entities: &ProtoEntityMap) {
+        for value in self {
+            value.map_entity_refs(entities);
+        }
+    }
+
+    fn visit_entity_refs(&self, visitor: &mut dyn FnMut(&ProtoEntityRef)) {
+        for value in self {
+            value.visit_entity_refs(visitor);
+        }
+    }
+}
+
+impl<T: MapEntityRefs, const N: usize> MapEntityRefs for [T; N] {
+    fn map_entity_refs(&mut self, entities: &ProtoEntityMap) {
+        self.as_mut_slice().map_entity_refs(entities);
+    }
+
+    fn visit_entity_refs(&self, visitor: &mut dyn FnMut(&ProtoEntityRef)) {
+        self.as_slice().visit_entity_refs(visitor);
+    }
+}
+
+impl<T: MapEntityRefs> MapEntityRefs for Vec<T> {
+    fn map_entity_refs(&mut self, entities: &ProtoEntityMap) {
+        self.as_mut_slice().map_entity_refs(entities);
+    }
+
+    fn visit_entity_refs(&self, visitor: &mut dyn FnMut(&ProtoEntityRef)) {
+        self.as_slice().visit_entity_refs(visitor);
+    }
+}
+
+/// Used by the [`ProtoComponent`](bevy_proto_derive::ProtoComponent) derive to map the fields
+/// implementing [`MapEntityRefs`] and skip all others (through autoref specialization).
+#[doc(hidden)]
+pub mod __private {
+    use super::{MapEntityRefs, ProtoEntityMap, ProtoEntityRef};
+
+    pub struct Field<T>(pub T);
+
+    pub trait MapField {
+        fn __map_entity_refs(self, entities: &ProtoEntityMap);
+    }
+
+    impl<T: MapEntityRefs + ?Sized> MapField for Field<&mut T> {
+        fn __map_entity_refs(self, entities: &ProtoEntityMap) {
+            self.0.map_entity_refs(entities);
+        }
+    }
+
+    pub trait SkipMapField {
+        fn __map_entity_refs(self, _entities: &ProtoEntityMap);
+    }
+
+    impl<T: ?Sized> SkipMapField for &Field<&mut T> {
+        fn __map_entity_refs(self, _entities: &ProtoEntityMap) {}
+    }
+
+    pub trait VisitField {
+        fn __visit_entity_refs(self, visitor: &mut dyn FnMut(&ProtoEntityRef));
+    }
+
+    impl<T: MapEntityRefs + ?Sized> VisitField for Field<&T> {
+        fn __visit_entity_refs(self, visitor: &mut dyn FnMut(&ProtoEntityRef)) {
+            self.0.visit_entity_refs(visitor);
+        }
+    }
+
+    pub trait SkipVisitField {
+        fn __visit_entity_refs(self, _visitor: &mut dyn FnMut(&ProtoEntityRef));
+    }
+
+    impl<T: ?Sized> SkipVisitField for &Field<&T> {
+        fn __visit_entity_refs(self, _visitor: &mut dyn FnMut(&ProtoEntityRef)) {}
+    }
+}
+
+/// Finds the local names referenced by the given component
+pub(crate) fn find_refs(component: &dyn ProtoComponent) -> Vec<String> {
+    let mut refs = Vec::new();
+    component.visit_entity_refs(&mut |entity_ref| refs.push(entity_ref.local.clone()));
+    refs
+}
+
+/// Finds every entity reference which can't be resolved by any of the prototypes spawning it
+///
+/// A component spawned as part of several hierarchies (such as one belonging to a template) only
+/// needs one of them to declare the referenced name.
+///
+/// returns: Vec<(&str, String)> containing the name of the prototype declaring each unresolved
+/// reference along with the local name it references
+pub(crate) fn find_missing_refs(data: &ProtoData) -> Vec<(&str, String)> {
+    // Maps Component Address -> Referenced Names, since each component may be spawned often
+    let mut found = HashMap::<usize, Vec<String>>::new();
+    // Maps (Owner, Referenced Name) -> Whether any hierarchy resolves it
+    let mut refs = HashMap::<(&str, String), bool>::new();
+
+    for proto in data.iter() {
+        let mut locals = HashSet::new();
+        let mut used = Vec::new();
+        walk(
+            proto.as_ref(),
+            proto.name(),
+            data,
+            &mut Vec::new(),
+            &mut locals,
+            &mut used,
+            &mut found,
+        );
+
+        for (owner, local) in used {
+            let resolved = locals.contains(local.as_str());
+            *refs.entry((owner, local)).or_default() |= resolved;
+        }
+    }
+
+    let mut missing = refs
+        .into_iter()
+        .filter(|(_, resolved)| !resolved)
+        .map(|(key, _)| key)
+        .collect::<Vec<_>>();
+    missing.sort();
+    return missing;
+
+    /// Records the local names declared within the hierarchy of the given prototype, along with
+    /// the names referenced by its components (and who declared them)
+    ///
+    /// `owner` is the name of the prototype the given one was declared in (which differs for
+    /// inline children).
+    fn walk<'a>(
+        proto: &'a dyn Prototypical,
+        owner: &'a str,
+        data: &'a ProtoData,
+        ancestors: &mut Vec<&'a str>,
+        locals: &mut HashSet<&'a str>,
+        used: &mut Vec<(&'a str, String)>,
+        found: &mut HashMap<usize, Vec<String>>,
+    ) {
+        if let Some(local) = proto.local() {
+            locals.insert(local);
+        }
+
+        let mut children = Vec::new();
+        collect(
+            proto,
+            owner,
+            data,
+            &mut IndexSet::default(),
+            &mut children,
+            &mut |owner, component| {
+                let address = component as *const dyn ProtoComponent as *const () as usize;
+                let refs = found.entry(address).or_insert_with(|| find_refs(component));
+                used.extend(refs.iter().map(|local| (owner, local.clone())));
+            },
+        );
+
+        ancestors.push(proto.name());
+        for (owner, child) in children {
+            match child {
+                ProtoChild::Reference(name) => {
+                    // Missing children and cycles are reported elsewhere
+                    if let Some(child) = data.get_prototype(name) {
+                        if !ancestors.contains(&child.name()) {
+                            walk(child, child.name(), data, ancestors, locals, used, found);
+                        }
+                    }
+                }
+                ProtoChild::Inline(child) => {
+                    walk(child.as_ref(), owner, data, ancestors, locals, used, found);
+                }
+            }
+        }
+        ancestors.pop();
+    }
+
+    /// Calls `func` on each component spawned by the given prototype (including those of its
+    /// templates) and collects its children, each along with the name of the prototype which
+    /// declared it
+    fn collect<'a>(
+        proto: &'a dyn Prototypical,
+        owner: &'a str,
+        data: &'a ProtoData,
+        traversed: &mut IndexSet<&'a str>,
+        children: &mut Vec<(&'a str, &'a ProtoChild)>,
+        func: &mut dyn FnMut(&'a str, &'a dyn ProtoComponent),
+    ) {
+        traversed.insert(proto.name());
+
+        for template in proto.templates_rev() {
+            if traversed.contains(template.as_str()) {
+                continue;
+            }
+
+            if let Some(parent) = data.get_prototype(template) {
+                collect(parent, parent.name(), data, traversed, children, func);
+            }
+        }
+
+        for component in proto.iter_components() {
+            func(owner, component.as_ref());
+        }
+        children.extend(proto.children().iter().map(|child| (owner, child)));
+    }
+}
@@ -0,0 +1,416 @@
+//! Provides the error types produced while loading prototype data.
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+
+use bevy::ecs::system::Resource;
+
+use crate::data::ProtoSource;
+
+/// An error produced by a [`ProtoDeserializer`](crate::data::ProtoDeserializer) when
+/// the given data could not be turned into a prototype.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProtoDeserializeError {
+    /// A human-readable description of the error.
+    pub message: String,
+    /// The line (starting at 1) at which the error occurred, if known.
+    pub line: Option<usize>,
+    /// The column (starting at 1) at which the error occurred, if known.
+    pub column: Option<usize>,
+    /// The `type` of the component that failed to deserialize, if the error could be
+    /// narrowed down to a single component.
+    pub component: Option<String>,
+}
+
+impl ProtoDeserializeError {
+    /// Creates a new error with the given message and no location information.
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            line: None,
+            column: None,
+            component: None,
+        }
+    }
+
+    /// Sets the line and column at which this error occurred.
+    pub fn with_location(mut self, line: usize, column: usize) -> Self {
+        self.line = Some(line);
+        self.column = Some(column);
+        self
+    }
+
+    /// Sets the `type` of the component responsible for this error.
+    pub fn with_component(mut self, component: Option<String>) -> Self {
+        self.component = component;
+        self
+    }
+}
+
+impl Display for ProtoDeserializeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)?;
+        if let Some(component) = &self.component {
+            write!(f, " (in component `{}`)", component)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ProtoDeserializeError {}
+
+/// An error produced when a prototype's [`ProtoParams`](crate::ProtoParams) (or random values)
+/// could not be substituted into one of its components.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProtoParamError {
+    /// The name of the prototype being spawned.
+    pub prototype: String,
+    /// The `type` of the component referencing the params.
+    pub component: String,
+    /// The kind of error.
+    pub kind: ProtoParamErrorKind,
+}
+
+impl Display for ProtoParamError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "could not spawn prototype `{}`: {} (in component `{}`)",
+            self.prototype, self.kind, self.component
+        )
+    }
+}
+
+impl std::error::Error for ProtoParamError {}
+
+/// The kinds of [`ProtoParamError`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProtoParamErrorKind {
+    /// A referenced param was neither given nor declared with a default.
+    Missing {
+        /// The name of the param.
+        param: String,
+    },
+    /// A param's value can't be used where it's referenced.
+    InvalidType {
+        /// The name of the param.
+        param: String,
+        /// The kinds of values accepted there.
+        expected: &'static str,
+    },
+    /// An expression could not be evaluated.
+    InvalidExpression {
+        /// The expression.
+        expression: String,
+        /// Why the expression could not be evaluated.
+        reason: String,
+    },
+    /// A random value (such as `{ $range: [8, 12] }`) could not be evaluated.
+    InvalidRandom {
+        /// The key of the random value (such as `$range`).
+        node: String,
+        /// Why the random value could not be evaluated.
+        reason: String,
+    },
+    /// The component could not be deserialized once the params were substituted.
+    Deserialize(String),
+}
+
+impl Display for ProtoParamErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Missing { param } => write!(f, "missing param `{}`", param),
+            Self::InvalidType { param, expected } => {
+                write!(f, "param `{}` must be {}", param, expected)
+            }
+            Self::InvalidExpression { expression, reason } => {
+                write!(f, "invalid expression `{}`: {}", expression, reason)
+            }
+            Self::InvalidRandom { node, reason } => write!(f, "invalid `{}`: {}", node, reason),
+            Self::Deserialize(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<serde_yaml::Error> for ProtoDeserializeError {
+    fn from(err: serde_yaml::Error) -> Self {
+        let error = Self::new(err.to_string());
+        match err.location() {
+            Some(location) => error.with_location(location.line(), location.column()),
+            None => error,
+        }
+    }
+}
+
+#[cfg(feature = "json")]
+impl From<serde_json::Error> for ProtoDeserializeError {
+    fn from(err: serde_json::Error) -> Self {
+        Self::new(err.to_string()).with_location(err.line(), err.column())
+    }
+}
+
+#[cfg(feature = "ron")]
+impl From<ron::error::SpannedError> for ProtoDeserializeError {
+    fn from(err: ron::error::SpannedError) -> Self {
+        Self::new(err.to_string()).with_location(err.position.line, err.position.col)
+    }
+}
+
+/// The reason a prototype file failed to load.
+#[derive(Debug)]
+pub enum ProtoLoadErrorKind {
+    /// The file (or directory) could not be read.
+    Io(std::io::Error),
+    /// The file contents could not be deserialized.
+    Deserialize(ProtoDeserializeError),
+    /// The file defines a prototype whose name is already used by a prototype in another file.
+    ///
+    /// This is only recorded when using [`DuplicatePolicy::Error`](crate::data::DuplicatePolicy::Error).
+    Duplicate {
+        /// The name of the prototype.
+        name: String,
+        /// The location of the original prototype.
+        original: ProtoSource,
+    },
+    /// The file contains a patch for a prototype which does not exist (in its own or an earlier layer).
+    MissingPatchTarget {
+        /// The name of the prototype being patched.
+        target: String,
+    },
+    /// The file contains a patch for a prototype whose components can't be modified.
+    UnpatchableTarget {
+        /// The name of the prototype being patched.
+        target: String,
+    },
+    /// The file contains a [`ProtoTable`](crate::ProtoTable) which is invalid (such as one that
+    /// references a prototype that does not exist).
+    InvalidTable {
+        /// The name of the table.
+        table: String,
+        /// Why the table is invalid.
+        reason: String,
+    },
+    /// The file contains a prototype with a component referencing an entity by a local name
+    /// which none of the prototypes spawning that component declare (see
+    /// [`ProtoEntityRef`](crate::ProtoEntityRef)).
+    MissingEntityRef {
+        /// The name of the prototype containing the reference.
+        prototype: String,
+        /// The referenced local name.
+        local: String,
+    },
+    /// The prototype pack could not be loaded.
+    #[cfg(feature = "pack")]
+    Pack(ProtoPackError),
+}
+
+impl Display for ProtoLoadErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "could not be read: {}", err),
+            Self::Deserialize(err) => write!(f, "could not be deserialized: {}", err),
+            Self::Duplicate { name, original } => write!(
+                f,
+                "defines a prototype named `{}`, which was already defined in {}",
+                name, original
+            ),
+            Self::MissingPatchTarget { target } => write!(
+                f,
+                "patches prototype `{}`, which does not exist in this or an earlier layer",
+                target
+            ),
+            Self::UnpatchableTarget { target } => write!(
+                f,
+                "patches prototype `{}`, whose components can't be modified",
+                target
+            ),
+            Self::InvalidTable { table, reason } => {
+                write!(f, "defines an invalid table `{}`: {}", table, reason)
+            }
+            Self::MissingEntityRef { prototype, local } => write!(
+                f,
+                "defines prototype `{}`, which references an entity named `@{}` that is never spawned with it",
+                prototype, local
+            ),
+            #[cfg(feature = "pack")]
+            Self::Pack(err) => write!(f, "could not be loaded as a prototype pack: {}", err),
+        }
+    }
+}
+
+/// An error that occurred while loading a single prototype file.
+#[derive(Debug)]
+pub struct ProtoLoadError {
+    /// The path of the file that failed to load.
+    pub path: PathBuf,
+    /// The reason the file failed to load.
+    pub kind: ProtoLoadErrorKind,
+}
+
+impl ProtoLoadError {
+    /// Creates a new [`ProtoLoadError`] for the given path.
+    pub fn new(path: impl Into<PathBuf>, kind: ProtoLoadErrorKind) -> Self {
+        Self {
+            path: path.into(),
+            kind,
+        }
+    }
+}
+
+impl Display for ProtoLoadError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Prototype file {:?} {}", self.path, self.kind)
+    }
+}
+
+impl std::error::Error for ProtoLoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.kind {
+            ProtoLoadErrorKind::Io(err) => Some(err),
+            ProtoLoadErrorKind::Deserialize(err) => Some(err),
+            ProtoLoadErrorKind::Duplicate { .. }
+            | ProtoLoadErrorKind::MissingPatchTarget { .. }
+            | ProtoLoadErrorKind::UnpatchableTarget { .. }
+            | ProtoLoadErrorKind::InvalidTable { .. }
+            | ProtoLoadErrorKind::MissingEntityRef { .. } => None,
+            #[cfg(feature = "pack")]
+            ProtoLoadErrorKind::Pack(err) => Some(err),
+        }
+    }
+}
+
+/// An error produced while reading or writing a [`ProtoPack`](crate::pack::ProtoPack).
+#[cfg(feature = "pack")]
+#[derive(Debug)]
+pub enum ProtoPackError {
+    /// The pack file could not be read or written.
+    Io(std::io::Error),
+    /// The data does not start with a valid pack header.
+    InvalidHeader,
+    /// The pack was written with a different version of the pack format (contained in the error).
+    UnsupportedVersion(u32),
+    /// The pack contents do not match its checksum.
+    ChecksumMismatch,
+    /// The pack contents could not be encoded or decoded.
+    Encoding(bincode::Error),
+}
+
+#[cfg(feature = "pack")]
+impl Display for ProtoPackError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "{}", err),
+            Self::InvalidHeader => write!(f, "invalid pack header"),
+            Self::UnsupportedVersion(version) => write!(
+                f,
+                "unsupported pack version {} (expected {})",
+                version,
+                crate::pack::PACK_VERSION
+            ),
+            Self::ChecksumMismatch => write!(f, "checksum mismatch (the pack may be corrupted)"),
+            Self::Encoding(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+#[cfg(feature = "pack")]
+impl std::error::Error for ProtoPackError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::Encoding(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "pack")]
+impl From<std::io::Error> for ProtoPackError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+#[cfg(feature = "pack")]
+impl From<bincode::Error> for ProtoPackError {
+    fn from(err: bincode::Error) -> Self {
+        Self::Encoding(err)
+    }
+}
+
+/// A resource containing every error encountered while loading prototype files.
+///
+/// Each error is also logged as it is recorded.
+///
+/// # Examples
+///
+/// ```
+/// use bevy::prelude::*;
+/// use bevy_proto::prelude::*;
+///
+/// fn report_errors(errors: Res<ProtoLoadErrors>) {
+///     for error in errors.iter() {
+///         println!("{} failed to load: {}", error.path.display(), error.kind);
+///     }
+/// }
+/// ```
+#[derive(Resource, Debug, Default)]
+pub struct ProtoLoadErrors {
+    errors: Vec<ProtoLoadError>,
+}
+
+impl ProtoLoadErrors {
+    /// Get an iterator over all recorded errors
+    pub fn iter(&self) -> impl Iterator<Item = &ProtoLoadError> {
+        self.errors.iter()
+    }
+
+    /// Get an iterator over the errors recorded for the given file
+    pub fn for_path<'a>(&'a self, path: &'a Path) -> impl Iterator<Item = &'a ProtoLoadError> {
+        self.errors.iter().filter(move |err| err.path == path)
+    }
+
+    /// The number of recorded errors
+    pub fn len(&self) -> usize {
+        self.errors.len()
+    }
+
+    /// Returns true if no errors were recorded
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Logs and records the given error
+    pub(crate) fn push(&mut self, error: ProtoLoadError) {
+        bevy::log::error!("{}", error);
+        self.errors.push(error);
+    }
+
+    /// Removes all errors recorded for the given file
+    pub(crate) fn clear_path(&mut self, path: &Path) {
+        self.errors.retain(|err| err.path != path);
+    }
+
+    /// Replaces the errors found by checking every prototype and table (see
+    /// [`check_errors`](crate::data::check_errors)) before a reload (or a change to a
+    /// [`PrototypeAsset`](crate::PrototypeAsset)) with those found after it
+    ///
+    /// Errors found both times are kept as they are, so they aren't logged again.
+    pub(crate) fn replace_checks(
+        &mut self,
+        previous: Vec<ProtoLoadError>,
+        current: Vec<ProtoLoadError>,
+    ) {
+        let same = |a: &ProtoLoadError, b: &ProtoLoadError| {
+            a.path == b.path && a.kind.to_string() == b.kind.to_string()
+        };
+        self.errors.retain(|err| {
+            !previous.iter().any(|previous| same(previous, err))
+                || current.iter().any(|current| same(current, err))
+        });
+        for error in current {
+            if !self.errors.iter().any(|err| same(err, &error)) {
+                self.push(error);
+            }
+        }
+    }
+}
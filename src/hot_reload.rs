@@ -1,8 +1,28 @@
-use bevy::prelude::{App, Plugin, Res, ResMut, Resource};
+use std::any::{Any, TypeId};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+use bevy::ecs::system::SystemState;
+use bevy::prelude::{
+    App, AssetServer, Commands, Entity, Mut, Parent, Plugin, Query, Res, Resource, World,
+};
+use bevy::utils::{HashMap, HashSet};
 use crossbeam_channel::Receiver;
-use notify::{Config, Event, RecommendedWatcher, RecursiveMode, Result, Watcher};
+use notify::{Config, Event, EventKind, RecommendedWatcher, RecursiveMode, Result, Watcher};
+
+use indexmap::IndexSet;
 
+use crate::children::ProtoChildren;
+use crate::components::ProtoComponent;
+#[cfg(feature = "analysis")]
+use crate::data::analyze_deps;
+use crate::data::{check_errors, collect_paths, read_file, register_file, FileResult};
+use crate::entity_ref::{find_refs, ProtoEntityMap};
+use crate::errors::ProtoLoadErrors;
+use crate::instance::{mark_instance, ProtoInstance};
+use crate::params;
 use crate::prelude::{ProtoData, ProtoDataOptions};
+use crate::prototype::{spawn_internal, Prototypical};
 
 // Copied from bevy_asset's implementation
 // https://github.com/bevyengine/bevy/blob/main/crates/bevy_asset/src/filesystem_watcher.rs
@@ -13,9 +33,9 @@ struct FilesystemWatcher {
 }
 
 impl FilesystemWatcher {
-    /// Watch for changes recursively at the provided path.
-    fn watch<P: AsRef<std::path::Path>>(&mut self, path: P) -> Result<()> {
-        self.watcher.watch(path.as_ref(), RecursiveMode::Recursive)
+    /// Watch for changes at the provided path.
+    fn watch<P: AsRef<Path>>(&mut self, path: P, mode: RecursiveMode) -> Result<()> {
+        self.watcher.watch(path.as_ref(), mode)
     }
 }
 
@@ -35,51 +55,699 @@ impl Default for FilesystemWatcher {
 
 // Copied from bevy_asset's filesystem watching implementation:
 // https://github.com/bevyengine/bevy/blob/main/crates/bevy_asset/src/io/file_asset_io.rs#L167-L199
-fn watch_for_changes(
-    watcher: Res<FilesystemWatcher>,
-    mut proto_data: ResMut<ProtoData>,
-    options: Res<ProtoDataOptions>,
-) {
-    let mut changed = bevy::utils::HashSet::default();
+fn watch_for_changes(world: &mut World) {
+    let mut changed = BTreeSet::new();
+    let watcher = world.resource::<FilesystemWatcher>();
     loop {
         let event = match watcher.receiver.try_recv() {
-            Ok(result) => result.unwrap(),
+            Ok(Ok(event)) => event,
+            Ok(Err(err)) => {
+                bevy::log::error!("Failed to watch prototype files: {}", err);
+                continue;
+            }
             Err(crossbeam_channel::TryRecvError::Empty) => break,
             Err(crossbeam_channel::TryRecvError::Disconnected) => {
                 panic!("FilesystemWatcher disconnected.")
             }
         };
-        if let notify::event::Event {
-            kind: notify::event::EventKind::Modify(_),
-            paths,
-            ..
-        } = event
+        // Renames are reported as a removal of the old path and a creation of the new one
+        if let EventKind::Any | EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_) =
+            event.kind
         {
-            for path in &paths {
-                if !changed.contains(path) {
-                    if let Ok(data) = std::fs::read_to_string(path) {
-                        if let Some(proto) = options.deserializer.deserialize(&data) {
-                            proto_data
-                                .prototypes
-                                .insert(proto.name().to_string(), proto);
-                        }
-                    }
+            changed.extend(event.paths);
+        }
+    }
+
+    reload_changed(world, &changed);
+}
+
+/// Reloads the prototypes and tables at the given changed paths, then updates the live entities
+/// spawned from them
+fn reload_changed(world: &mut World, changed: &BTreeSet<PathBuf>) {
+    let options = world.resource::<ProtoDataOptions>().clone();
+    let changed = changed
+        .iter()
+        .filter_map(|path| options.loadable_path(path))
+        .collect::<BTreeSet<_>>();
+    if changed.is_empty() {
+        return;
+    }
+
+    world.resource_scope(|world, mut proto_data: Mut<ProtoData>| {
+        world.resource_scope(|world, mut errors: Mut<ProtoLoadErrors>| {
+            world.resource_scope(|world, mut reloaded: Mut<ReloadedPrototypes>| {
+                reload_paths(
+                    world,
+                    &changed,
+                    &mut proto_data,
+                    &mut errors,
+                    &options,
+                    &mut reloaded,
+                );
+            });
+        });
+    });
+
+    // Instances are only updated once every reloaded prototype has been patched and merged
+    reload_instances(world);
+}
+
+/// Reloads everything at the given paths (files or directories), removing the prototypes and
+/// tables that were previously loaded from them but no longer are
+///
+/// The files are loaded just as they are by [`ProtoData::reload_all`]. Since patches are applied
+/// on top of the prototypes they patch, the files defining those prototypes (along with every
+/// other patch to them) are reloaded too.
+fn reload_paths(
+    world: &mut World,
+    changed: &BTreeSet<PathBuf>,
+    proto_data: &mut ProtoData,
+    errors: &mut ProtoLoadErrors,
+    options: &ProtoDataOptions,
+    reloaded: &mut ReloadedPrototypes,
+) {
+    // === Find Files === //
+    let mut files = BTreeSet::new();
+    for path in changed {
+        errors.clear_path(path);
+        files.extend(
+            proto_data
+                .sources
+                .values()
+                .chain(proto_data.table_sources.values())
+                .chain(proto_data.patch_sources.values().flatten())
+                .filter(|source| source.path.starts_with(path))
+                .map(|source| source.path.clone()),
+        );
+
+        if path.is_dir() {
+            let mut paths = Vec::new();
+            let mut dir_errors = Vec::new();
+            collect_paths(options, path, &mut paths, &mut dir_errors);
+            for err in dir_errors {
+                errors.push(err);
+            }
+            files.extend(paths);
+        } else if path.is_file() {
+            files.insert(path.clone());
+        }
+    }
+
+    let mut results = BTreeMap::new();
+    loop {
+        for path in &files {
+            if path.is_file() && !results.contains_key(path) {
+                results.insert(path.clone(), read_file(options, path));
+            }
+        }
+
+        let related = related_files(proto_data, &files, results.values());
+        let count = files.len();
+        files.extend(related);
+        if files.len() == count {
+            break;
+        }
+    }
+
+    // === Remove Previous Contents === //
+    let previous_checks = check_errors(proto_data);
+    let previous = proto_data
+        .sources
+        .iter()
+        .filter(|(_, source)| files.contains(&source.path))
+        .map(|(name, _)| name.clone())
+        .collect::<Vec<_>>();
+    for name in previous {
+        let removed = proto_data.remove_prototype(&name);
+        if options.reload_instances {
+            // Only the version before this frame's first reload matters
+            reloaded.previous.entry(name).or_insert(removed);
+        }
+    }
+    let previous_tables = proto_data
+        .table_sources
+        .iter()
+        .filter(|(_, source)| files.contains(&source.path))
+        .map(|(name, _)| name.clone())
+        .collect::<Vec<_>>();
+    for name in previous_tables {
+        proto_data.remove_table(&name);
+    }
+    for sources in proto_data.patch_sources.values_mut() {
+        sources.retain(|source| !files.contains(&source.path));
+    }
+    for path in &files {
+        errors.clear_path(path);
+    }
+
+    // === Load Current Contents === //
+    // Files are loaded in the same order as the directories they're in
+    let mut results = results.into_iter().collect::<Vec<_>>();
+    results.sort_by_key(|(path, _)| options.layer_of(path));
+    for (path, result) in results {
+        register_file(world, proto_data, errors, options, path, result);
+    }
+    proto_data.apply_patches(world, errors, options, None);
+    proto_data.resolve_merges(world, errors);
+    errors.replace_checks(previous_checks, check_errors(proto_data));
+
+    #[cfg(feature = "analysis")]
+    analyze_deps(proto_data);
+}
+
+/// Finds the files which must be reloaded along with the given ones: those defining the
+/// prototypes patched in the given files, and those patching the prototypes defined in them
+fn related_files<'a>(
+    proto_data: &ProtoData,
+    files: &BTreeSet<PathBuf>,
+    results: impl Iterator<Item = &'a FileResult>,
+) -> Vec<PathBuf> {
+    // The prototypes defined or patched by these files, before and after they're reloaded
+    let mut names = proto_data
+        .sources
+        .iter()
+        .filter(|(_, source)| files.contains(&source.path))
+        .map(|(name, _)| name.as_str())
+        .chain(
+            proto_data
+                .patch_sources
+                .iter()
+                .filter(|(_, sources)| sources.iter().any(|source| files.contains(&source.path)))
+                .map(|(name, _)| name.as_str()),
+        )
+        .collect::<HashSet<_>>();
+    for result in results {
+        if let FileResult::Prototypes(Ok(prototypes)) = result {
+            // Any prototype defined here may be the target of a patch that was missing it
+            names.extend(prototypes.iter().flatten().map(|proto| {
+                match proto
+                    .patch()
+                    .and_then(|target| proto_data.get_prototype(target))
+                {
+                    Some(target) => target.name(),
+                    None => proto.name(),
                 }
+            }));
+        }
+    }
+
+    names
+        .into_iter()
+        .flat_map(|name| {
+            proto_data
+                .sources
+                .get(name)
+                .into_iter()
+                .chain(proto_data.patch_sources.get(name).into_iter().flatten())
+        })
+        .map(|source| source.path.clone())
+        .filter(|path| !files.contains(path))
+        .collect()
+}
+
+/// The prototypes reloaded since the last run of [`reload_instances`]
+#[derive(Resource, Default)]
+struct ReloadedPrototypes {
+    /// Maps Prototype Name -> Version before reloading (if it existed)
+    previous: HashMap<String, Option<Box<dyn Prototypical>>>,
+}
+
+/// Updates the live entities spawned from each reloaded prototype (or from a prototype using
+/// it as a template), as described by [`ProtoDataOptions::reload_instances`]
+///
+/// This runs at the end of [`watch_for_changes`], once the reloaded files have been through the
+/// whole load pipeline.
+fn reload_instances(world: &mut World) {
+    let mut previous = match world.get_resource_mut::<ReloadedPrototypes>() {
+        Some(mut reloaded) if !reloaded.previous.is_empty() => {
+            std::mem::take(&mut reloaded.previous)
+        }
+        _ => return,
+    };
+    if !world.contains_resource::<AssetServer>() {
+        return;
+    }
+
+    let options = world.resource::<ProtoDataOptions>();
+    let preserved = options
+        .preserve_on_reload
+        .iter()
+        .map(|name| options.registry.canonical_name(name))
+        .collect::<Vec<_>>();
+    let preserved = preserved.iter().map(String::as_str).collect::<Vec<_>>();
+
+    let mut state = SystemState::<(
+        Commands,
+        Res<ProtoData>,
+        Res<AssetServer>,
+        Query<(Entity, &ProtoInstance)>,
+        Query<&Parent>,
+        Query<(&ProtoChildren, Option<&ProtoInstance>)>,
+    )>::new(world);
+
+    // === Find Instances === //
+    // Maps Prototype Name -> Entities to update
+    let mut instances = HashMap::<String, Vec<Entity>>::new();
+    let (_, data, _, query, parents, hierarchies) = state.get(world);
+    for (entity, instance) in &query {
+        let reloaded = previous.contains_key(&instance.name)
+            || instance
+                .templates
+                .iter()
+                .any(|template| previous.contains_key(template));
+        if reloaded {
+            instances
+                .entry(instance.name.clone())
+                .or_default()
+                .push(entity);
+        }
+    }
+    if instances.is_empty() {
+        return;
+    }
+
+    // Maps Entity -> Entities spawned along with it, by their local name
+    let entity_maps = instances
+        .values()
+        .flatten()
+        .map(|entity| (*entity, entity_map(*entity, &parents, &hierarchies, &data)))
+        .collect::<HashMap<_, _>>();
+
+    // === Find Declared Components === //
+    let data = world.resource::<ProtoData>();
+    let declared = instances
+        .keys()
+        .map(|name| {
+            let keys = data
+                .get_prototype(name)
+                .map(|proto| {
+                    flatten_components(proto, data, &preserved)
+                        .into_iter()
+                        .map(|component| ComponentKey::of(component, data))
+                        .collect::<HashSet<_>>()
+                })
+                .unwrap_or_default();
+            (name.clone(), keys)
+        })
+        .collect::<HashMap<_, _>>();
+
+    // === Remove Undeclared Components === //
+    // The previous versions are swapped back in, so that they can remove their own components
+    swap_previous(&mut world.resource_mut::<ProtoData>(), &mut previous);
+    let (mut commands, data, ..) = state.get_mut(world);
+    for (name, entities) in &instances {
+        let proto = match data.get_prototype(name) {
+            Some(proto) => proto,
+            None => continue,
+        };
+        for component in flatten_components(proto, &data, &preserved) {
+            if declared[name].contains(&ComponentKey::of(component, &data)) {
+                continue;
+            }
+            for entity in entities {
+                let mut proto_commands = proto.create_commands(commands.entity(*entity), &data);
+                component.remove_self(&mut proto_commands);
             }
-            changed.extend(paths);
         }
     }
+    swap_previous(&mut world.resource_mut::<ProtoData>(), &mut previous);
+
+    // === Insert Declared Components === //
+    let (mut commands, data, asset_server, ..) = state.get_mut(world);
+    for (name, entities) in &instances {
+        let proto = match data.get_prototype(name) {
+            Some(proto) => proto,
+            None => continue,
+        };
+        let resolved = params::resolve_defaults(proto, &data);
+
+        // Without its hierarchy, an entity keeps the components referencing other entities
+        let with_refs = flatten_components(proto, &data, &preserved)
+            .into_iter()
+            .filter(|component| !find_refs(*component).is_empty())
+            .filter_map(|component| data.registry.name_of(component))
+            .collect::<Vec<_>>();
+        let preserved_with_refs = preserved
+            .iter()
+            .copied()
+            .chain(with_refs.iter().map(String::as_str))
+            .collect::<Vec<_>>();
+
+        for entity in entities {
+            let entity_map = entity_maps[entity].as_ref();
+            let mut entity = commands.entity(*entity);
+            mark_instance(proto, &mut entity, &data);
+
+            let mut proto_commands = proto.create_commands(entity, &data);
+            proto_commands.resolved = Some(&resolved);
+            proto_commands.entities = entity_map;
+            spawn_internal(
+                proto,
+                &mut proto_commands,
+                &data,
+                &asset_server,
+                &mut IndexSet::default(),
+                if entity_map.is_some() {
+                    &preserved
+                } else {
+                    &preserved_with_refs
+                },
+            );
+        }
+    }
+    state.apply(world);
+}
+
+/// Rebuilds the local names of the hierarchy the given entity was spawned as part of, from the
+/// [`ProtoChildren`] inserted into the root of that hierarchy
+///
+/// returns: Option<ProtoEntityMap> which is `None` if the entity's hierarchy can't be found
+fn entity_map(
+    entity: Entity,
+    parents: &Query<&Parent>,
+    hierarchies: &Query<(&ProtoChildren, Option<&ProtoInstance>)>,
+    data: &ProtoData,
+) -> Option<ProtoEntityMap> {
+    let mut current = entity;
+    loop {
+        if let Ok((children, instance)) = hierarchies.get(current) {
+            if current == entity || children.entities().any(|child| child == entity) {
+                let mut entities = ProtoEntityMap::default();
+                let local = instance
+                    .and_then(|instance| data.get_prototype(&instance.name))
+                    .and_then(|proto| proto.local());
+                if let Some(local) = local {
+                    entities.insert(local, current);
+                }
+                children.extend_entity_map(&mut entities);
+                return Some(entities);
+            }
+        }
+        current = parents.get(current).ok()?.get();
+    }
+}
+
+/// Identifies a component by its `type` name (or by its concrete type, if it isn't named)
+///
+/// Reflected components are identified by the full type path they resolved to, so the same
+/// component is matched whether it was given by its name or by its path.
+#[derive(PartialEq, Eq, Hash)]
+enum ComponentKey {
+    Named(String),
+    Unnamed(TypeId),
+}
+
+impl ComponentKey {
+    fn of(component: &dyn ProtoComponent, data: &ProtoData) -> Self {
+        match data.registry.name_of(component) {
+            Some(name) => Self::Named(name),
+            None => {
+                let component: &dyn Any = component;
+                Self::Unnamed(component.type_id())
+            }
+        }
+    }
+}
+
+/// Collects the components inserted by the given prototype (including those of its templates),
+/// skipping those whose `type` name is in `excluded`
+fn flatten_components<'a>(
+    proto: &'a dyn Prototypical,
+    data: &'a ProtoData,
+    excluded: &[&'a str],
+) -> Vec<&'a dyn ProtoComponent> {
+    let mut components = Vec::new();
+    collect(
+        proto,
+        data,
+        &mut IndexSet::default(),
+        excluded,
+        &mut components,
+    );
+    return components;
+
+    fn collect<'a>(
+        proto: &'a dyn Prototypical,
+        data: &'a ProtoData,
+        traversed: &mut IndexSet<&'a str>,
+        excluded: &[&'a str],
+        components: &mut Vec<&'a dyn ProtoComponent>,
+    ) {
+        traversed.insert(proto.name());
+
+        let template_excluded = excluded
+            .iter()
+            .copied()
+            .chain(proto.excluded_components().iter().map(String::as_str))
+            .collect::<Vec<_>>();
+        for template in proto.templates_rev() {
+            // Cycles are reported when spawning
+            if traversed.contains(template.as_str()) {
+                continue;
+            }
+            if let Some(parent) = data.get_prototype(template) {
+                collect(parent, data, traversed, &template_excluded, components);
+            }
+        }
+
+        components.extend(
+            proto
+                .iter_components()
+                .map(AsRef::as_ref)
+                .filter(|component| !data.is_excluded(*component, excluded)),
+        );
+    }
+}
+
+/// Swaps each of the given prototypes with the one of the same name in `data` (calling this
+/// twice restores the original prototypes)
+fn swap_previous(
+    data: &mut ProtoData,
+    previous: &mut HashMap<String, Option<Box<dyn Prototypical>>>,
+) {
+    for (name, proto) in previous.iter_mut() {
+        let current = data.prototypes.remove(name);
+        if let Some(proto) = proto.take() {
+            data.prototypes.insert(name.clone(), proto);
+        }
+        *proto = current;
+    }
 }
 
 pub(crate) struct HotReloadPlugin {
-    pub(crate) path: String,
+    pub(crate) directories: Vec<String>,
+    pub(crate) recursive: bool,
 }
 
 impl Plugin for HotReloadPlugin {
     fn build(&self, app: &mut App) {
+        let mode = if self.recursive {
+            RecursiveMode::Recursive
+        } else {
+            RecursiveMode::NonRecursive
+        };
+
         let mut watcher = FilesystemWatcher::default();
-        watcher.watch(self.path.clone()).unwrap();
+        for directory in &self.directories {
+            // Directories that can't be read are already reported when loading
+            if let Err(err) = watcher.watch(directory, mode) {
+                bevy::log::warn!("Could not watch `{}` for changes: {}", directory, err);
+            }
+        }
+
+        app.insert_resource(watcher)
+            .init_resource::<ReloadedPrototypes>()
+            .add_system(watch_for_changes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use bevy::asset::FileAssetIo;
+    use bevy::prelude::{Component, FromWorld};
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+    use crate::data::ProtoCommands;
+    use crate::entity_ref::{MapEntityRefs, ProtoEntityRef};
+    use crate::errors::ProtoLoadErrorKind;
 
-        app.insert_resource(watcher).add_system(watch_for_changes);
+    #[derive(Component, Clone, Serialize, Deserialize)]
+    struct ReloadAim {
+        target: ProtoEntityRef,
+        speed: f32,
+    }
+
+    #[cfg_attr(feature = "typetag", typetag::serde)]
+    impl ProtoComponent for ReloadAim {
+        fn insert_self(&self, commands: &mut ProtoCommands, _: &Res<AssetServer>) {
+            let mut component = self.clone();
+            commands.map_entity_refs(&mut component.target);
+            commands.insert(component);
+        }
+
+        fn visit_entity_refs(&self, visitor: &mut dyn FnMut(&ProtoEntityRef)) {
+            self.target.visit_entity_refs(visitor);
+        }
+    }
+
+    #[derive(Clone, Serialize, Deserialize)]
+    struct ReloadHealth(u32);
+
+    #[cfg_attr(feature = "typetag", typetag::serde)]
+    impl ProtoComponent for ReloadHealth {
+        fn insert_self(&self, _: &mut ProtoCommands, _: &Res<AssetServer>) {}
+    }
+
+    /// Creates an empty directory for the prototype files of the test with the given name
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir()
+            .join(format!("bevy_proto_{}", std::process::id()))
+            .join(name);
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Loads the prototypes in the given directory into a new world, reloading instances
+    fn load(dir: &Path) -> World {
+        let mut world = World::new();
+        world.insert_resource(AssetServer::new(FileAssetIo::new(dir, false)));
+        let options = ProtoDataOptions {
+            directories: vec![dir.to_string_lossy().to_string()],
+            reload_instances: true,
+            ..Default::default()
+        };
+        options.registry.register::<ReloadAim>("ReloadAim");
+        options.registry.register::<ReloadHealth>("ReloadHealth");
+        world.insert_resource(options);
+        world.init_resource::<ReloadedPrototypes>();
+        let data = ProtoData::from_world(&mut world);
+        world.insert_resource(data);
+        world
+    }
+
+    #[test]
+    fn reload_resolves_entity_refs() {
+        let dir = test_dir("reload_resolves_entity_refs");
+        let path = dir.join("turret.yaml");
+        let file = |speed: f32| {
+            format!(
+                "name: Turret\ncomponents:\n  - type: ReloadAim\n    value:\n      target: \"@barrel\"\n      speed: {:?}\nchildren:\n  - name: Barrel\n    local: barrel\n",
+                speed
+            )
+        };
+        fs::write(&path, file(1.0)).unwrap();
+        let mut world = load(&dir);
+
+        let mut state =
+            SystemState::<(Commands, Res<ProtoData>, Res<AssetServer>)>::new(&mut world);
+        let (mut commands, data, asset_server) = state.get_mut(&mut world);
+        let proto = data.get_prototype("Turret").unwrap();
+        let (entity, children) = proto.spawn_hierarchy(&mut commands, &data, &asset_server);
+        let (turret, barrel) = (entity.id(), children.local("barrel").unwrap());
+        state.apply(&mut world);
+        assert_eq!(
+            world.get::<ReloadAim>(turret).unwrap().target.entity(),
+            barrel
+        );
+
+        fs::write(&path, file(2.0)).unwrap();
+        reload_changed(&mut world, &BTreeSet::from([path]));
+
+        let aim = world.get::<ReloadAim>(turret).unwrap();
+        assert_eq!(aim.speed, 2.0);
+        assert_eq!(aim.target.entity(), barrel);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn reload_tables() {
+        let dir = test_dir("reload_tables");
+        let path = dir.join("loot.table.yaml");
+        fs::write(dir.join("gold.yaml"), "name: Gold\n").unwrap();
+        fs::write(dir.join("bones.yaml"), "name: Bones\n").unwrap();
+        fs::write(&path, "entries:\n  - prototype: Gold\n").unwrap();
+        let mut world = load(&dir);
+
+        let roll = |world: &World| {
+            let data = world.resource::<ProtoData>();
+            data.get_table("loot").unwrap().roll(data, data.rng())
+        };
+        assert_eq!(roll(&world), vec!["Gold"]);
+
+        // Reloading the table checks it again
+        fs::write(&path, "entries:\n  - prototype: Silver\n").unwrap();
+        reload_changed(&mut world, &BTreeSet::from([path.clone()]));
+        assert!(roll(&world).is_empty());
+        let errors = world.resource::<ProtoLoadErrors>();
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            &errors.iter().next().unwrap().kind,
+            ProtoLoadErrorKind::InvalidTable { table, .. } if table == "loot"
+        ));
+
+        // And clears its errors once it's fixed
+        fs::write(&path, "entries:\n  - prototype: Bones\n").unwrap();
+        reload_changed(&mut world, &BTreeSet::from([path]));
+        assert_eq!(roll(&world), vec!["Bones"]);
+        assert!(world.resource::<ProtoLoadErrors>().is_empty());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn reload_reapplies_patches() {
+        let dir = test_dir("reload_reapplies_patches");
+        let goblin = dir.join("goblin.yaml");
+        let tough = dir.join("tough.yaml");
+        let health = |hp: u32| format!("  - type: ReloadHealth\n    value: {}\n", hp);
+        fs::write(&goblin, format!("name: Goblin\ncomponents:\n{}", health(5))).unwrap();
+        fs::write(
+            &tough,
+            format!("patch: Goblin\ncomponents:\n{}", health(50)),
+        )
+        .unwrap();
+        let mut world = load(&dir);
+
+        let hp = |world: &World| {
+            let data = world.resource::<ProtoData>();
+            let proto = data.get_prototype("Goblin")?;
+            proto.iter_components().find_map(|component| {
+                let component: &dyn Any = component.as_ref();
+                component
+                    .downcast_ref::<ReloadHealth>()
+                    .map(|health| health.0)
+            })
+        };
+        assert_eq!(hp(&world), Some(50));
+
+        // Breaking the patched prototype reports it (along with the patch that lost its target)
+        fs::write(&goblin, "name: Goblin\ncomponents: [\n").unwrap();
+        reload_changed(&mut world, &BTreeSet::from([goblin.clone()]));
+        assert_eq!(hp(&world), None);
+        let errors = world.resource::<ProtoLoadErrors>();
+        assert_eq!(errors.for_path(&goblin).count(), 1);
+        assert!(matches!(
+            &errors.for_path(&tough).next().unwrap().kind,
+            ProtoLoadErrorKind::MissingPatchTarget { target } if target == "Goblin"
+        ));
+
+        // Fixing it re-applies the patch and clears the stale errors
+        fs::write(&goblin, format!("name: Goblin\ncomponents:\n{}", health(7))).unwrap();
+        reload_changed(&mut world, &BTreeSet::from([goblin]));
+        assert_eq!(hp(&world), Some(50));
+        assert!(world.resource::<ProtoLoadErrors>().is_empty());
+
+        // As does changing the patch itself
+        fs::write(
+            &tough,
+            format!("patch: Goblin\ncomponents:\n{}", health(60)),
+        )
+        .unwrap();
+        reload_changed(&mut world, &BTreeSet::from([tough]));
+        assert_eq!(hp(&world), Some(60));
+        let _ = fs::remove_dir_all(&dir);
     }
 }
@@ -0,0 +1,202 @@
+//! Contains [`ProtoInstance`] and [`ProtoInstances`], used to track the entities spawned from
+//! each prototype.
+use bevy::core::Name;
+use bevy::ecs::prelude::{Changed, Component, Entity, Query, RemovedComponents, ResMut, Resource};
+use bevy::ecs::system::EntityCommands;
+use bevy::utils::{HashMap, HashSet};
+use indexmap::IndexSet;
+
+use crate::data::ProtoData;
+use crate::prototype::Prototypical;
+
+/// A marker recording which prototype an entity was spawned from.
+///
+/// This is inserted by [`Prototypical::insert`] (and so by every way of spawning a prototype)
+/// when [`ProtoDataOptions::mark_instances`](crate::data::ProtoDataOptions::mark_instances) is
+/// enabled. Each child entity spawned from a named prototype is marked as well.
+///
+/// # Examples
+///
+/// ```
+/// use bevy::prelude::*;
+/// use bevy_proto::prelude::*;
+///
+/// fn count_enemies(instances: Query<&ProtoInstance>) {
+///     let enemies = instances.iter().filter(|instance| instance.is("Enemy")).count();
+///     info!("There are {} enemies", enemies);
+/// }
+/// ```
+#[derive(Component, Debug, Clone, PartialEq, Eq)]
+pub struct ProtoInstance {
+    /// The name of the prototype the entity was spawned from.
+    pub name: String,
+    /// The names of every template the prototype inherits from, including those of its templates
+    /// (nearest first).
+    pub templates: Vec<String>,
+}
+
+impl ProtoInstance {
+    /// Creates the marker for an entity spawned from the given prototype
+    ///
+    /// # Arguments
+    ///
+    /// * `proto`: The prototype the entity was spawned from
+    /// * `data`: The prototype data containing its templates
+    ///
+    /// returns: ProtoInstance
+    pub fn new<P: Prototypical + ?Sized>(proto: &P, data: &ProtoData) -> Self {
+        let mut templates = IndexSet::new();
+        collect_templates(proto, data, &mut templates);
+        // A template cycle could lead back to the prototype itself
+        templates.shift_remove(proto.name());
+
+        Self {
+            name: proto.name().to_string(),
+            templates: templates.into_iter().map(str::to_string).collect(),
+        }
+    }
+
+    /// Returns true if the entity was spawned from the prototype with the given name, or from a
+    /// prototype inheriting from it
+    pub fn is(&self, name: &str) -> bool {
+        self.name == name || self.templates.iter().any(|template| template == name)
+    }
+}
+
+/// Collects the names of the given prototype's templates (recursively), nearest first
+fn collect_templates<'a, P: Prototypical + ?Sized>(
+    proto: &'a P,
+    data: &'a ProtoData,
+    templates: &mut IndexSet<&'a str>,
+) {
+    for template in proto.templates_rev().rev() {
+        let parent = match data.get_prototype(template) {
+            Some(parent) => parent,
+            None => continue,
+        };
+        if templates.insert(parent.name()) {
+            collect_templates(parent, data, templates);
+        }
+    }
+}
+
+/// A resource mapping each prototype to the live entities spawned from it.
+///
+/// This is kept up to date from the [`ProtoInstance`] markers (so it requires
+/// [`ProtoDataOptions::mark_instances`](crate::data::ProtoDataOptions::mark_instances)). Newly
+/// spawned entities are added, and despawned ones removed, at the end of each frame.
+///
+/// # Examples
+///
+/// ```
+/// use bevy::prelude::*;
+/// use bevy_proto::prelude::*;
+///
+/// fn despawn_goblins(mut commands: Commands, instances: Res<ProtoInstances>) {
+///     for entity in instances.get("Goblin") {
+///         commands.entity(entity).despawn_recursive();
+///     }
+/// }
+/// ```
+#[derive(Resource, Debug, Default)]
+pub struct ProtoInstances {
+    /// Maps Prototype Name -> Live Entities
+    entities: HashMap<String, HashSet<Entity>>,
+    /// Maps Entity -> Prototype Name
+    prototypes: HashMap<Entity, String>,
+}
+
+impl ProtoInstances {
+    /// Get the live entities spawned from the prototype with the given name
+    ///
+    /// This doesn't include entities spawned from prototypes inheriting from it.
+    pub fn get(&self, name: &str) -> impl Iterator<Item = Entity> + '_ {
+        self.entities.get(name).into_iter().flatten().copied()
+    }
+
+    /// The number of live entities spawned from the prototype with the given name
+    pub fn count(&self, name: &str) -> usize {
+        self.entities.get(name).map_or(0, HashSet::len)
+    }
+
+    /// Get the name of the prototype the given entity was spawned from
+    pub fn prototype_of(&self, entity: Entity) -> Option<&str> {
+        self.prototypes.get(&entity).map(String::as_str)
+    }
+
+    /// Get an iterator over every prototype with live entities, along with those entities
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &HashSet<Entity>)> {
+        self.entities
+            .iter()
+            .map(|(name, entities)| (name.as_str(), entities))
+    }
+
+    /// The total number of live entities spawned from any prototype
+    pub fn len(&self) -> usize {
+        self.prototypes.len()
+    }
+
+    /// Returns true if there are no live entities spawned from any prototype
+    pub fn is_empty(&self) -> bool {
+        self.prototypes.is_empty()
+    }
+
+    fn insert(&mut self, entity: Entity, name: &str) {
+        if self.prototypes.get(&entity).map(String::as_str) == Some(name) {
+            return;
+        }
+
+        self.remove(entity);
+        self.prototypes.insert(entity, name.to_string());
+        self.entities
+            .entry(name.to_string())
+            .or_default()
+            .insert(entity);
+    }
+
+    fn remove(&mut self, entity: Entity) {
+        let name = match self.prototypes.remove(&entity) {
+            Some(name) => name,
+            None => return,
+        };
+        if let Some(entities) = self.entities.get_mut(&name) {
+            entities.remove(&entity);
+            if entities.is_empty() {
+                self.entities.remove(&name);
+            }
+        }
+    }
+}
+
+/// Updates [`ProtoInstances`] with the entities marked (or unmarked) since it last ran
+pub(crate) fn update_proto_instances(
+    mut instances: ResMut<ProtoInstances>,
+    marked: Query<(Entity, &ProtoInstance), Changed<ProtoInstance>>,
+    mut removed: RemovedComponents<ProtoInstance>,
+) {
+    for entity in removed.iter() {
+        instances.remove(entity);
+    }
+    for (entity, instance) in &marked {
+        instances.insert(entity, &instance.name);
+    }
+}
+
+/// Inserts the [`ProtoInstance`] and `Name` of the given prototype into its entity, if enabled
+pub(crate) fn mark_instance<P: Prototypical + ?Sized>(
+    proto: &P,
+    entity: &mut EntityCommands,
+    data: &ProtoData,
+) {
+    // Inline prototypes without a name can't be identified
+    if proto.name().is_empty() {
+        return;
+    }
+
+    if data.mark_instances {
+        entity.insert(ProtoInstance::new(proto, data));
+    }
+    if data.name_instances {
+        entity.insert(Name::new(proto.name().to_string()));
+    }
+}
@@ -4,7 +4,14 @@
 //! This crate provides several abstractions for specifying serializable entities and components:
 //! - The [`ProtoComponent`](components::ProtoComponent) trait provides methods to load components from assets.
 //! - The [`ProtoDeserializer`](data::ProtoDeserializer) trait describes component deserialization.
+//!   Built-in implementations for each supported file format live in [`deserializers`].
 //! - [`ProtoPlugin`](plugin::ProtoPlugin) provides configuration for asset loading.
+//! - [`PrototypeAsset`](asset::PrototypeAsset) allows prototype files to be loaded through the `AssetServer`.
+//! - The [`ProtoLoadErrors`](errors::ProtoLoadErrors) resource collects any prototype files that failed to load.
+//! - The [`ProtoLoadState`](loading::ProtoLoadState) resource reports whether prototypes have finished loading.
+//! - With the `pack` feature, `pack::ProtoPack` compiles prototypes and tables into a single binary file for release builds.
+//! - With the `schema` feature, `schema::ProtoSchemaGenerator` generates a JSON Schema for prototype files.
+//! - With the `validate` feature, the `validate` module checks prototype files for errors (such as in CI).
 //!
 //! # Examples
 //!
@@ -74,28 +81,72 @@
 //!
 extern crate bevy_proto_derive;
 
+mod asset;
+pub use asset::PrototypeAsset;
+mod children;
+pub use children::{ProtoChild, ProtoChildren, SpawnedChild};
 mod components;
 pub use bevy_proto_derive::ProtoComponent;
 pub use components::ProtoComponent;
+mod entity_ref;
+#[doc(hidden)]
+pub use entity_ref::__private;
+pub use entity_ref::{MapEntityRefs, ProtoEntityMap, ProtoEntityRef};
+mod instance;
+pub use instance::{ProtoInstance, ProtoInstances};
+mod loading;
+pub use loading::{ProtoDataLoaded, ProtoLoadState};
+mod merge;
+mod params;
+pub use params::ProtoParams;
 mod plugin;
 pub use plugin::ProtoPlugin;
+mod reflect;
+pub use reflect::Reflected;
+pub use registry::{ProtoComponentRegistry, RegisterProtoComponent};
 mod prototype;
-pub use prototype::{deserialize_templates_list, Prototype, Prototypical};
+mod random;
+pub use prototype::{
+    deserialize_templates_list, resolve_template, Prototype, PrototypeSeed, Prototypical,
+};
+pub use random::ProtoRng;
+mod table;
+pub use table::{ProtoTable, TableCount, TableEntry};
 
 #[cfg(feature = "hot_reloading")]
 mod hot_reload;
 
 pub mod data;
+pub mod deserializers;
+pub mod errors;
+#[cfg(feature = "pack")]
+pub mod pack;
+pub mod registry;
+#[cfg(feature = "schema")]
+pub mod schema;
+#[cfg(feature = "validate")]
+pub mod validate;
 #[macro_use]
 mod utils;
 
 pub mod prelude {
     //! Includes all public types and the macro to derive [`ProtoComponent`](super::components::ProtoComponent).
 
+    pub use super::asset::PrototypeAsset;
+    pub use super::children::{ProtoChild, ProtoChildren, SpawnedChild};
     pub use super::components::ProtoComponent;
     pub use super::data::*;
+    pub use super::entity_ref::{MapEntityRefs, ProtoEntityMap, ProtoEntityRef};
+    pub use super::errors::*;
+    pub use super::instance::{ProtoInstance, ProtoInstances};
+    pub use super::loading::{ProtoDataLoaded, ProtoLoadState};
+    pub use super::params::ProtoParams;
     pub use super::plugin::ProtoPlugin;
-    pub use super::prototype::{Prototype, Prototypical};
+    pub use super::prototype::{Prototype, PrototypeSeed, Prototypical};
+    pub use super::random::ProtoRng;
+    pub use super::reflect::Reflected;
+    pub use super::registry::{ProtoComponentRegistry, RegisterProtoComponent};
+    pub use super::table::{ProtoTable, TableCount, TableEntry};
     pub use bevy_proto_derive::*;
 }
 
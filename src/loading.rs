@@ -0,0 +1,185 @@
+//! Contains the types used to track the loading of [`ProtoData`].
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use bevy::ecs::system::Resource;
+use bevy::prelude::{Mut, World};
+use bevy::tasks::{AsyncComputeTaskPool, IoTaskPool, Task};
+use futures_lite::future;
+
+use crate::data::{
+    check_entity_refs, check_tables, collect_paths, read_file, register_file, FileResult,
+    ProtoData, ProtoDataOptions,
+};
+use crate::errors::{ProtoLoadError, ProtoLoadErrors};
+
+/// A resource describing whether [`ProtoData`] has finished loading.
+///
+/// When [`ProtoDataOptions::async_loading`] is disabled, this is already [`Ready`](Self::Ready)
+/// (or [`Failed`](Self::Failed)) by the time the app starts.
+///
+/// # Examples
+///
+/// ```
+/// use bevy::prelude::*;
+/// use bevy_proto::prelude::*;
+///
+/// fn loading_screen(state: Res<ProtoLoadState>) {
+///     match *state {
+///         ProtoLoadState::Loading => println!("Loading prototypes..."),
+///         ProtoLoadState::Ready => println!("Done!"),
+///         ProtoLoadState::Failed => println!("Some prototypes failed to load!"),
+///     }
+/// }
+/// ```
+#[derive(Resource, Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ProtoLoadState {
+    /// Prototype files are still being loaded.
+    #[default]
+    Loading,
+    /// Every prototype file was loaded successfully.
+    Ready,
+    /// Loading has finished, but at least one file failed to load.
+    ///
+    /// The failures can be found in the [`ProtoLoadErrors`] resource.
+    Failed,
+}
+
+impl ProtoLoadState {
+    /// Returns true if loading has finished, whether or not it was successful
+    pub fn is_finished(&self) -> bool {
+        !matches!(self, Self::Loading)
+    }
+
+    /// The state of a finished load which produced the given errors.
+    pub(crate) fn from_errors(errors: &ProtoLoadErrors) -> Self {
+        if errors.is_empty() {
+            Self::Ready
+        } else {
+            Self::Failed
+        }
+    }
+}
+
+/// An event sent once [`ProtoData`] has finished loading.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ProtoDataLoaded {
+    /// The final loading state (either [`Ready`](ProtoLoadState::Ready) or [`Failed`](ProtoLoadState::Failed)).
+    pub state: ProtoLoadState,
+}
+
+/// The background tasks used to load [`ProtoData`] when [`ProtoDataOptions::async_loading`] is enabled.
+///
+/// Tasks are handled in the order they were started, so that prototypes are registered in the same
+/// order as with synchronous loading (which matters for [`DuplicatePolicy`](crate::data::DuplicatePolicy)).
+#[derive(Resource)]
+pub(crate) struct ProtoLoadTasks {
+    options: Arc<ProtoDataOptions>,
+    /// Tasks searching a directory for prototype files
+    scans: VecDeque<Task<(Vec<PathBuf>, Vec<ProtoLoadError>)>>,
+    /// Tasks reading and deserializing a single prototype (or table) file
+    files: VecDeque<Task<(PathBuf, FileResult)>>,
+}
+
+impl ProtoLoadTasks {
+    /// Starts searching each of the configured directories for prototype files.
+    pub(crate) fn new(options: ProtoDataOptions) -> Self {
+        let options = Arc::new(options);
+        let scans = options
+            .directories
+            .iter()
+            .map(|directory| {
+                let options = options.clone();
+                let directory = directory.clone();
+                IoTaskPool::get().spawn(async move {
+                    let mut paths = Vec::new();
+                    let mut errors = Vec::new();
+                    collect_paths(&options, Path::new(&directory), &mut paths, &mut errors);
+                    (paths, errors)
+                })
+            })
+            .collect();
+
+        Self {
+            options,
+            scans,
+            files: VecDeque::new(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.scans.is_empty() && self.files.is_empty()
+    }
+}
+
+/// Removes the finished tasks at the front of the queue, stopping at the first unfinished one.
+fn pop_finished<T>(tasks: &mut VecDeque<Task<T>>) -> Vec<Task<T>> {
+    let mut finished = Vec::new();
+    while matches!(tasks.front(), Some(task) if task.is_finished()) {
+        finished.extend(tasks.pop_front());
+    }
+    finished
+}
+
+/// Adds the results of any finished loading tasks to [`ProtoData`].
+pub(crate) fn poll_proto_load_tasks(world: &mut World) {
+    let mut tasks = match world.remove_resource::<ProtoLoadTasks>() {
+        Some(tasks) => tasks,
+        None => return,
+    };
+
+    // === Start Loading Found Files === //
+    for task in pop_finished(&mut tasks.scans) {
+        let (paths, errors) = future::block_on(task);
+
+        let mut load_errors = world.resource_mut::<ProtoLoadErrors>();
+        for err in errors {
+            load_errors.push(err);
+        }
+
+        for path in paths {
+            let options = tasks.options.clone();
+            tasks
+                .files
+                .push_back(AsyncComputeTaskPool::get().spawn(async move {
+                    let result = read_file(&options, &path);
+                    (path, result)
+                }));
+        }
+    }
+
+    // === Register Loaded Files === //
+    let finished = pop_finished(&mut tasks.files);
+
+    world.resource_scope(|world, mut data: Mut<ProtoData>| {
+        world.resource_scope(|world, mut errors: Mut<ProtoLoadErrors>| {
+            for task in finished {
+                let (path, result) = future::block_on(task);
+                register_file(world, &mut data, &mut errors, &tasks.options, path, result);
+            }
+        });
+    });
+
+    if !tasks.is_empty() {
+        world.insert_resource(tasks);
+        return;
+    }
+
+    // === Finish Loading === //
+    world.resource_scope(|world, mut data: Mut<ProtoData>| {
+        world.resource_scope(|world, mut errors: Mut<ProtoLoadErrors>| {
+            data.apply_patches(world, &mut errors, &tasks.options, None);
+            data.resolve_merges(world, &mut errors);
+            check_tables(&data, &mut errors);
+            check_entity_refs(&data, &mut errors);
+        });
+    });
+
+    #[cfg(feature = "analysis")]
+    crate::data::analyze_deps(world.resource::<ProtoData>());
+
+    let state = ProtoLoadState::from_errors(world.resource::<ProtoLoadErrors>());
+    world.insert_resource(state);
+    world.send_event(ProtoDataLoaded { state });
+}
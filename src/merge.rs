@@ -0,0 +1,504 @@
+//! Contains [`MergedComponent`], which merges a component's `value` into the one it inherits.
+use std::any::Any;
+use std::sync::RwLock;
+
+use bevy::prelude::{AssetServer, Res};
+use indexmap::IndexSet;
+use serde::ser::{self, SerializeStruct};
+use serde::{Serialize, Serializer};
+use serde_yaml::value::TaggedValue;
+use serde_yaml::{Mapping, Value};
+
+use crate::children::for_each_inline;
+use crate::components::ProtoComponent;
+use crate::data::{ProtoCommands, ProtoData};
+use crate::entity_ref::ProtoEntityRef;
+use crate::errors::ProtoDeserializeError;
+use crate::params;
+use crate::prototype::Prototypical;
+use crate::registry::{component_value, deserialize_value, ProtoComponentRegistry};
+
+/// The key used to replace a value outright instead of merging it
+const REPLACE: &str = "$replace";
+
+/// A component declared with `merge` rather than `value`.
+///
+/// Its fields are merged into the value of the component it would otherwise inherit from its
+/// templates (see [`merge_values`]), and the component is deserialized once from the result
+/// when the prototype data is resolved (see [`ProtoData::resolve_merges`]).
+pub(crate) struct MergedComponent {
+    /// The `type` name of the component
+    name: String,
+    /// The fields to merge into the inherited value
+    value: Value,
+    /// The component deserialized from the merged value
+    resolved: RwLock<Option<Box<dyn ProtoComponent>>>,
+}
+
+impl MergedComponent {
+    pub(crate) fn new(name: String, value: Value) -> Self {
+        Self {
+            name,
+            value,
+            resolved: RwLock::new(None),
+        }
+    }
+
+    /// The `type` name of the component
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The fields to merge into the inherited value
+    #[cfg(feature = "pack")]
+    pub(crate) fn value(&self) -> &Value {
+        &self.value
+    }
+
+    /// Replaces the component deserialized from the merged value
+    pub(crate) fn set_resolved(&self, component: Option<Box<dyn ProtoComponent>>) {
+        *self.resolved.write().unwrap() = component;
+    }
+
+    /// Calls the given function with the component deserialized from the merged value (if any)
+    pub(crate) fn with_resolved<R>(
+        &self,
+        func: impl FnOnce(Option<&dyn ProtoComponent>) -> R,
+    ) -> R {
+        func(self.resolved.read().unwrap().as_deref())
+    }
+}
+
+impl ProtoComponent for MergedComponent {
+    fn insert_self(&self, commands: &mut ProtoCommands, asset_server: &Res<AssetServer>) {
+        // Components that failed to merge have already been reported
+        if let Some(component) = self.resolved.read().unwrap().as_ref() {
+            component.insert_self(commands, asset_server);
+        }
+    }
+
+    fn remove_self(&self, commands: &mut ProtoCommands) {
+        if let Some(component) = self.resolved.read().unwrap().as_ref() {
+            component.remove_self(commands);
+        }
+    }
+
+    fn visit_entity_refs(&self, visitor: &mut dyn FnMut(&ProtoEntityRef)) {
+        if let Some(component) = self.resolved.read().unwrap().as_ref() {
+            component.visit_entity_refs(visitor);
+        }
+    }
+
+    // Merged components are never deserialized by `typetag` (see `registry::ComponentVisitor`)
+    #[cfg(feature = "typetag")]
+    #[doc(hidden)]
+    fn typetag_name(&self) -> &'static str {
+        "MergedComponent"
+    }
+
+    #[cfg(feature = "typetag")]
+    #[doc(hidden)]
+    fn typetag_deserialize(&self) {}
+}
+
+impl Serialize for MergedComponent {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        // The fields can only be read back by a self-describing format
+        if !serializer.is_human_readable() {
+            return Err(ser::Error::custom(format_args!(
+                "merged component `{}` can't be stored in this format",
+                self.name
+            )));
+        }
+
+        let mut state = serializer.serialize_struct("ProtoComponent", 2)?;
+        state.serialize_field("type", &self.name)?;
+        state.serialize_field("merge", &self.value)?;
+        state.end()
+    }
+}
+
+/// Returns the given component as a [`MergedComponent`], if it is one
+pub(crate) fn as_merged(component: &dyn ProtoComponent) -> Option<&MergedComponent> {
+    let component: &dyn Any = component;
+    component.downcast_ref::<MergedComponent>()
+}
+
+/// Merges `value` into `base`.
+///
+/// Maps are merged key by key, lists are appended to the base list, and anything else replaces
+/// the base value. A map of the form `{ $replace: ... }` always replaces the base value with its
+/// contents.
+pub(crate) fn merge_values(base: Value, value: &Value) -> Value {
+    match value {
+        Value::Mapping(map) => {
+            if let (1, Some(replacement)) = (map.len(), map.get(REPLACE)) {
+                return merge_values(Value::Null, replacement);
+            }
+
+            let mut base = match base {
+                Value::Mapping(base) => base,
+                _ => Mapping::new(),
+            };
+            for (key, value) in map {
+                match base.get_mut(key) {
+                    Some(existing) => *existing = merge_values(std::mem::take(existing), value),
+                    None => {
+                        base.insert(key.clone(), merge_values(Value::Null, value));
+                    }
+                }
+            }
+            Value::Mapping(base)
+        }
+        Value::Sequence(values) => {
+            let mut base = match base {
+                Value::Sequence(base) => base,
+                _ => Vec::with_capacity(values.len()),
+            };
+            base.extend(values.iter().map(|value| merge_values(Value::Null, value)));
+            Value::Sequence(base)
+        }
+        // Tagged values (such as enum variants) are only merged if the tags match
+        Value::Tagged(tagged) => {
+            let base = match base {
+                Value::Tagged(base) if base.tag == tagged.tag => base.value,
+                _ => Value::Null,
+            };
+            Value::Tagged(Box::new(TaggedValue {
+                tag: tagged.tag.clone(),
+                value: merge_values(base, &tagged.value),
+            }))
+        }
+        value => value.clone(),
+    }
+}
+
+/// Deserializes every [`MergedComponent`] in the given [`ProtoData`] from its merged value
+///
+/// This includes the components of inline children (see [`ProtoChild`](crate::ProtoChild)).
+///
+/// returns: Vec<(String, Vec<usize>, usize, Result<Box<dyn ProtoComponent>, ProtoDeserializeError>)>
+/// containing the name of each prototype, the path to the inline child (see
+/// [`inline_child`](crate::children::inline_child)), the index of the merged component, and its result
+#[allow(clippy::type_complexity)]
+pub(crate) fn resolve_all(
+    data: &ProtoData,
+) -> Vec<(
+    String,
+    Vec<usize>,
+    usize,
+    Result<Box<dyn ProtoComponent>, ProtoDeserializeError>,
+)> {
+    let mut resolved = Vec::new();
+
+    for proto in data.iter() {
+        let name = proto.name();
+        resolve_in(proto.as_ref(), &[], data, &mut |path, index, result| {
+            resolved.push((name.to_string(), path.to_vec(), index, result));
+        });
+        for_each_inline(proto.as_ref(), &mut Vec::new(), &mut |child, path| {
+            resolve_in(child, path, data, &mut |path, index, result| {
+                resolved.push((name.to_string(), path.to_vec(), index, result));
+            });
+        });
+    }
+
+    resolved
+}
+
+/// Deserializes every [`MergedComponent`] of a single prototype (or inline child)
+#[allow(clippy::type_complexity)]
+fn resolve_in(
+    proto: &dyn Prototypical,
+    path: &[usize],
+    data: &ProtoData,
+    push: &mut dyn FnMut(&[usize], usize, Result<Box<dyn ProtoComponent>, ProtoDeserializeError>),
+) {
+    for (index, component) in proto.iter_components().enumerate() {
+        let merged = match as_merged(component.as_ref()) {
+            Some(merged) => merged,
+            None => continue,
+        };
+
+        let mut traversed = IndexSet::default();
+        traversed.insert(proto.name());
+        let result = inherited_value(proto, &merged.name, data, &mut traversed)
+            .and_then(|inherited| {
+                let value = merge_values(inherited.unwrap_or_default(), &merged.value);
+                params::finish_merged(proto, data, &merged.name, value)
+            })
+            .map_err(|err| {
+                ProtoDeserializeError::new(format!(
+                    "could not merge prototype `{}` with its templates: {}",
+                    proto.name(),
+                    err
+                ))
+                .with_component(Some(merged.name.clone()))
+            });
+        push(path, index, result);
+    }
+}
+
+/// Merges the given [`MergedComponent`] into the component it patches (which has the same `type`)
+///
+/// returns: Result<Box<dyn ProtoComponent>, ProtoDeserializeError>
+pub(crate) fn merge_into(
+    existing: &dyn ProtoComponent,
+    merged: &MergedComponent,
+    registry: &ProtoComponentRegistry,
+) -> Result<Box<dyn ProtoComponent>, ProtoDeserializeError> {
+    // Merging into another merged component must wait until the templates are known
+    if let Some(existing) = as_merged(existing) {
+        let value = merge_values(existing.value.clone(), &merged.value);
+        return Ok(Box::new(MergedComponent::new(merged.name.clone(), value)));
+    }
+
+    component_value(registry, existing)
+        .and_then(|value| {
+            let value = merge_values(value, &merged.value);
+            deserialize_value(registry, &merged.name, value)
+        })
+        .map_err(|err| ProtoDeserializeError::new(err).with_component(Some(merged.name.clone())))
+}
+
+/// The value of the component with the given `type` name which the given prototype inherits from
+/// its templates (if any)
+fn inherited_value<'a>(
+    proto: &'a dyn Prototypical,
+    name: &str,
+    data: &'a ProtoData,
+    traversed: &mut IndexSet<&'a str>,
+) -> Result<Option<Value>, String> {
+    if proto
+        .excluded_components()
+        .iter()
+        .any(|excluded| excluded == name)
+    {
+        return Ok(None);
+    }
+
+    // Templates listed first override those listed last
+    for template in proto.templates() {
+        if traversed.contains(template.as_str()) {
+            // Cycles are reported separately
+            continue;
+        }
+
+        if let Some(parent) = data.get_prototype(template) {
+            traversed.insert(parent.name());
+            let value = own_value(parent, name, data, traversed);
+            traversed.pop();
+
+            if let Some(value) = value? {
+                return Ok(Some(value));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+/// The value of the component with the given `type` name which the given prototype inserts
+/// (either its own or one it inherits), after merging
+fn own_value<'a>(
+    proto: &'a dyn Prototypical,
+    name: &str,
+    data: &'a ProtoData,
+    traversed: &mut IndexSet<&'a str>,
+) -> Result<Option<Value>, String> {
+    let own = proto
+        .iter_components()
+        .rev()
+        .find(|component| data.registry.name_of(component.as_ref()).as_deref() == Some(name));
+
+    match own {
+        Some(component) => match as_merged(component.as_ref()) {
+            Some(merged) => {
+                let inherited = inherited_value(proto, name, data, traversed)?;
+                Ok(Some(merge_values(
+                    inherited.unwrap_or_default(),
+                    &merged.value,
+                )))
+            }
+            None => component_value(&data.registry, component.as_ref()).map(Some),
+        },
+        None => inherited_value(proto, name, data, traversed),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use bevy::asset::FileAssetIo;
+    use bevy::ecs::system::SystemState;
+    use bevy::prelude::{Commands, Component, FromWorld, World};
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::data::ProtoDataOptions;
+    use crate::errors::ProtoLoadErrors;
+    use crate::params::ProtoParams;
+
+    #[derive(Component, Clone, Debug, PartialEq, Serialize, Deserialize)]
+    struct MergeStats {
+        hp: u32,
+        tags: Vec<String>,
+        reach: MergeReach,
+    }
+
+    #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+    struct MergeReach {
+        min: u32,
+        max: u32,
+    }
+
+    #[cfg_attr(feature = "typetag", typetag::serde)]
+    impl ProtoComponent for MergeStats {
+        fn insert_self(&self, commands: &mut ProtoCommands, _: &Res<AssetServer>) {
+            commands.insert(self.clone());
+        }
+    }
+
+    fn yaml(value: &str) -> Value {
+        serde_yaml::from_str(value).unwrap()
+    }
+
+    /// Loads the given files into a new world
+    fn load(name: &str, files: &[(&str, &str)]) -> World {
+        let dir = std::env::temp_dir()
+            .join(format!("bevy_proto_{}", std::process::id()))
+            .join(name);
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for (file, contents) in files {
+            fs::write(dir.join(file), contents).unwrap();
+        }
+
+        let mut world = World::new();
+        world.insert_resource(AssetServer::new(FileAssetIo::new(&dir, false)));
+        let options = ProtoDataOptions {
+            directories: vec![dir.to_string_lossy().to_string()],
+            ..Default::default()
+        };
+        options.registry.register::<MergeStats>("MergeStats");
+        world.insert_resource(options);
+        let data = ProtoData::from_world(&mut world);
+        world.insert_resource(data);
+        let _ = fs::remove_dir_all(&dir);
+        world
+    }
+
+    /// Spawns the prototype with the given name (and params), returning its stats
+    fn spawn(world: &mut World, name: &str, params: &ProtoParams) -> Option<MergeStats> {
+        let mut state = SystemState::<(Commands, Res<ProtoData>, Res<AssetServer>)>::new(world);
+        let (mut commands, data, asset_server) = state.get_mut(world);
+        let entity = data
+            .get_prototype(name)
+            .unwrap()
+            .spawn_with(&mut commands, &data, &asset_server, params)
+            .unwrap()
+            .id();
+        state.apply(world);
+        world.get::<MergeStats>(entity).cloned()
+    }
+
+    #[test]
+    fn merge_nested_maps_and_sequences() {
+        let base = yaml("{ hp: 10, tags: [a], reach: { min: 1, max: 2 }, items: [{ a: 1 }] }");
+
+        // Maps are merged key by key (at any depth), while sequences are appended to (even if
+        // they contain maps)
+        let merged = merge_values(
+            base.clone(),
+            &yaml("{ tags: [b], reach: { max: 3 }, items: [{ b: 2 }] }"),
+        );
+        assert_eq!(
+            merged,
+            yaml(
+                "{ hp: 10, tags: [a, b], reach: { min: 1, max: 3 }, items: [{ a: 1 }, { b: 2 }] }"
+            )
+        );
+
+        // A sequence replaces a map (and vice versa), as does `$replace`
+        let merged = merge_values(
+            base,
+            &yaml("{ tags: { first: b }, reach: [1, 3], items: { $replace: [{ b: 2 }] } }"),
+        );
+        assert_eq!(
+            merged,
+            yaml("{ hp: 10, tags: { first: b }, reach: [1, 3], items: [{ b: 2 }] }")
+        );
+    }
+
+    #[test]
+    fn merge_without_inherited_component() {
+        let mut world = load(
+            "merge_without_inherited_component",
+            &[
+                ("base.yaml", "name: Base\n"),
+                (
+                    "knight.yaml",
+                    "name: Knight\ntemplates: Base\ncomponents:\n  - type: MergeStats\n    merge:\n      hp: 10\n      tags: [a]\n      reach: { min: 1, max: 2 }\n",
+                ),
+                (
+                    "squire.yaml",
+                    "name: Squire\ntemplates: Base\ncomponents:\n  - type: MergeStats\n    merge:\n      hp: 5\n",
+                ),
+            ],
+        );
+
+        // Without an inherited value, the merge is deserialized on its own
+        let stats = spawn(&mut world, "Knight", &ProtoParams::new());
+        assert_eq!(
+            stats,
+            Some(MergeStats {
+                hp: 10,
+                tags: vec![String::from("a")],
+                reach: MergeReach { min: 1, max: 2 },
+            })
+        );
+
+        // So an incomplete merge is reported instead of inserted
+        let errors = world.resource::<ProtoLoadErrors>();
+        assert_eq!(errors.len(), 1);
+        assert!(errors.iter().next().unwrap().path.ends_with("squire.yaml"));
+        assert_eq!(spawn(&mut world, "Squire", &ProtoParams::new()), None);
+    }
+
+    #[test]
+    fn merge_with_params() {
+        let mut world = load(
+            "merge_with_params",
+            &[
+                (
+                    "soldier.yaml",
+                    "name: Soldier\nparams:\n  hp: 10\ncomponents:\n  - type: MergeStats\n    value:\n      hp: $hp\n      tags: [soldier]\n      reach: { min: 1, max: 2 }\n",
+                ),
+                (
+                    "archer.yaml",
+                    "name: Archer\ntemplates: Soldier\nparams:\n  reach: 8\ncomponents:\n  - type: MergeStats\n    merge:\n      tags: [archer]\n      reach: { max: $reach }\n",
+                ),
+            ],
+        );
+        assert!(world.resource::<ProtoLoadErrors>().is_empty());
+
+        // Params are substituted into the merged value, whether they're referenced by the
+        // template or the merge itself
+        let stats = spawn(&mut world, "Archer", &ProtoParams::new().with("hp", 15));
+        assert_eq!(
+            stats,
+            Some(MergeStats {
+                hp: 15,
+                tags: vec![String::from("soldier"), String::from("archer")],
+                reach: MergeReach { min: 1, max: 8 },
+            })
+        );
+
+        let stats = spawn(&mut world, "Archer", &ProtoParams::new().with("reach", 12));
+        assert_eq!(
+            stats.map(|stats| (stats.hp, stats.reach.max)),
+            Some((10, 12))
+        );
+    }
+}
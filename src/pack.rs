@@ -0,0 +1,767 @@
+//! Provides [`ProtoPack`], which stores a whole set of prototypes (and tables) in a single binary file.
+//!
+//! Packs are meant for release builds, where shipping (and parsing) every prototype file at startup
+//! is unnecessary. A pack is built from the same [`ProtoDataOptions`] used to load the prototype
+//! files, and can then be loaded by setting [`ProtoDataOptions::pack`].
+//!
+//! Packs are encoded with [bincode](https://docs.rs/bincode), so any [`ProtoComponent`] stored in
+//! one must be deserializable from a non-self-describing format (e.g. no untagged enums). Params,
+//! along with components given as a `merge` or referencing params (or random values), are stored
+//! as YAML within the pack instead, so they're read back exactly as they were written.
+//! [`ProtoTable`]s are stored as YAML too.
+//!
+//! [`Reflected`](crate::Reflected) components are supported as well: each is stored by its type
+//! path along with its value as YAML, and is deserialized again when the pack is read. This uses
+//! the type registry of the [`ProtoComponentRegistry`] the pack is read with, so every reflected
+//! type in the pack must be registered in the app loading it (just as when loading the original
+//! files).
+//!
+//! This module requires the `pack` feature.
+use std::path::{Path, PathBuf};
+
+use bincode::Options;
+use serde::{Deserialize, Serialize, Serializer};
+
+use crate::children::ProtoChild;
+use crate::components::ProtoComponent;
+use crate::data::{collect_paths, read_path, read_tables, ProtoDataOptions, ProtoSource};
+use crate::errors::{ProtoLoadError, ProtoLoadErrorKind, ProtoLoadErrors, ProtoPackError};
+use crate::merge::{as_merged, MergedComponent};
+use crate::params::{as_parameterized, ParameterizedComponent, ProtoParams, ReadsParams};
+use crate::prototype::{Prototype, Prototypical};
+use crate::reflect::as_reflected;
+use crate::registry::{deserialize_value, ComponentRef, ComponentSeed, ProtoComponentRegistry};
+use crate::table::{is_table_path, ProtoTable};
+
+/// The bytes every pack starts with.
+const MAGIC: &[u8; 8] = b"PROTOPAK";
+
+/// The version of the pack format written by this version of the crate.
+///
+/// Packs written with a different version must be rebuilt.
+pub const PACK_VERSION: u32 = 9;
+
+/// A precompiled set of prototypes and tables, along with the location each was originally defined in.
+///
+/// A pack file consists of a header (the bytes `PROTOPAK`, the [`PACK_VERSION`], and a CRC-32
+/// checksum of the contents) followed by the bincode-encoded prototypes and tables.
+///
+/// Prototypes (and patches) and tables are stored in the order they were loaded along with their layer, so
+/// loading a pack produces the same [`ProtoData`](crate::data::ProtoData) as loading the original
+/// files. Any [`Prototypical`] type is stored by its name, local name, templates, params, excluded components, components, children, and patch details,
+/// and is loaded back as a [`Prototype`].
+///
+/// Components registered in a [`ProtoComponentRegistry`] (rather than with `typetag`) are encoded
+/// and decoded with the registry given to [`to_bytes`](Self::to_bytes) and [`from_bytes`](Self::from_bytes).
+/// Packs loaded through [`ProtoDataOptions::pack`](crate::data::ProtoDataOptions::pack) use the registry of those options.
+///
+/// # Examples
+///
+/// Build a pack (for example, in a build script or a release tool):
+///
+/// ```no_run
+/// use bevy_proto::data::ProtoDataOptions;
+/// use bevy_proto::pack::ProtoPack;
+///
+/// let options = ProtoDataOptions {
+///     directories: vec![String::from("assets/prototypes")],
+///     recursive_loading: true,
+///     ..Default::default()
+/// };
+///
+/// let pack = ProtoPack::from_directories(&options).expect("prototypes should be valid");
+/// pack.save("assets/prototypes.pack", &options.registry).expect("pack should be written");
+/// ```
+///
+/// Then load it instead of the prototype files:
+///
+/// ```
+/// use bevy_proto::data::ProtoDataOptions;
+///
+/// let options = ProtoDataOptions {
+///     pack: Some(String::from("assets/prototypes.pack")),
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Default)]
+pub struct ProtoPack {
+    entries: Vec<(ProtoSource, Box<dyn Prototypical>)>,
+    tables: Vec<(ProtoSource, ProtoTable)>,
+}
+
+impl ProtoPack {
+    /// Creates a pack containing every prototype and table in the directories of the given options.
+    ///
+    /// Files are read exactly as they would be when loading [`ProtoData`](crate::data::ProtoData),
+    /// including any [`namespacing`](ProtoDataOptions::namespacing).
+    ///
+    /// # Arguments
+    ///
+    /// * `options`: The options describing which prototype files to read
+    ///
+    /// returns: Result<ProtoPack, ProtoLoadErrors>
+    pub fn from_directories(options: &ProtoDataOptions) -> Result<Self, ProtoLoadErrors> {
+        let mut pack = Self::default();
+        let mut errors = ProtoLoadErrors::default();
+
+        let mut paths = Vec::new();
+        let mut dir_errors = Vec::new();
+        for directory in &options.directories {
+            collect_paths(options, Path::new(directory), &mut paths, &mut dir_errors);
+        }
+        for err in dir_errors {
+            errors.push(err);
+        }
+
+        for path in paths {
+            if is_table_path(&path) {
+                pack.push_tables(options, path, &mut errors);
+                continue;
+            }
+
+            let results = match read_path(options, &path) {
+                Ok(results) => results,
+                Err(err) => {
+                    errors.push(ProtoLoadError::new(path, ProtoLoadErrorKind::Io(err)));
+                    continue;
+                }
+            };
+
+            let layer = options.layer_of(&path);
+            for (document, result) in results.into_iter().enumerate() {
+                match result {
+                    Ok(proto) => pack.push(
+                        ProtoSource::new(path.clone(), document).with_layer(layer),
+                        proto,
+                    ),
+                    Err(err) => errors.push(ProtoLoadError::new(
+                        path.clone(),
+                        ProtoLoadErrorKind::Deserialize(err),
+                    )),
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(pack)
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Adds every table in the given table file to this pack
+    fn push_tables(
+        &mut self,
+        options: &ProtoDataOptions,
+        path: PathBuf,
+        errors: &mut ProtoLoadErrors,
+    ) {
+        let results = match read_tables(options, &path) {
+            Ok(results) => results,
+            Err(err) => {
+                errors.push(ProtoLoadError::new(path, ProtoLoadErrorKind::Io(err)));
+                return;
+            }
+        };
+
+        let layer = options.layer_of(&path);
+        for (document, result) in results.into_iter().enumerate() {
+            match result {
+                Ok(table) => self.push_table(
+                    ProtoSource::new(path.clone(), document).with_layer(layer),
+                    table,
+                ),
+                Err(err) => errors.push(ProtoLoadError::new(
+                    path.clone(),
+                    ProtoLoadErrorKind::Deserialize(err),
+                )),
+            }
+        }
+    }
+
+    /// Adds a prototype to the end of this pack.
+    pub fn push(&mut self, source: ProtoSource, prototype: Box<dyn Prototypical>) {
+        self.entries.push((source, prototype));
+    }
+
+    /// Adds a table to the end of this pack.
+    pub fn push_table(&mut self, source: ProtoSource, table: ProtoTable) {
+        self.tables.push((source, table));
+    }
+
+    /// Get an iterator over the prototypes in this pack (in load order), along with their sources
+    pub fn iter(&self) -> impl Iterator<Item = (&ProtoSource, &dyn Prototypical)> {
+        self.entries
+            .iter()
+            .map(|(source, proto)| (source, proto.as_ref()))
+    }
+
+    /// Get an iterator over the tables in this pack (in load order), along with their sources
+    pub fn iter_tables(&self) -> impl Iterator<Item = (&ProtoSource, &ProtoTable)> {
+        self.tables.iter().map(|(source, table)| (source, table))
+    }
+
+    /// The number of prototypes in this pack
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if this pack contains no prototypes
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Encodes this pack, including its header.
+    ///
+    /// # Arguments
+    ///
+    /// * `registry`: The registry naming the registered components in this pack
+    pub fn to_bytes(&self, registry: &ProtoComponentRegistry) -> Result<Vec<u8>, ProtoPackError> {
+        let entries = self
+            .entries
+            .iter()
+            .map(|(source, proto)| PackedPrototypeRef {
+                path: &source.path,
+                document: source.document,
+                layer: source.layer,
+                name: proto.name(),
+                local: proto.local(),
+                templates: proto.templates(),
+                params: proto.params(),
+                components: pack_components(proto.iter_components(), registry),
+                patch: proto.patch(),
+                remove: proto.removed_components(),
+                exclude: proto.excluded_components(),
+                children: pack_children(proto.children(), registry),
+            })
+            .collect::<Vec<_>>();
+        let tables = self
+            .tables
+            .iter()
+            .map(|(source, table)| PackedTableRef {
+                path: &source.path,
+                document: source.document,
+                layer: source.layer,
+                table,
+            })
+            .collect::<Vec<_>>();
+        let payload = bincode::serialize(&(entries, tables))?;
+
+        let mut bytes = Vec::with_capacity(MAGIC.len() + 8 + payload.len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&PACK_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&crc32fast::hash(&payload).to_le_bytes());
+        bytes.extend_from_slice(&payload);
+        Ok(bytes)
+    }
+
+    /// Decodes a pack previously encoded with [`ProtoPack::to_bytes`].
+    ///
+    /// The header is checked before anything is decoded, so packs from other versions
+    /// or with corrupted contents are rejected.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes`: The encoded pack
+    /// * `registry`: The registry used to decode the components in the pack
+    pub fn from_bytes(
+        bytes: &[u8],
+        registry: &ProtoComponentRegistry,
+    ) -> Result<Self, ProtoPackError> {
+        let header_len = MAGIC.len() + 8;
+        if bytes.len() < header_len || &bytes[..MAGIC.len()] != MAGIC {
+            return Err(ProtoPackError::InvalidHeader);
+        }
+
+        let (version, rest) = bytes[MAGIC.len()..].split_at(4);
+        let version = u32::from_le_bytes(version.try_into().unwrap());
+        if version != PACK_VERSION {
+            return Err(ProtoPackError::UnsupportedVersion(version));
+        }
+
+        let (checksum, payload) = rest.split_at(4);
+        let checksum = u32::from_le_bytes(checksum.try_into().unwrap());
+        if checksum != crc32fast::hash(payload) {
+            return Err(ProtoPackError::ChecksumMismatch);
+        }
+
+        let (entries, tables) =
+            bincode::deserialize::<(Vec<PackedPrototype>, Vec<PackedTable>)>(payload)?;
+        let entries = entries
+            .into_iter()
+            .map(|packed| {
+                let source =
+                    ProtoSource::new(packed.path, packed.document).with_layer(packed.layer);
+                let proto = Prototype {
+                    name: packed.name,
+                    local: packed.local,
+                    templates: packed.templates,
+                    params: packed.params.unwrap_or_default(),
+                    components: unpack_components(packed.components, registry)?,
+                    patch: packed.patch,
+                    remove: packed.remove,
+                    exclude: packed.exclude,
+                    children: unpack_children(packed.children, registry)?,
+                };
+                Ok((source, Box::new(proto) as Box<dyn Prototypical>))
+            })
+            .collect::<Result<_, ProtoPackError>>()?;
+        let tables = tables
+            .into_iter()
+            .map(|packed| {
+                let source =
+                    ProtoSource::new(packed.path, packed.document).with_layer(packed.layer);
+                (source, packed.table)
+            })
+            .collect();
+
+        Ok(Self { entries, tables })
+    }
+
+    /// Writes this pack to the file at the given path (see [`to_bytes`](Self::to_bytes)).
+    pub fn save(
+        &self,
+        path: impl AsRef<Path>,
+        registry: &ProtoComponentRegistry,
+    ) -> Result<(), ProtoPackError> {
+        std::fs::write(path, self.to_bytes(registry)?)?;
+        Ok(())
+    }
+
+    /// Reads a pack from the file at the given path (see [`from_bytes`](Self::from_bytes)).
+    pub fn load(
+        path: impl AsRef<Path>,
+        registry: &ProtoComponentRegistry,
+    ) -> Result<Self, ProtoPackError> {
+        Self::from_bytes(&std::fs::read(path)?, registry)
+    }
+
+    /// Consumes this pack, returning its prototypes and its tables (each in load order) along with
+    /// their sources
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn into_parts(
+        self,
+    ) -> (
+        Vec<(ProtoSource, Box<dyn Prototypical>)>,
+        Vec<(ProtoSource, ProtoTable)>,
+    ) {
+        (self.entries, self.tables)
+    }
+}
+
+/// A borrowed prototype, as it is written to a pack
+#[derive(Serialize)]
+struct PackedPrototypeRef<'a> {
+    path: &'a Path,
+    document: usize,
+    layer: usize,
+    name: &'a str,
+    local: Option<&'a str>,
+    templates: &'a [String],
+    #[serde(with = "yaml")]
+    params: Option<&'a ProtoParams>,
+    components: Vec<PackedComponentRef<'a>>,
+    patch: Option<&'a str>,
+    remove: &'a [String],
+    exclude: &'a [String],
+    children: Vec<PackedChildRef<'a>>,
+}
+
+/// A prototype, as it is read from a pack
+#[derive(Deserialize)]
+struct PackedPrototype {
+    path: PathBuf,
+    document: usize,
+    layer: usize,
+    name: String,
+    local: Option<String>,
+    templates: Vec<String>,
+    #[serde(with = "yaml")]
+    params: Option<ProtoParams>,
+    components: Vec<PackedComponent>,
+    patch: Option<String>,
+    remove: Vec<String>,
+    exclude: Vec<String>,
+    children: Vec<PackedChild>,
+}
+
+/// A borrowed table, as it is written to a pack
+#[derive(Serialize)]
+struct PackedTableRef<'a> {
+    path: &'a Path,
+    document: usize,
+    layer: usize,
+    // Table counts are untagged, so they can only be read back by a self-describing format
+    #[serde(with = "yaml")]
+    table: &'a ProtoTable,
+}
+
+/// A table, as it is read from a pack
+#[derive(Deserialize)]
+struct PackedTable {
+    path: PathBuf,
+    document: usize,
+    layer: usize,
+    #[serde(with = "yaml")]
+    table: ProtoTable,
+}
+
+/// A borrowed [`ProtoChild`], as it is written to a pack
+#[derive(Serialize)]
+enum PackedChildRef<'a> {
+    Reference(&'a str),
+    Inline {
+        name: &'a str,
+        local: Option<&'a str>,
+        templates: &'a [String],
+        #[serde(with = "yaml")]
+        params: &'a ProtoParams,
+        components: Vec<PackedComponentRef<'a>>,
+        exclude: &'a [String],
+        children: Vec<PackedChildRef<'a>>,
+    },
+}
+
+/// A [`ProtoChild`], as it is read from a pack
+#[derive(Deserialize)]
+enum PackedChild {
+    Reference(String),
+    Inline {
+        name: String,
+        local: Option<String>,
+        templates: Vec<String>,
+        #[serde(with = "yaml")]
+        params: ProtoParams,
+        components: Vec<PackedComponent>,
+        exclude: Vec<String>,
+        children: Vec<PackedChild>,
+    },
+}
+
+fn pack_children<'a>(
+    children: &'a [ProtoChild],
+    registry: &'a ProtoComponentRegistry,
+) -> Vec<PackedChildRef<'a>> {
+    children
+        .iter()
+        .map(|child| match child {
+            ProtoChild::Reference(name) => PackedChildRef::Reference(name),
+            ProtoChild::Inline(proto) => PackedChildRef::Inline {
+                name: &proto.name,
+                local: proto.local.as_deref(),
+                templates: &proto.templates,
+                params: &proto.params,
+                components: pack_components(proto.components.iter(), registry),
+                exclude: &proto.exclude,
+                children: pack_children(&proto.children, registry),
+            },
+        })
+        .collect()
+}
+
+fn unpack_children(
+    children: Vec<PackedChild>,
+    registry: &ProtoComponentRegistry,
+) -> Result<Vec<ProtoChild>, ProtoPackError> {
+    children
+        .into_iter()
+        .map(|child| match child {
+            PackedChild::Reference(name) => Ok(ProtoChild::Reference(name)),
+            PackedChild::Inline {
+                name,
+                local,
+                templates,
+                params,
+                components,
+                exclude,
+                children,
+            } => Ok(ProtoChild::Inline(Box::new(Prototype {
+                name,
+                local,
+                templates,
+                params,
+                components: unpack_components(components, registry)?,
+                exclude,
+                children: unpack_children(children, registry)?,
+                ..Default::default()
+            }))),
+        })
+        .collect()
+}
+
+fn pack_components<'a>(
+    components: impl Iterator<Item = &'a Box<dyn ProtoComponent>>,
+    registry: &'a ProtoComponentRegistry,
+) -> Vec<PackedComponentRef<'a>> {
+    components
+        .map(|component| PackedComponentRef::new(component.as_ref(), registry))
+        .collect()
+}
+
+fn unpack_components(
+    components: Vec<PackedComponent>,
+    registry: &ProtoComponentRegistry,
+) -> Result<Vec<Box<dyn ProtoComponent>>, ProtoPackError> {
+    components
+        .into_iter()
+        .map(|component| component.into_component(registry))
+        .collect::<Result<_, _>>()
+        .map_err(|err| ProtoPackError::Encoding(Box::new(bincode::ErrorKind::Custom(err))))
+}
+
+/// A borrowed component, as it is written to a pack
+#[derive(Serialize)]
+enum PackedComponentRef<'a> {
+    Value(#[serde(serialize_with = "encode")] ComponentRef<'a>),
+    Parameterized {
+        name: &'a str,
+        #[serde(with = "yaml")]
+        value: &'a serde_yaml::Value,
+        reads: ReadsParams,
+    },
+    Merged {
+        name: &'a str,
+        #[serde(with = "yaml")]
+        merge: &'a serde_yaml::Value,
+    },
+    Reflected {
+        type_path: &'a str,
+        #[serde(with = "yaml")]
+        value: &'a serde_yaml::Value,
+    },
+}
+
+impl<'a> PackedComponentRef<'a> {
+    fn new(component: &'a dyn ProtoComponent, registry: &'a ProtoComponentRegistry) -> Self {
+        if let Some(parameterized) = as_parameterized(component) {
+            return Self::Parameterized {
+                name: parameterized.name(),
+                value: parameterized.value(),
+                reads: parameterized.reads(),
+            };
+        }
+        if let Some(merged) = as_merged(component) {
+            return Self::Merged {
+                name: merged.name(),
+                merge: merged.value(),
+            };
+        }
+        if let Some(reflected) = as_reflected(component) {
+            return Self::Reflected {
+                type_path: reflected.type_path(),
+                value: reflected.written_value(),
+            };
+        }
+        Self::Value(ComponentRef::new(registry, component))
+    }
+}
+
+/// A component, as it is read from a pack
+#[derive(Deserialize)]
+enum PackedComponent {
+    /// The component, encoded on its own so that it can be decoded with the registry
+    Value(Vec<u8>),
+    Parameterized {
+        name: String,
+        #[serde(with = "yaml")]
+        value: serde_yaml::Value,
+        reads: ReadsParams,
+    },
+    Merged {
+        name: String,
+        #[serde(with = "yaml")]
+        merge: serde_yaml::Value,
+    },
+    Reflected {
+        type_path: String,
+        #[serde(with = "yaml")]
+        value: serde_yaml::Value,
+    },
+}
+
+impl PackedComponent {
+    fn into_component(
+        self,
+        registry: &ProtoComponentRegistry,
+    ) -> Result<Box<dyn ProtoComponent>, String> {
+        Ok(match self {
+            Self::Value(bytes) => component_options()
+                .deserialize_seed(ComponentSeed::new(registry), &bytes)
+                .map_err(|err| err.to_string())?,
+            Self::Parameterized { name, value, reads } => {
+                Box::new(ParameterizedComponent::with_reads(name, value, reads))
+            }
+            Self::Merged { name, merge } => Box::new(MergedComponent::new(name, merge)),
+            // Resolved again, with the type registry of the given `ProtoComponentRegistry`
+            Self::Reflected { type_path, value } => deserialize_value(registry, &type_path, value)?,
+        })
+    }
+}
+
+/// The bincode options each [`PackedComponent::Value`] is encoded with
+fn component_options() -> impl Options {
+    bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .allow_trailing_bytes()
+}
+
+/// Encodes a component on its own (see [`PackedComponent::Value`])
+fn encode<S: Serializer>(component: &ComponentRef, serializer: S) -> Result<S::Ok, S::Error> {
+    use serde::ser::Error;
+
+    let bytes = component_options()
+        .serialize(component)
+        .map_err(S::Error::custom)?;
+    serializer.serialize_bytes(&bytes)
+}
+
+/// (De)serializes a value as a YAML string, for values that can only be read back by a
+/// self-describing format
+mod yaml {
+    use serde::de::{DeserializeOwned, Error as _};
+    use serde::ser::Error as _;
+
+    use super::*;
+
+    pub fn serialize<T: Serialize, S: serde::Serializer>(
+        value: &T,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let yaml = serde_yaml::to_string(value).map_err(S::Error::custom)?;
+        serializer.serialize_str(&yaml)
+    }
+
+    pub fn deserialize<'de, T: DeserializeOwned, D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<T, D::Error> {
+        let yaml = String::deserialize(deserializer)?;
+        serde_yaml::from_str(&yaml).map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use bevy::prelude::{AppTypeRegistry, AssetServer, Component, ReflectComponent, Res};
+    use bevy::reflect::std_traits::ReflectDefault;
+    use bevy::reflect::{FromReflect, Reflect};
+
+    use super::*;
+    use crate::data::ProtoCommands;
+
+    #[derive(Clone, Serialize, Deserialize)]
+    struct PackHealth(u32);
+
+    #[cfg_attr(feature = "typetag", typetag::serde)]
+    impl ProtoComponent for PackHealth {
+        fn insert_self(&self, _: &mut ProtoCommands, _: &Res<AssetServer>) {}
+    }
+
+    #[derive(Component, Reflect, FromReflect, Default)]
+    #[reflect(Component, Default)]
+    struct PackScale {
+        value: f32,
+    }
+
+    /// Writes the given files into an empty directory, returning the options used to read it
+    fn write_files(name: &str, files: &[(&str, &str)]) -> (PathBuf, ProtoDataOptions) {
+        let dir = std::env::temp_dir()
+            .join(format!("bevy_proto_{}", std::process::id()))
+            .join(name);
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for (file, contents) in files {
+            fs::write(dir.join(file), contents).unwrap();
+        }
+
+        let options = ProtoDataOptions {
+            directories: vec![dir.to_string_lossy().to_string()],
+            ..Default::default()
+        };
+        options.registry.register::<PackHealth>("PackHealth");
+        let types = AppTypeRegistry::default();
+        types.write().register::<PackScale>();
+        options.registry.set_type_registry(types);
+        (dir, options)
+    }
+
+    /// Builds a pack from a directory containing a prototype with params, a reflected component
+    /// and a table
+    fn build_pack(name: &str) -> (PathBuf, ProtoDataOptions, ProtoPack) {
+        let (dir, options) = write_files(
+            name,
+            &[
+                (
+                    "goblin.yaml",
+                    "name: Goblin\nparams:\n  hp: 10\ncomponents:\n  - type: PackHealth\n    value: $hp\n  - type: PackScale\n    value:\n      value: 2.0\n",
+                ),
+                (
+                    "loot.table.yaml",
+                    "name: Loot\nentries:\n  - prototype: Goblin\n    weight: 2\n",
+                ),
+            ],
+        );
+        let pack = ProtoPack::from_directories(&options).unwrap();
+        (dir, options, pack)
+    }
+
+    #[test]
+    fn round_trip() {
+        let (dir, options, pack) = build_pack("round_trip");
+        let bytes = pack.to_bytes(&options.registry).unwrap();
+        let read = ProtoPack::from_bytes(&bytes, &options.registry).unwrap();
+
+        assert_eq!(read.len(), 1);
+        let (source, goblin) = read.iter().next().unwrap();
+        assert_eq!(source, pack.iter().next().unwrap().0);
+        assert_eq!(goblin.name(), "Goblin");
+        let hp = goblin.params().and_then(|params| params.get("hp"));
+        assert_eq!(hp, Some(&serde_yaml::Value::from(10)));
+
+        let components = goblin.iter_components().collect::<Vec<_>>();
+        assert_eq!(components.len(), 2);
+        let health = as_parameterized(components[0].as_ref()).unwrap();
+        assert_eq!(health.name(), "PackHealth");
+        assert_eq!(health.reads(), ReadsParams::Always);
+        let scale = as_reflected(components[1].as_ref()).unwrap();
+        assert!(scale.type_path().ends_with("PackScale"));
+
+        assert!(read.iter_tables().eq(pack.iter_tables()));
+
+        // Writing what was read gives the same pack
+        let rewritten = read.to_bytes(&options.registry).unwrap();
+        assert_eq!(rewritten, bytes);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn checksum_mismatch() {
+        let (dir, options, pack) = build_pack("checksum_mismatch");
+        let mut bytes = pack.to_bytes(&options.registry).unwrap();
+        *bytes.last_mut().unwrap() ^= 0xFF;
+
+        assert!(matches!(
+            ProtoPack::from_bytes(&bytes, &options.registry),
+            Err(ProtoPackError::ChecksumMismatch)
+        ));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn version_mismatch() {
+        let (dir, options, pack) = build_pack("version_mismatch");
+        let mut bytes = pack.to_bytes(&options.registry).unwrap();
+        bytes[MAGIC.len()..MAGIC.len() + 4].copy_from_slice(&(PACK_VERSION - 1).to_le_bytes());
+
+        assert!(matches!(
+            ProtoPack::from_bytes(&bytes, &options.registry),
+            Err(ProtoPackError::UnsupportedVersion(version)) if version == PACK_VERSION - 1
+        ));
+        assert!(matches!(
+            ProtoPack::from_bytes(b"NOTAPACK", &options.registry),
+            Err(ProtoPackError::InvalidHeader)
+        ));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
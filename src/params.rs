@@ -0,0 +1,1183 @@
+//! Contains [`ProtoParams`], the parameters substituted into a prototype's components when it's spawned.
+use std::any::Any;
+use std::collections::BTreeMap;
+use std::iter::Peekable;
+use std::str::Chars;
+
+use bevy::prelude::{AssetServer, Res};
+use bevy::utils::HashMap;
+use indexmap::IndexSet;
+use serde::ser::{self, SerializeStruct};
+use serde::{Deserialize, Serialize, Serializer};
+use serde_yaml::value::TaggedValue;
+use serde_yaml::Value;
+
+use crate::components::ProtoComponent;
+use crate::data::{ProtoCommands, ProtoData};
+use crate::errors::{ProtoParamError, ProtoParamErrorKind};
+use crate::merge::as_merged;
+use crate::prototype::{Prototype, Prototypical};
+use crate::random::{pick_weighted, random_node, CHOOSE, RANGE};
+use crate::registry::{deserialize_value, ProtoComponentRegistry};
+
+/// The parameters of a prototype, given when spawning it with [`Prototypical::spawn_with`].
+///
+/// Prototypes declare their params (along with their default values) under `params`. Component
+/// values can then reference them, either as a whole value (`$level`), as part of an arithmetic
+/// expression (`$level * 10 + 5`), or within a string (`"${name} the Goblin"`). A literal `$` is
+/// written as `$$`.
+///
+/// ```yaml
+/// name: Goblin
+/// params:
+///   level: 1
+///   name: Grok
+/// components:
+///   - type: Health
+///     value:
+///       max: $level * 10
+///   - type: Named
+///     value: "${name} the Goblin"
+/// ```
+///
+/// Params are inherited from templates, with a prototype's own defaults taking precedence over
+/// those of its templates. References are only read in prototypes that declare `params` (even if
+/// that's just `params: {}`) or inherit them from a template that does, wherever the declaration
+/// appears in the prototype.
+///
+/// Values may also be random, either as a param's default or directly within a component's value:
+///
+/// * `{ $range: [8, 12] }`: A number between the bounds (an integer if both bounds are integers)
+/// * `{ $choose: [Grok, Grak] }`: One of the listed values, chosen uniformly
+/// * `{ $weighted: [{ weight: 3, value: Grok }, { weight: 1, value: Grak }] }`: One of the listed
+///   values, chosen according to its weight
+///
+/// Random values are evaluated each time the prototype is spawned, using the
+/// [`ProtoRng`](crate::ProtoRng) resource. Each param takes a single value per spawn, so a random
+/// param referenced twice has the same value in both places. Unlike references to params, random
+/// values are read in every prototype, whether or not it declares `params`.
+///
+/// # Examples
+///
+/// ```
+/// use bevy_proto::prelude::*;
+///
+/// let params = ProtoParams::new()
+///     .with("level", 5)
+///     .with("name", "Grak");
+///
+/// assert_eq!(params.get("level").and_then(|level| level.as_u64()), Some(5));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ProtoParams {
+    /// Maps Param Name -> Value
+    params: BTreeMap<String, Value>,
+}
+
+impl ProtoParams {
+    /// Creates an empty set of params
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the value of a param
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: The name of the param, without the `$`
+    /// * `value`: The value of the param
+    ///
+    /// returns: ProtoParams
+    pub fn with(mut self, name: impl Into<String>, value: impl Into<Value>) -> Self {
+        self.set(name, value);
+        self
+    }
+
+    /// Sets the value of a param, returning its previous value (if any)
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: The name of the param, without the `$`
+    /// * `value`: The value of the param
+    ///
+    /// returns: Option<Value>
+    pub fn set(&mut self, name: impl Into<String>, value: impl Into<Value>) -> Option<Value> {
+        self.params.insert(name.into(), value.into())
+    }
+
+    /// Get the value of a param
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.params.get(name)
+    }
+
+    /// Get an iterator over the name and value of each param
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.params
+            .iter()
+            .map(|(name, value)| (name.as_str(), value))
+    }
+
+    /// The number of params
+    pub fn len(&self) -> usize {
+        self.params.len()
+    }
+
+    /// Returns true if there are no params
+    pub fn is_empty(&self) -> bool {
+        self.params.is_empty()
+    }
+}
+
+// === Deserialization === //
+
+/// Deserializes the components of the given prototype held for referencing params, unless the
+/// prototype declares `params` (or has templates, which may declare them)
+///
+/// Without a declaration, their values are used as written, except for any random values.
+pub(crate) fn finish_components(
+    proto: &mut Prototype,
+    declared: bool,
+    registry: &ProtoComponentRegistry,
+) -> Result<(), String> {
+    if declared {
+        return Ok(());
+    }
+
+    let inherits = !proto.templates.is_empty();
+    for component in proto.components.iter_mut() {
+        let (name, value) = match as_parameterized(component.as_ref()) {
+            Some(parameterized) => (parameterized.name.clone(), parameterized.value.clone()),
+            None => continue,
+        };
+        *component = if inherits {
+            // Whether the templates declare params is only known once they've been loaded
+            Box::new(ParameterizedComponent::with_reads(
+                name,
+                value,
+                ReadsParams::Inherited,
+            ))
+        } else if has_random(&value) {
+            Box::new(ParameterizedComponent::random(name, value))
+        } else {
+            deserialize_value(registry, &name, value)?
+        };
+    }
+    Ok(())
+}
+
+/// Deserializes the merged value of a component (see [`MergedComponent`](crate::merge::MergedComponent)),
+/// unless it references params or random values
+///
+/// Unlike values read while deserializing, merged values only become known once the templates
+/// have been loaded, so references are read if the prototype or any of its templates declares
+/// `params`.
+pub(crate) fn finish_merged(
+    proto: &dyn Prototypical,
+    data: &ProtoData,
+    name: &str,
+    value: Value,
+) -> Result<Box<dyn ProtoComponent>, String> {
+    if !has_references(&value) {
+        return deserialize_value(&data.registry, name, value);
+    }
+
+    let name = name.to_string();
+    if declares_params(proto, data) {
+        Ok(Box::new(ParameterizedComponent::new(name, value)))
+    } else if has_random(&value) {
+        Ok(Box::new(ParameterizedComponent::random(name, value)))
+    } else {
+        deserialize_value(&data.registry, &name, value)
+    }
+}
+
+/// Returns true if the given prototype or any of its templates declares `params`
+fn declares_params<P: Prototypical + ?Sized>(proto: &P, data: &ProtoData) -> bool {
+    let mut traversed = IndexSet::default();
+    traversed.insert(proto.name());
+    return declares(proto, data, &mut traversed);
+
+    fn declares<'a, P: Prototypical + ?Sized>(
+        proto: &'a P,
+        data: &'a ProtoData,
+        traversed: &mut IndexSet<&'a str>,
+    ) -> bool {
+        proto.params().is_some()
+            || proto.templates().iter().any(|template| {
+                if traversed.contains(template.as_str()) {
+                    return false;
+                }
+                match data.get_prototype(template) {
+                    Some(parent) => {
+                        traversed.insert(parent.name());
+                        declares(parent, data, traversed)
+                    }
+                    None => false,
+                }
+            })
+    }
+}
+
+// === Components === //
+
+/// Whether the references in the value of a [`ParameterizedComponent`] are read as params
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) enum ReadsParams {
+    /// The prototype declares `params`
+    Always,
+    /// The prototype doesn't declare `params`, but its templates might
+    Inherited,
+    /// The value only contains random values
+    Never,
+}
+
+/// A component whose `value` references params.
+///
+/// It's deserialized each time it's spawned, once the params have been substituted.
+pub(crate) struct ParameterizedComponent {
+    /// The `type` name of the component
+    name: String,
+    /// The value containing the references
+    value: Value,
+    /// Whether the value references params, rather than only containing random values
+    reads: ReadsParams,
+}
+
+impl ParameterizedComponent {
+    pub(crate) fn new(name: String, value: Value) -> Self {
+        Self::with_reads(name, value, ReadsParams::Always)
+    }
+
+    /// Creates a component whose value only contains random values (see [`finish_components`])
+    pub(crate) fn random(name: String, value: Value) -> Self {
+        Self::with_reads(name, value, ReadsParams::Never)
+    }
+
+    pub(crate) fn with_reads(name: String, value: Value, reads: ReadsParams) -> Self {
+        Self { name, value, reads }
+    }
+
+    /// The `type` name of the component
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The value containing the references
+    #[cfg(feature = "pack")]
+    pub(crate) fn value(&self) -> &Value {
+        &self.value
+    }
+
+    /// Whether the value references params, rather than only containing random values
+    #[cfg(feature = "pack")]
+    pub(crate) fn reads(&self) -> ReadsParams {
+        self.reads
+    }
+
+    /// Resolves this component with the default params of the prototype being inserted
+    fn resolve_defaults(
+        &self,
+        commands: &ProtoCommands,
+    ) -> Result<Box<dyn ProtoComponent>, ProtoParamError> {
+        let prototype = commands.protoype();
+        let data = commands.raw_data();
+        let params = inherited_params(prototype, data, &ProtoParams::default());
+        let declared = declares_params(prototype, data);
+        data.rng
+            .with(|rng| self.resolve(&mut Substitution::new(&params, rng), data, declared))
+            .map_err(|kind| ProtoParamError {
+                prototype: prototype.name().to_string(),
+                component: self.name.clone(),
+                kind,
+            })
+    }
+
+    /// Substitutes the params (and random values) and deserializes the result
+    ///
+    /// `declared` is whether the prototype this component belongs to (or any of its templates)
+    /// declares `params`.
+    fn resolve(
+        &self,
+        substitution: &mut Substitution,
+        data: &ProtoData,
+        declared: bool,
+    ) -> Result<Box<dyn ProtoComponent>, ProtoParamErrorKind> {
+        substitution.reads_params = match self.reads {
+            ReadsParams::Always => true,
+            ReadsParams::Inherited => declared,
+            ReadsParams::Never => false,
+        };
+        let value = substitution.substitute(&self.value)?;
+        deserialize_value(&data.registry, &self.name, value)
+            .map_err(ProtoParamErrorKind::Deserialize)
+    }
+}
+
+impl ProtoComponent for ParameterizedComponent {
+    fn insert_self(&self, commands: &mut ProtoCommands, asset_server: &Res<AssetServer>) {
+        // Usually resolved by `Prototypical::insert` (or `insert_with`)
+        match commands
+            .resolved_params()
+            .and_then(|resolved| resolved.get(self))
+        {
+            Some(Some(component)) => return component.insert_self(commands, asset_server),
+            // Components that failed to resolve have already been reported
+            Some(None) => return,
+            None => {}
+        }
+
+        // Otherwise, the defaults are used
+        match self.resolve_defaults(commands) {
+            Ok(component) => component.insert_self(commands, asset_server),
+            Err(err) => bevy::log::error!("{}", err),
+        }
+    }
+
+    fn remove_self(&self, commands: &mut ProtoCommands) {
+        // Which components are removed doesn't depend on the params
+        if let Ok(component) = self.resolve_defaults(commands) {
+            component.remove_self(commands);
+        }
+    }
+
+    // Parameterized components are never deserialized by `typetag` (see `registry::ComponentVisitor`)
+    #[cfg(feature = "typetag")]
+    #[doc(hidden)]
+    fn typetag_name(&self) -> &'static str {
+        "ParameterizedComponent"
+    }
+
+    #[cfg(feature = "typetag")]
+    #[doc(hidden)]
+    fn typetag_deserialize(&self) {}
+}
+
+impl Serialize for ParameterizedComponent {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        // The references can only be read back by a self-describing format
+        if !serializer.is_human_readable() {
+            return Err(ser::Error::custom(format_args!(
+                "component `{}` references params, so it can't be stored in this format",
+                self.name
+            )));
+        }
+
+        let mut state = serializer.serialize_struct("ProtoComponent", 2)?;
+        state.serialize_field("type", &self.name)?;
+        state.serialize_field("value", &self.value)?;
+        state.end()
+    }
+}
+
+/// Returns the given component as a [`ParameterizedComponent`], if it is one
+pub(crate) fn as_parameterized(component: &dyn ProtoComponent) -> Option<&ParameterizedComponent> {
+    let component: &dyn Any = component;
+    component.downcast_ref::<ParameterizedComponent>()
+}
+
+/// The components of a prototype (and its templates) resolved for a single spawn
+#[derive(Default)]
+pub(crate) struct ResolvedParams {
+    /// Maps Component Address -> Resolved Component (or `None` if it failed to resolve)
+    components: HashMap<usize, Option<Box<dyn ProtoComponent>>>,
+}
+
+impl ResolvedParams {
+    fn get(&self, component: &ParameterizedComponent) -> Option<Option<&dyn ProtoComponent>> {
+        self.components
+            .get(&address(component))
+            .map(|component| component.as_deref())
+    }
+}
+
+fn address(component: &ParameterizedComponent) -> usize {
+    component as *const ParameterizedComponent as usize
+}
+
+/// Resolves every [`ParameterizedComponent`] inserted when spawning the given prototype,
+/// failing if any of them can't be resolved
+///
+/// returns: Result<ResolvedParams, ProtoParamError>
+pub(crate) fn resolve_all<P: Prototypical + ?Sized>(
+    proto: &P,
+    data: &ProtoData,
+    given: &ProtoParams,
+) -> Result<ResolvedParams, ProtoParamError> {
+    resolve_with(proto, data, given, true)
+}
+
+/// Resolves every [`ParameterizedComponent`] inserted when spawning the given prototype with its
+/// default params, logging (and skipping) those which can't be resolved
+pub(crate) fn resolve_defaults<P: Prototypical + ?Sized>(
+    proto: &P,
+    data: &ProtoData,
+) -> ResolvedParams {
+    resolve_with(proto, data, &ProtoParams::default(), false).unwrap_or_default()
+}
+
+fn resolve_with<P: Prototypical + ?Sized>(
+    proto: &P,
+    data: &ProtoData,
+    given: &ProtoParams,
+    strict: bool,
+) -> Result<ResolvedParams, ProtoParamError> {
+    let mut resolved = ResolvedParams::default();
+    let mut found = false;
+    visit_parameterized(proto, data, &mut traversed(proto), &[], &mut |_, _| {
+        found = true
+    });
+    if !found {
+        return Ok(resolved);
+    }
+
+    // Every component shares the same substitution, so each param has a single value per spawn
+    let params = inherited_params(proto, data, given);
+    data.rng.with(|rng| {
+        let mut substitution = Substitution::new(&params, rng);
+        let mut failed = None;
+        let mut visit = |component: &ParameterizedComponent, declared: bool| {
+            if failed.is_some() {
+                return;
+            }
+
+            let result = component
+                .resolve(&mut substitution, data, declared)
+                .map_err(|kind| ProtoParamError {
+                    prototype: proto.name().to_string(),
+                    component: component.name.clone(),
+                    kind,
+                });
+
+            match result {
+                Ok(value) => {
+                    resolved.components.insert(address(component), Some(value));
+                }
+                Err(err) if strict => failed = Some(err),
+                Err(err) => {
+                    bevy::log::error!("{}", err);
+                    resolved.components.insert(address(component), None);
+                }
+            }
+        };
+        visit_parameterized(proto, data, &mut traversed(proto), &[], &mut visit);
+
+        match failed {
+            Some(err) => Err(err),
+            None => Ok(resolved),
+        }
+    })
+}
+
+/// The set of traversed prototypes, starting from the given one
+fn traversed<P: Prototypical + ?Sized>(proto: &P) -> IndexSet<&str> {
+    let mut traversed = IndexSet::default();
+    traversed.insert(proto.name());
+    traversed
+}
+
+/// Visits the [`ParameterizedComponent`]s of the given prototype and its templates (including
+/// those resolved from a [`MergedComponent`](crate::merge::MergedComponent)), following the same
+/// order (and exclusions) as spawning
+///
+/// Each is visited along with whether the prototype it belongs to (or any of its templates)
+/// declares `params`.
+fn visit_parameterized<'a, P: Prototypical + ?Sized>(
+    proto: &'a P,
+    data: &'a ProtoData,
+    traversed: &mut IndexSet<&'a str>,
+    excluded: &[&'a str],
+    visit: &mut dyn FnMut(&ParameterizedComponent, bool),
+) {
+    let template_excluded = excluded
+        .iter()
+        .copied()
+        .chain(proto.excluded_components().iter().map(String::as_str))
+        .collect::<Vec<_>>();
+
+    for template in proto.templates_rev() {
+        if traversed.contains(template.as_str()) {
+            // Cycles are reported separately
+            continue;
+        }
+
+        if let Some(parent) = data.get_prototype(template) {
+            traversed.insert(parent.name());
+            visit_parameterized(parent, data, traversed, &template_excluded, visit);
+        }
+    }
+
+    // Only checked for prototypes with parameterized components
+    let mut declared = None;
+    for component in proto.iter_components() {
+        let mut visit_included = |component: &ParameterizedComponent| {
+            if !excluded.contains(&component.name()) {
+                let declared = *declared.get_or_insert_with(|| declares_params(proto, data));
+                visit(component, declared);
+            }
+        };
+
+        match as_merged(component.as_ref()) {
+            Some(merged) => merged.with_resolved(|resolved| {
+                if let Some(parameterized) = resolved.and_then(as_parameterized) {
+                    visit_included(parameterized);
+                }
+            }),
+            None => {
+                if let Some(parameterized) = as_parameterized(component.as_ref()) {
+                    visit_included(parameterized);
+                }
+            }
+        }
+    }
+}
+
+/// Combines the given params with the defaults declared by the prototype and its templates
+///
+/// Given params take precedence, followed by the prototype's own defaults, then those of its
+/// templates (in the same order used to override components).
+pub(crate) fn inherited_params<P: Prototypical + ?Sized>(
+    proto: &P,
+    data: &ProtoData,
+    given: &ProtoParams,
+) -> ProtoParams {
+    let mut params = given.clone();
+    let mut traversed = IndexSet::default();
+    traversed.insert(proto.name());
+    add_defaults(proto, data, &mut params, &mut traversed);
+    return params;
+
+    fn add_defaults<'a, P: Prototypical + ?Sized>(
+        proto: &'a P,
+        data: &'a ProtoData,
+        params: &mut ProtoParams,
+        traversed: &mut IndexSet<&'a str>,
+    ) {
+        for (name, value) in proto.params().into_iter().flat_map(ProtoParams::iter) {
+            if params.get(name).is_none() {
+                params.set(name, value.clone());
+            }
+        }
+
+        for template in proto.templates() {
+            if traversed.contains(template.as_str()) {
+                continue;
+            }
+
+            if let Some(parent) = data.get_prototype(template) {
+                traversed.insert(parent.name());
+                add_defaults(parent, data, params, traversed);
+            }
+        }
+    }
+}
+
+// === Substitution === //
+
+/// Checks whether the given value contains any references to params (or random values)
+pub(crate) fn has_references(value: &Value) -> bool {
+    match value {
+        Value::String(string) => string.contains('$'),
+        Value::Sequence(values) => values.iter().any(has_references),
+        Value::Mapping(map) => random_node(map).is_some() || map.values().any(has_references),
+        Value::Tagged(tagged) => has_references(&tagged.value),
+        _ => false,
+    }
+}
+
+/// Returns true if the given value contains a random value (such as `{ $range: [8, 12] }`)
+fn has_random(value: &Value) -> bool {
+    match value {
+        Value::Sequence(values) => values.iter().any(has_random),
+        Value::Mapping(map) => random_node(map).is_some() || map.values().any(has_random),
+        Value::Tagged(tagged) => has_random(&tagged.value),
+        _ => false,
+    }
+}
+
+/// Substitutes params and evaluates random values for a single spawn
+struct Substitution<'a> {
+    params: &'a ProtoParams,
+    /// Whether strings are checked for references to params (otherwise only random values are
+    /// evaluated)
+    reads_params: bool,
+    /// Maps Param Name -> Substituted Value
+    ///
+    /// Params may themselves be random (or reference other params), so each is only substituted
+    /// once per spawn.
+    substituted: HashMap<String, Value>,
+    /// The params currently being substituted, used to catch params referencing themselves
+    substituting: Vec<String>,
+    rng: &'a mut fastrand::Rng,
+}
+
+impl<'a> Substitution<'a> {
+    fn new(params: &'a ProtoParams, rng: &'a mut fastrand::Rng) -> Self {
+        Self {
+            params,
+            reads_params: true,
+            substituted: HashMap::default(),
+            substituting: Vec::new(),
+            rng,
+        }
+    }
+
+    /// Replaces every reference in the given value with the value of its param
+    fn substitute(&mut self, value: &Value) -> Result<Value, ProtoParamErrorKind> {
+        Ok(match value {
+            Value::String(string) if self.reads_params => self.substitute_str(string)?,
+            Value::Sequence(values) => Value::Sequence(
+                values
+                    .iter()
+                    .map(|value| self.substitute(value))
+                    .collect::<Result<_, _>>()?,
+            ),
+            Value::Mapping(map) => match random_node(map) {
+                Some((key, arg)) => self.random(key, arg)?,
+                None => Value::Mapping(
+                    map.iter()
+                        .map(|(key, value)| Ok((key.clone(), self.substitute(value)?)))
+                        .collect::<Result<_, _>>()?,
+                ),
+            },
+            Value::Tagged(tagged) => Value::Tagged(Box::new(TaggedValue {
+                tag: tagged.tag.clone(),
+                value: self.substitute(&tagged.value)?,
+            })),
+            value => value.clone(),
+        })
+    }
+
+    fn substitute_str(&mut self, string: &str) -> Result<Value, ProtoParamErrorKind> {
+        // === Whole Value or Expression === //
+        if let Some(name) = string.strip_prefix('$').filter(|name| is_identifier(name)) {
+            return self.param(name);
+        }
+        if is_expression(string) {
+            return self.evaluate(string).map(Number::into_value);
+        }
+
+        // === Interpolation === //
+        let mut result = String::with_capacity(string.len());
+        let mut rest = string;
+        while let Some(index) = rest.find('$') {
+            result.push_str(&rest[..index]);
+            rest = &rest[index + 1..];
+
+            if let Some(escaped) = rest.strip_prefix('$') {
+                result.push('$');
+                rest = escaped;
+            } else if let Some(inner) = rest.strip_prefix('{') {
+                let end =
+                    inner
+                        .find('}')
+                        .ok_or_else(|| ProtoParamErrorKind::InvalidExpression {
+                            expression: string.to_string(),
+                            reason: String::from("`${` is never closed"),
+                        })?;
+                let name = inner[..end].trim();
+                match self.param(name)? {
+                    Value::String(value) => result.push_str(&value),
+                    Value::Number(value) => result.push_str(&value.to_string()),
+                    Value::Bool(value) => result.push_str(&value.to_string()),
+                    _ => {
+                        return Err(ProtoParamErrorKind::InvalidType {
+                            param: name.to_string(),
+                            expected: "a string, number, or bool",
+                        })
+                    }
+                }
+                rest = &inner[end + 1..];
+            } else {
+                // A lone `$` is kept as-is
+                result.push('$');
+            }
+        }
+        result.push_str(rest);
+
+        Ok(Value::String(result))
+    }
+
+    /// The substituted value of the given param
+    fn param(&mut self, name: &str) -> Result<Value, ProtoParamErrorKind> {
+        if let Some(value) = self.substituted.get(name) {
+            return Ok(value.clone());
+        }
+
+        let value = self
+            .params
+            .get(name)
+            .ok_or_else(|| ProtoParamErrorKind::Missing {
+                param: name.to_string(),
+            })?;
+        if self.substituting.iter().any(|param| param == name) {
+            return Err(ProtoParamErrorKind::InvalidExpression {
+                expression: format!("${}", name),
+                reason: String::from("the param references itself"),
+            });
+        }
+
+        self.substituting.push(name.to_string());
+        let value = self.substitute(value);
+        self.substituting.pop();
+
+        let value = value?;
+        self.substituted.insert(name.to_string(), value.clone());
+        Ok(value)
+    }
+
+    /// Evaluates the random value with the given key (such as `$range`) and argument
+    fn random(&mut self, key: &str, arg: &Value) -> Result<Value, ProtoParamErrorKind> {
+        let invalid = |reason: &str| ProtoParamErrorKind::InvalidRandom {
+            node: key.to_string(),
+            reason: reason.to_string(),
+        };
+
+        match key {
+            RANGE => {
+                let bounds = match self.substitute(arg)? {
+                    Value::Sequence(bounds) if bounds.len() == 2 => bounds,
+                    _ => return Err(invalid("expected a list of two numbers")),
+                };
+                let min =
+                    number(&bounds[0]).ok_or_else(|| invalid("the minimum isn't a number"))?;
+                let max =
+                    number(&bounds[1]).ok_or_else(|| invalid("the maximum isn't a number"))?;
+
+                match (min, max) {
+                    (Number::Int(min), Number::Int(max)) if min <= max => {
+                        Ok(Value::from(self.rng.i64(min..=max)))
+                    }
+                    (min, max) if min.as_f64() <= max.as_f64() => {
+                        let (min, max) = (min.as_f64(), max.as_f64());
+                        Ok(Value::from(min + self.rng.f64() * (max - min)))
+                    }
+                    _ => Err(invalid("the minimum is greater than the maximum")),
+                }
+            }
+            CHOOSE => {
+                let choices = match arg {
+                    Value::Sequence(choices) if !choices.is_empty() => choices,
+                    _ => return Err(invalid("expected a non-empty list")),
+                };
+                let index = self.rng.usize(..choices.len());
+                self.substitute(&choices[index])
+            }
+            _ => {
+                let entries = match arg {
+                    Value::Sequence(entries) if !entries.is_empty() => entries,
+                    _ => return Err(invalid("expected a non-empty list")),
+                };
+
+                let mut weights = Vec::with_capacity(entries.len());
+                for entry in entries {
+                    if entry.get("value").is_none() {
+                        return Err(invalid("each entry needs a `value`"));
+                    }
+                    let weight = entry
+                        .get("weight")
+                        .ok_or_else(|| invalid("each entry needs a `weight`"))?;
+                    let weight = number(&self.substitute(weight)?)
+                        .ok_or_else(|| invalid("each `weight` must be a number"))?;
+                    weights.push(weight.as_f64());
+                }
+
+                let index = pick_weighted(self.rng, &weights)
+                    .ok_or_else(|| invalid("at least one `weight` must be positive"))?;
+                self.substitute(&entries[index]["value"])
+            }
+        }
+    }
+
+    /// Evaluates an arithmetic expression (such as `$level * 10 + 5`)
+    ///
+    /// Expressions support numbers, params, parentheses, and the `+`, `-`, `*`, `/`, and `%`
+    /// operators. The result is an integer unless a float is involved (or a division has a
+    /// remainder).
+    fn evaluate(&mut self, expression: &str) -> Result<Number, ProtoParamErrorKind> {
+        let mut parser = Parser {
+            expression,
+            chars: expression.chars().peekable(),
+            substitution: self,
+        };
+        let value = parser.sum()?;
+        parser.skip_whitespace();
+        match parser.chars.next() {
+            None => Ok(value),
+            Some(c) => Err(parser.error(format!("unexpected `{}`", c))),
+        }
+    }
+}
+
+/// Checks whether the given string is an arithmetic expression, rather than a string to
+/// interpolate
+///
+/// This is the case if it references a param and only contains numbers, operators, and parentheses
+/// otherwise (such as `($level + 1) * 2`).
+fn is_expression(string: &str) -> bool {
+    let mut chars = string.chars().peekable();
+    let mut references = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '$' if chars.next_if(|c| is_identifier_char(*c)).is_some() => {
+                while chars.next_if(|c| is_identifier_char(*c)).is_some() {}
+                references = true;
+            }
+            '0'..='9' | '.' | '+' | '-' | '*' | '/' | '%' | '(' | ')' => {}
+            c if c.is_whitespace() => {}
+            _ => return false,
+        }
+    }
+    references
+}
+
+fn is_identifier(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(is_identifier_char)
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+// === Expressions === //
+
+/// A number produced by an expression
+#[derive(Copy, Clone)]
+enum Number {
+    Int(i64),
+    Float(f64),
+}
+
+impl Number {
+    fn as_f64(self) -> f64 {
+        match self {
+            Self::Int(value) => value as f64,
+            Self::Float(value) => value,
+        }
+    }
+
+    fn into_value(self) -> Value {
+        match self {
+            Self::Int(value) => Value::from(value),
+            Self::Float(value) => Value::from(value),
+        }
+    }
+}
+
+/// Reads the given value as a number (if it is one)
+fn number(value: &Value) -> Option<Number> {
+    match value {
+        Value::Number(number) => match number.as_i64() {
+            Some(value) => Some(Number::Int(value)),
+            None => number.as_f64().map(Number::Float),
+        },
+        _ => None,
+    }
+}
+
+struct Parser<'a, 'b> {
+    expression: &'a str,
+    chars: Peekable<Chars<'a>>,
+    substitution: &'a mut Substitution<'b>,
+}
+
+impl<'a, 'b> Parser<'a, 'b> {
+    fn sum(&mut self) -> Result<Number, ProtoParamErrorKind> {
+        let mut value = self.product()?;
+        loop {
+            self.skip_whitespace();
+            let op = match self.chars.peek() {
+                Some(op @ ('+' | '-')) => *op,
+                _ => return Ok(value),
+            };
+            self.chars.next();
+            let rhs = self.product()?;
+            value = self.apply(op, value, rhs)?;
+        }
+    }
+
+    fn product(&mut self) -> Result<Number, ProtoParamErrorKind> {
+        let mut value = self.factor()?;
+        loop {
+            self.skip_whitespace();
+            let op = match self.chars.peek() {
+                Some(op @ ('*' | '/' | '%')) => *op,
+                _ => return Ok(value),
+            };
+            self.chars.next();
+            let rhs = self.factor()?;
+            value = self.apply(op, value, rhs)?;
+        }
+    }
+
+    fn factor(&mut self) -> Result<Number, ProtoParamErrorKind> {
+        self.skip_whitespace();
+        match self.chars.peek().copied() {
+            Some('-') => {
+                self.chars.next();
+                match self.factor()? {
+                    Number::Int(value) => value
+                        .checked_neg()
+                        .map(Number::Int)
+                        .ok_or_else(|| self.error(String::from("integer overflow"))),
+                    Number::Float(value) => Ok(Number::Float(-value)),
+                }
+            }
+            Some('(') => {
+                self.chars.next();
+                let value = self.sum()?;
+                self.skip_whitespace();
+                match self.chars.next() {
+                    Some(')') => Ok(value),
+                    _ => Err(self.error(String::from("expected `)`"))),
+                }
+            }
+            Some('$') => {
+                self.chars.next();
+                let name = self.take_while(is_identifier_char);
+                let value = self.substitution.param(&name)?;
+                number(&value).ok_or(ProtoParamErrorKind::InvalidType {
+                    param: name,
+                    expected: "a number",
+                })
+            }
+            Some(c) if c.is_ascii_digit() || c == '.' => {
+                let number = self.take_while(|c| c.is_ascii_digit() || c == '.');
+                match number.parse::<i64>() {
+                    Ok(value) => Ok(Number::Int(value)),
+                    Err(_) => number
+                        .parse::<f64>()
+                        .map(Number::Float)
+                        .map_err(|_| self.error(format!("invalid number `{}`", number))),
+                }
+            }
+            Some(c) => Err(self.error(format!("unexpected `{}`", c))),
+            None => Err(self.error(String::from("unexpected end of expression"))),
+        }
+    }
+
+    fn apply(&self, op: char, lhs: Number, rhs: Number) -> Result<Number, ProtoParamErrorKind> {
+        if let (Number::Int(lhs), Number::Int(rhs)) = (lhs, rhs) {
+            let value = match op {
+                '+' => lhs.checked_add(rhs),
+                '-' => lhs.checked_sub(rhs),
+                '*' => lhs.checked_mul(rhs),
+                '/' | '%' if rhs == 0 => return Err(self.error(String::from("division by zero"))),
+                '/' => match lhs.checked_rem(rhs) {
+                    Some(0) => lhs.checked_div(rhs),
+                    Some(_) => return Ok(Number::Float(lhs as f64 / rhs as f64)),
+                    None => None,
+                },
+                _ => lhs.checked_rem(rhs),
+            };
+            return value
+                .map(Number::Int)
+                .ok_or_else(|| self.error(String::from("integer overflow")));
+        }
+
+        let (lhs, rhs) = (lhs.as_f64(), rhs.as_f64());
+        Ok(Number::Float(match op {
+            '+' => lhs + rhs,
+            '-' => lhs - rhs,
+            '*' => lhs * rhs,
+            '/' => lhs / rhs,
+            _ => lhs % rhs,
+        }))
+    }
+
+    fn take_while(&mut self, predicate: impl Fn(char) -> bool) -> String {
+        let mut taken = String::new();
+        while let Some(c) = self.chars.next_if(|c| predicate(*c)) {
+            taken.push(c);
+        }
+        taken
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|c| c.is_whitespace()).is_some() {}
+    }
+
+    fn error(&self, reason: String) -> ProtoParamErrorKind {
+        ProtoParamErrorKind::InvalidExpression {
+            expression: self.expression.to_string(),
+            reason,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use bevy::asset::FileAssetIo;
+    use bevy::ecs::system::SystemState;
+    use bevy::prelude::{Commands, Component, FromWorld, World};
+
+    use super::*;
+    use crate::data::ProtoDataOptions;
+
+    #[derive(Component, Clone, Debug, PartialEq, Serialize, Deserialize)]
+    struct ParamHealth {
+        value: u32,
+    }
+
+    #[cfg_attr(feature = "typetag", typetag::serde)]
+    impl ProtoComponent for ParamHealth {
+        fn insert_self(&self, commands: &mut ProtoCommands, _: &Res<AssetServer>) {
+            commands.insert(self.clone());
+        }
+    }
+
+    fn substitute(value: &str, params: &ProtoParams) -> Result<Value, ProtoParamErrorKind> {
+        let mut rng = fastrand::Rng::with_seed(0);
+        Substitution::new(params, &mut rng).substitute(&Value::from(value))
+    }
+
+    fn overflow(expression: &str) -> ProtoParamErrorKind {
+        ProtoParamErrorKind::InvalidExpression {
+            expression: expression.to_string(),
+            reason: String::from("integer overflow"),
+        }
+    }
+
+    #[test]
+    fn precedence() {
+        let params = ProtoParams::new().with("a", 2);
+        assert_eq!(substitute("$a + 3 * 4", &params), Ok(Value::from(14)));
+        assert_eq!(substitute("($a + 3) * 4", &params), Ok(Value::from(20)));
+        assert_eq!(substitute("$a * 10 - 4 - 3", &params), Ok(Value::from(13)));
+        assert_eq!(substitute("-$a * -3 % 4", &params), Ok(Value::from(2)));
+        assert_eq!(substitute("$a * 1.5", &params), Ok(Value::from(3.0)));
+    }
+
+    #[test]
+    fn division() {
+        let params = ProtoParams::new().with("a", 6);
+        assert_eq!(substitute("$a / 3", &params), Ok(Value::from(2)));
+        assert_eq!(substitute("$a / 4", &params), Ok(Value::from(1.5)));
+        assert_eq!(substitute("$a % 4", &params), Ok(Value::from(2)));
+        assert_eq!(
+            substitute("$a / 0", &params),
+            Err(ProtoParamErrorKind::InvalidExpression {
+                expression: String::from("$a / 0"),
+                reason: String::from("division by zero"),
+            })
+        );
+    }
+
+    #[test]
+    fn overflow_is_an_error() {
+        let max = ProtoParams::new().with("a", i64::MAX);
+        assert_eq!(substitute("$a + 1", &max), Err(overflow("$a + 1")));
+        assert_eq!(substitute("$a * 2", &max), Err(overflow("$a * 2")));
+
+        let min = ProtoParams::new().with("a", i64::MIN);
+        assert_eq!(substitute("-$a", &min), Err(overflow("-$a")));
+        assert_eq!(substitute("$a / -1", &min), Err(overflow("$a / -1")));
+        assert_eq!(substitute("$a % -1", &min), Err(overflow("$a % -1")));
+        assert_eq!(substitute("$a - 1", &min), Err(overflow("$a - 1")));
+    }
+
+    #[test]
+    fn missing_params() {
+        let params = ProtoParams::new().with("a", 1);
+        let missing = Err(ProtoParamErrorKind::Missing {
+            param: String::from("b"),
+        });
+        assert_eq!(substitute("$b", &params), missing);
+        assert_eq!(substitute("$a + $b", &params), missing);
+        assert_eq!(substitute("Level ${b}", &params), missing);
+    }
+
+    #[test]
+    fn ill_typed_params() {
+        let params = ProtoParams::new()
+            .with("name", "Grok")
+            .with("list", vec![1, 2]);
+        assert_eq!(
+            substitute("$name + 1", &params),
+            Err(ProtoParamErrorKind::InvalidType {
+                param: String::from("name"),
+                expected: "a number",
+            })
+        );
+        assert_eq!(
+            substitute("Items: ${list}", &params),
+            Err(ProtoParamErrorKind::InvalidType {
+                param: String::from("list"),
+                expected: "a string, number, or bool",
+            })
+        );
+        assert_eq!(
+            substitute("${name} the Goblin", &params),
+            Ok(Value::from("Grok the Goblin"))
+        );
+    }
+
+    #[test]
+    fn random_without_params() {
+        let value: Value =
+            serde_yaml::from_str("{ name: $name, health: { $range: [8, 8] } }").unwrap();
+        assert!(has_random(&value));
+
+        let params = ProtoParams::new();
+        let mut rng = fastrand::Rng::with_seed(0);
+        let mut substitution = Substitution::new(&params, &mut rng);
+        substitution.reads_params = false;
+        assert_eq!(
+            substitution.substitute(&value),
+            Ok(serde_yaml::from_str("{ name: $name, health: 8 }").unwrap())
+        );
+    }
+
+    #[test]
+    fn params_inherited_from_templates() {
+        let dir = std::env::temp_dir()
+            .join(format!("bevy_proto_{}", std::process::id()))
+            .join("params_inherited_from_templates");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("base.yaml"),
+            "name: Base\nparams:\n  hp: 10\ncomponents: []\n",
+        )
+        .unwrap();
+        // Only the template declares `params`
+        fs::write(
+            dir.join("goblin.yaml"),
+            "name: Goblin\ntemplates: Base\ncomponents:\n  - type: ParamHealth\n    value: { value: $hp }\n",
+        )
+        .unwrap();
+
+        let mut world = World::new();
+        world.insert_resource(AssetServer::new(FileAssetIo::new(&dir, false)));
+        let options = ProtoDataOptions {
+            directories: vec![dir.to_string_lossy().to_string()],
+            ..Default::default()
+        };
+        options.registry.register::<ParamHealth>("ParamHealth");
+        world.insert_resource(options);
+        let data = ProtoData::from_world(&mut world);
+        world.insert_resource(data);
+        let _ = fs::remove_dir_all(&dir);
+
+        let mut spawn = |params: &ProtoParams| {
+            let mut state =
+                SystemState::<(Commands, Res<ProtoData>, Res<AssetServer>)>::new(&mut world);
+            let (mut commands, data, asset_server) = state.get_mut(&mut world);
+            let entity = data
+                .get_prototype("Goblin")
+                .unwrap()
+                .spawn_with(&mut commands, &data, &asset_server, params)
+                .unwrap()
+                .id();
+            state.apply(&mut world);
+            world.get::<ParamHealth>(entity).cloned()
+        };
+        assert_eq!(spawn(&ProtoParams::new()), Some(ParamHealth { value: 10 }));
+        assert_eq!(
+            spawn(&ProtoParams::new().with("hp", 3)),
+            Some(ParamHealth { value: 3 })
+        );
+    }
+}
@@ -1,10 +1,18 @@
 //! Contains [`ProtoPlugin`].
-use bevy::app::{App, Plugin};
+use std::path::PathBuf;
 
-use crate::{
-    data::{ProtoData, ProtoDataOptions, ProtoDeserializer},
-    prototype::{Prototype, Prototypical},
+use bevy::app::{App, AppTypeRegistry, CoreSet, Plugin};
+use bevy::asset::{AddAsset, AssetPlugin, AssetServer};
+use bevy::ecs::schedule::IntoSystemConfig;
+
+use crate::asset::{
+    sync_prototype_assets, PrototypeAsset, PrototypeAssetLoader, PrototypeAssetNames,
 };
+use crate::data::{ProtoData, ProtoDataOptions};
+use crate::deserializers::default_extensions;
+use crate::instance::{update_proto_instances, ProtoInstances};
+use crate::loading::{poll_proto_load_tasks, ProtoDataLoaded};
+use crate::registry::ProtoComponentRegistry;
 
 /// Inserts resources for loading prototypes.
 #[derive(Default)]
@@ -33,8 +41,8 @@ impl ProtoPlugin {
             options: Some(ProtoDataOptions {
                 directories: vec![dir.to_string()],
                 recursive_loading: false,
-                deserializer: Box::new(DefaultProtoDeserializer),
-                extensions: Some(vec!["yaml", "json"]),
+                extensions: Some(default_extensions()),
+                ..ProtoDataOptions::default()
             }),
         }
     }
@@ -58,8 +66,8 @@ impl ProtoPlugin {
             options: Some(ProtoDataOptions {
                 directories: vec![dir.to_string()],
                 recursive_loading: true,
-                deserializer: Box::new(DefaultProtoDeserializer),
-                extensions: Some(vec!["yaml", "json"]),
+                extensions: Some(default_extensions()),
+                ..ProtoDataOptions::default()
             }),
         }
     }
@@ -85,8 +93,8 @@ impl ProtoPlugin {
             options: Some(ProtoDataOptions {
                 directories: dirs,
                 recursive_loading: false,
-                deserializer: Box::new(DefaultProtoDeserializer),
-                extensions: Some(vec!["yaml", "json"]),
+                extensions: Some(default_extensions()),
+                ..ProtoDataOptions::default()
             }),
         }
     }
@@ -112,8 +120,8 @@ impl ProtoPlugin {
             options: Some(ProtoDataOptions {
                 directories: dirs,
                 recursive_loading: true,
-                deserializer: Box::new(DefaultProtoDeserializer),
-                extensions: Some(vec!["yaml", "json"]),
+                extensions: Some(default_extensions()),
+                ..ProtoDataOptions::default()
             }),
         }
     }
@@ -122,33 +130,56 @@ impl ProtoPlugin {
 impl Plugin for ProtoPlugin {
     fn build(&self, app: &mut App) {
         let opts = self.options.clone();
-        let opts = opts.unwrap_or(ProtoDataOptions {
+        let mut opts = opts.unwrap_or(ProtoDataOptions {
             directories: vec![String::from("assets/prototypes")],
             recursive_loading: false,
-            deserializer: Box::new(DefaultProtoDeserializer),
-            extensions: Some(vec!["yaml", "json"]),
+            extensions: Some(default_extensions()),
+            ..ProtoDataOptions::default()
         });
 
+        // Share the app's registry with the options (so components registered later are included)
+        let registry = app
+            .world
+            .get_resource_or_insert_with(ProtoComponentRegistry::default)
+            .clone();
+        registry.extend(&opts.registry);
+        if let Some(type_registry) = app.world.get_resource::<AppTypeRegistry>() {
+            registry.set_type_registry(type_registry.clone());
+        }
+        opts.registry = registry;
+
         #[cfg(feature = "hot_reloading")]
         app.add_plugin(crate::hot_reload::HotReloadPlugin {
-            path: opts.directories[0].clone(),
+            directories: opts.directories.clone(),
+            recursive: opts.recursive_loading,
         });
 
-        app.insert_resource(opts);
-        // Initialize prototypes
-        app.init_resource::<ProtoData>();
-    }
-}
-
-#[derive(Clone)]
-pub(crate) struct DefaultProtoDeserializer;
+        // Allow prototype files to be loaded as assets (requires the `AssetPlugin`)
+        if app.world.contains_resource::<AssetServer>() && !opts.asset_extensions.is_empty() {
+            // Asset paths are relative to the asset folder, while prototype sources are not
+            let asset_folder = app.get_added_plugins::<AssetPlugin>().first().map_or_else(
+                || PathBuf::from("assets"),
+                |plugin| PathBuf::from(&plugin.asset_folder),
+            );
+            app.add_asset::<PrototypeAsset>()
+                .add_asset_loader(PrototypeAssetLoader {
+                    options: opts.clone(),
+                    asset_folder,
+                })
+                .init_resource::<PrototypeAssetNames>()
+                .add_system(sync_prototype_assets);
+        }
 
-impl ProtoDeserializer for DefaultProtoDeserializer {
-    fn deserialize(&self, data: &str) -> Option<Box<dyn Prototypical>> {
-        if let Ok(value) = serde_yaml::from_str::<Prototype>(data) {
-            Some(Box::new(value))
-        } else {
-            None
+        // Keep the instance index up to date (including entities despawned late in the frame)
+        if opts.marks_instances() {
+            app.init_resource::<ProtoInstances>()
+                .add_system(update_proto_instances.in_base_set(CoreSet::Last));
         }
+
+        app.insert_resource(opts)
+            .add_event::<ProtoDataLoaded>()
+            .add_system(poll_proto_load_tasks);
+        // Initialize prototypes
+        app.init_resource::<ProtoData>();
     }
 }
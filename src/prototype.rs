@@ -2,18 +2,28 @@
 use std::fmt::Formatter;
 use std::iter::Rev;
 use std::slice::Iter;
+use std::sync::Arc;
 
 use bevy::ecs::prelude::Commands;
 use bevy::ecs::system::EntityCommands;
 use bevy::prelude::{AssetServer, Res};
 use indexmap::IndexSet;
 use serde::{
-    de::{self, Error, SeqAccess, Visitor},
-    Deserialize, Deserializer, Serialize,
+    de::{self, DeserializeSeed, Error, MapAccess, SeqAccess, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
 };
 
 use crate::{
-    components::ProtoComponent, data::ProtoCommands, data::ProtoData, utils::handle_cycle,
+    children,
+    children::{ChildrenSeed, ProtoChild, ProtoChildren},
+    components::ProtoComponent,
+    data::ProtoCommands,
+    data::ProtoData,
+    errors::ProtoParamError,
+    params,
+    params::ProtoParams,
+    registry::{ComponentRef, ComponentsSeed, ProtoComponentRegistry},
+    utils::handle_cycle,
 };
 
 /// Allows access to a prototype's name and components so that it can be spawned in
@@ -28,6 +38,89 @@ pub trait Prototypical: 'static + Send + Sync {
         &[]
     }
 
+    /// Changes the name of the prototype.
+    ///
+    /// This is used to assign the prototype its ID when loading with
+    /// [`ProtoDataOptions::namespacing`](crate::data::ProtoDataOptions::namespacing) enabled,
+    /// or to name it after its file when it has no name of its own. Prototypes that can't be renamed
+    /// may leave this unimplemented.
+    fn rename(&mut self, _name: String) {}
+
+    /// Mutable access to the names of the parent templates, if they can be modified.
+    ///
+    /// This is used to resolve relative template references when loading with
+    /// [`ProtoDataOptions::namespacing`](crate::data::ProtoDataOptions::namespacing) enabled.
+    fn templates_mut(&mut self) -> Option<&mut Vec<String>> {
+        None
+    }
+
+    /// The name of the prototype this patches, if this is a patch rather than a prototype of its own.
+    ///
+    /// Instead of being added, a patch modifies the components of its target when it is loaded.
+    /// See [`Prototype::patch`] for details.
+    fn patch(&self) -> Option<&str> {
+        None
+    }
+
+    /// Mutable access to the name of the prototype this patches, if this is a patch.
+    ///
+    /// This is used to resolve relative references to the target when loading with
+    /// [`ProtoDataOptions::namespacing`](crate::data::ProtoDataOptions::namespacing) enabled.
+    fn patch_mut(&mut self) -> Option<&mut String> {
+        None
+    }
+
+    /// The `type` names of the components a patch removes from its target.
+    fn removed_components(&self) -> &[String] {
+        &[]
+    }
+
+    /// The `type` names of the components this prototype excludes from its templates.
+    ///
+    /// Components of these types are skipped when inserting any of this prototype's templates
+    /// (and their templates, and so on). See [`Prototype::exclude`] for details.
+    fn excluded_components(&self) -> &[String] {
+        &[]
+    }
+
+    /// The params declared by this prototype, along with their default values (if any).
+    ///
+    /// See [`ProtoParams`] for details.
+    fn params(&self) -> Option<&ProtoParams> {
+        None
+    }
+
+    /// The child entities spawned along with this prototype.
+    ///
+    /// See [`ProtoChild`] for details.
+    fn children(&self) -> &[ProtoChild] {
+        &[]
+    }
+
+    /// Mutable access to the children, if they can be modified.
+    ///
+    /// This is used to resolve relative references to other prototypes when loading with
+    /// [`ProtoDataOptions::namespacing`](crate::data::ProtoDataOptions::namespacing) enabled.
+    fn children_mut(&mut self) -> Option<&mut Vec<ProtoChild>> {
+        None
+    }
+
+    /// The local name of the entity spawned for this prototype, if it has one.
+    ///
+    /// Components spawned along with it (by the same prototype or any prototype in its hierarchy)
+    /// can reference it with a [`ProtoEntityRef`](crate::ProtoEntityRef).
+    fn local(&self) -> Option<&str> {
+        None
+    }
+
+    /// Mutable access to the components, if they can be modified.
+    ///
+    /// This is used to apply patches. Prototypes that don't provide it can't be patched
+    /// (or be used as patches).
+    fn components_mut(&mut self) -> Option<&mut Vec<Box<dyn ProtoComponent>>> {
+        None
+    }
+
     /// The names of the parent templates (if any) in reverse order.
     fn templates_rev(&self) -> Rev<Iter<'_, String>> {
         self.templates().iter().rev()
@@ -79,8 +172,8 @@ pub trait Prototypical: 'static + Send + Sync {
     /// }
     ///
     /// ```
-    fn spawn<'w, 's, 'a, 'p>(
-        &'p self,
+    fn spawn<'w, 's, 'a>(
+        &self,
         commands: &'a mut Commands<'w, 's>,
         data: &Res<ProtoData>,
         asset_server: &Res<AssetServer>,
@@ -128,89 +221,522 @@ pub trait Prototypical: 'static + Send + Sync {
     /// }
     ///
     /// ```
-    fn insert<'w, 's, 'a, 'p>(
-        &'p self,
+    fn insert<'w, 's, 'a>(
+        &self,
         entity: EntityCommands<'w, 's, 'a>,
         data: &Res<ProtoData>,
         asset_server: &Res<AssetServer>,
     ) -> EntityCommands<'w, 's, 'a> {
-        let mut proto_commands = self.create_commands(entity, data);
+        // Components referencing params (or random values) are resolved with the defaults
+        let resolved = params::resolve_defaults(self, data);
 
-        spawn_internal(
-            self.name(),
-            self.templates().iter().rev(),
-            self.iter_components(),
-            &mut proto_commands,
+        children::insert_hierarchy(self, entity, data, asset_server, &resolved, &mut Vec::new()).0
+    }
+
+    /// Spawns an entity with this prototype's component structure, returning its spawned
+    /// [`children`](Self::children) along with it.
+    ///
+    /// This is the same as [`spawn`](Self::spawn), which also spawns the children.
+    ///
+    /// # Arguments
+    ///
+    /// * `commands`: The world `Commands`
+    /// * `data`: The prototype data in this world
+    /// * `asset_server`: The asset server
+    ///
+    /// returns: (EntityCommands, ProtoChildren)
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bevy::prelude::*;
+    /// use bevy_proto::prelude::*;
+    ///
+    /// fn spawn_knight(mut commands: Commands, data: Res<ProtoData>, asset_server: Res<AssetServer>) {
+    ///     let proto = data.get_prototype("Knight").expect("Prototype doesn't exist!");
+    ///
+    ///     let (knight, children) = proto.spawn_hierarchy(&mut commands, &data, &asset_server);
+    ///     info!("Spawned {:?} with children {:?}", knight.id(), children.entities().collect::<Vec<_>>());
+    /// }
+    /// ```
+    fn spawn_hierarchy<'w, 's, 'a>(
+        &self,
+        commands: &'a mut Commands<'w, 's>,
+        data: &Res<ProtoData>,
+        asset_server: &Res<AssetServer>,
+    ) -> (EntityCommands<'w, 's, 'a>, ProtoChildren) {
+        let resolved = params::resolve_defaults(self, data);
+        let entity = commands.spawn_empty();
+
+        children::insert_hierarchy(self, entity, data, asset_server, &resolved, &mut Vec::new())
+    }
+
+    /// Spawns an entity with this prototype's component structure, substituting the given params
+    /// into any component referencing them.
+    ///
+    /// Params that aren't given fall back to the defaults declared by this prototype (or its
+    /// templates). Nothing is spawned if a referenced param is missing or can't be used where
+    /// it's referenced.
+    ///
+    /// # Arguments
+    ///
+    /// * `commands`: The world `Commands`
+    /// * `data`: The prototype data in this world
+    /// * `asset_server`: The asset server
+    /// * `params`: The params to substitute
+    ///
+    /// returns: Result<EntityCommands, ProtoParamError>
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bevy::prelude::*;
+    /// use bevy_proto::prelude::*;
+    ///
+    /// fn spawn_boss(mut commands: Commands, data: Res<ProtoData>, asset_server: Res<AssetServer>) {
+    ///     let proto = data.get_prototype("Goblin").expect("Prototype doesn't exist!");
+    ///
+    ///     let params = ProtoParams::new().with("level", 10).with("name", "Grok");
+    ///     match proto.spawn_with(&mut commands, &data, &asset_server, &params) {
+    ///         Ok(entity) => info!("Spawned {:?}", entity.id()),
+    ///         Err(err) => error!("{}", err),
+    ///     }
+    /// }
+    /// ```
+    fn spawn_with<'w, 's, 'a>(
+        &self,
+        commands: &'a mut Commands<'w, 's>,
+        data: &Res<ProtoData>,
+        asset_server: &Res<AssetServer>,
+        params: &ProtoParams,
+    ) -> Result<EntityCommands<'w, 's, 'a>, ProtoParamError> {
+        // Resolve first so nothing is spawned on failure
+        let resolved = params::resolve_all(self, data, params)?;
+
+        let entity = commands.spawn_empty();
+        let (entity, _) = children::insert_hierarchy(
+            self,
+            entity,
+            data,
+            asset_server,
+            &resolved,
+            &mut Vec::new(),
+        );
+
+        Ok(entity)
+    }
+
+    /// Inserts this prototype's component structure to the given entity, substituting the given
+    /// params into any component referencing them.
+    ///
+    /// __Note:__ This _will_ override existing components of the same type. Nothing is inserted
+    /// if the params can't be substituted (see [`spawn_with`](Self::spawn_with)).
+    ///
+    /// # Arguments
+    ///
+    /// * `entity`: The `EntityCommands` for a given entity
+    /// * `data`: The prototype data in this world
+    /// * `asset_server`: The asset server
+    /// * `params`: The params to substitute
+    ///
+    /// returns: Result<EntityCommands, ProtoParamError>
+    fn insert_with<'w, 's, 'a>(
+        &self,
+        entity: EntityCommands<'w, 's, 'a>,
+        data: &Res<ProtoData>,
+        asset_server: &Res<AssetServer>,
+        params: &ProtoParams,
+    ) -> Result<EntityCommands<'w, 's, 'a>, ProtoParamError> {
+        let resolved = params::resolve_all(self, data, params)?;
+
+        let (entity, _) = children::insert_hierarchy(
+            self,
+            entity,
             data,
             asset_server,
-            &mut IndexSet::default(),
+            &resolved,
+            &mut Vec::new(),
         );
 
-        proto_commands.into()
+        Ok(entity)
+    }
+}
+
+/// Allows shared prototypes (such as those held by a [`PrototypeAsset`](crate::PrototypeAsset))
+/// to be used wherever a [`Prototypical`] is expected.
+impl<T: Prototypical + ?Sized> Prototypical for Arc<T> {
+    fn name(&self) -> &str {
+        (**self).name()
+    }
+
+    fn templates(&self) -> &[String] {
+        (**self).templates()
+    }
+
+    fn patch(&self) -> Option<&str> {
+        (**self).patch()
+    }
+
+    fn removed_components(&self) -> &[String] {
+        (**self).removed_components()
+    }
+
+    fn excluded_components(&self) -> &[String] {
+        (**self).excluded_components()
+    }
+
+    fn params(&self) -> Option<&ProtoParams> {
+        (**self).params()
+    }
+
+    fn children(&self) -> &[ProtoChild] {
+        (**self).children()
+    }
+
+    fn local(&self) -> Option<&str> {
+        (**self).local()
+    }
+
+    fn components_mut(&mut self) -> Option<&mut Vec<Box<dyn ProtoComponent>>> {
+        Arc::get_mut(self)?.components_mut()
+    }
+
+    fn templates_rev(&self) -> Rev<Iter<'_, String>> {
+        (**self).templates_rev()
+    }
+
+    fn iter_components(&self) -> Iter<'_, Box<dyn ProtoComponent>> {
+        (**self).iter_components()
+    }
+
+    fn create_commands<'w, 's, 'a, 'p>(
+        &'p self,
+        entity: EntityCommands<'w, 's, 'a>,
+        data: &'p Res<ProtoData>,
+    ) -> ProtoCommands<'w, 's, 'a, 'p> {
+        (**self).create_commands(entity, data)
+    }
+
+    fn spawn<'w, 's, 'a>(
+        &self,
+        commands: &'a mut Commands<'w, 's>,
+        data: &Res<ProtoData>,
+        asset_server: &Res<AssetServer>,
+    ) -> EntityCommands<'w, 's, 'a> {
+        (**self).spawn(commands, data, asset_server)
+    }
+
+    fn insert<'w, 's, 'a>(
+        &self,
+        entity: EntityCommands<'w, 's, 'a>,
+        data: &Res<ProtoData>,
+        asset_server: &Res<AssetServer>,
+    ) -> EntityCommands<'w, 's, 'a> {
+        (**self).insert(entity, data, asset_server)
     }
 }
 
 /// Internal method used for recursing up the template hierarchy and spawning components
 /// from the top to the bottom
-fn spawn_internal<'a>(
-    name: &'a str,
-    templates: Rev<Iter<'a, String>>,
-    components: Iter<'a, Box<dyn ProtoComponent>>,
+///
+/// Components whose `type` name is in `excluded` (i.e. those excluded by any prototype inheriting
+/// from this one) are skipped.
+pub(crate) fn spawn_internal<'a, P: Prototypical + ?Sized>(
+    proto: &'a P,
     proto_commands: &mut ProtoCommands,
     data: &'a Res<ProtoData>,
     asset_server: &Res<AssetServer>,
     traversed: &mut IndexSet<&'a str>,
+    excluded: &[&'a str],
 ) {
     // We insert first on the off chance that someone made a prototype its own template...
-    traversed.insert(name);
+    traversed.insert(proto.name());
 
-    for template in templates {
+    // Templates also skip the components excluded by this prototype
+    let template_excluded = excluded
+        .iter()
+        .copied()
+        .chain(proto.excluded_components().iter().map(String::as_str))
+        .collect::<Vec<_>>();
+
+    for template in proto.templates_rev() {
         if traversed.contains(template.as_str()) {
             // ! === Found Circular Dependency === ! //
             handle_cycle!(
                 template,
                 traversed,
+                data,
                 "For now, the rest of the spawn has been skipped."
             );
 
+            #[cfg(not(feature = "no_cycles"))]
             continue;
         }
 
         // === Spawn Template === //
         if let Some(parent) = data.get_prototype(template) {
             spawn_internal(
-                parent.name(),
-                parent.templates_rev(),
-                parent.iter_components(),
+                parent,
                 proto_commands,
                 data,
                 asset_server,
                 traversed,
+                &template_excluded,
             );
         }
     }
 
     // === Spawn Self === //
-    for component in components {
+    for component in proto.iter_components() {
+        if !excluded.is_empty() && data.is_excluded(component.as_ref(), excluded) {
+            continue;
+        }
         component.insert_self(proto_commands, asset_server);
     }
 }
 
 /// The default prototype object, providing the basics for the prototype system.
-#[derive(Serialize, Deserialize)]
+#[derive(Default, Serialize)]
 pub struct Prototype {
     /// The name of this prototype.
+    ///
+    /// When loaded from a file, this defaults to the file's stem (such as `goblin` for `goblin.yaml`).
     pub name: String,
+    /// The local name of the entity spawned for this prototype (if any).
+    ///
+    /// Other entities in the same hierarchy can reference it by this name with a
+    /// [`ProtoEntityRef`](crate::ProtoEntityRef), written as `"@name"`. This is usually given to
+    /// [`children`](Self::children), using an inline prototype to name a child spawned from
+    /// another prototype:
+    ///
+    /// ```yaml
+    /// name: Turret
+    /// components:
+    ///   - type: Aim
+    ///     value:
+    ///       target: "@barrel"
+    /// children:
+    ///   - local: barrel
+    ///     templates: Barrel
+    /// ```
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub local: Option<String>,
     /// The names of this prototype's templates (if any).
     ///
     /// See [`deserialize_templates_list`] for how these names are deserialized.
-    #[serde(default)]
-    #[serde(alias = "template")]
-    #[serde(deserialize_with = "deserialize_templates_list")]
     pub templates: Vec<String>,
+    /// The params this prototype's components can reference, along with their default values.
+    ///
+    /// See [`ProtoParams`] for how they're referenced, and for random values.
+    ///
+    /// Since component values are read as YAML values to check them for references, formats that
+    /// don't map onto YAML values (such as RON's enum variants) may not deserialize in a prototype.
+    #[serde(skip_serializing_if = "ProtoParams::is_empty")]
+    pub params: ProtoParams,
     /// The components belonging to this prototype.
-    #[serde(default)]
+    ///
+    /// See [`ProtoComponentRegistry`](crate::registry::ProtoComponentRegistry) for how these are deserialized.
+    ///
+    /// A component may give a `merge` instead of a `value`, in which case its fields are merged into
+    /// the value it inherits from this prototype's templates: maps are merged key by key and lists
+    /// are appended, unless the value is wrapped as `{ $replace: ... }`. The component is then
+    /// deserialized from the merged value once every prototype has been loaded.
+    ///
+    /// ```yaml
+    /// name: Veteran
+    /// templates: NPC
+    /// components:
+    ///   - type: Movement
+    ///     merge:
+    ///       speed: 15
+    /// ```
+    #[serde(serialize_with = "serialize_components")]
     pub components: Vec<Box<dyn ProtoComponent>>,
+    /// The child entities spawned along with this prototype, each given either as the name of
+    /// another prototype or as an inline prototype.
+    ///
+    /// Children are attached with Bevy's `Parent` and `Children` components, and the children of
+    /// this prototype's templates are spawned as well (before its own). See [`ProtoChild`] for
+    /// details.
+    ///
+    /// ```yaml
+    /// name: Knight
+    /// templates: Character
+    /// children:
+    ///   - Nameplate
+    ///   - name: Hitbox
+    ///     components:
+    ///       - type: Collider
+    ///         value: [1.0, 2.0]
+    /// ```
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<ProtoChild>,
+    /// The name of the prototype this patches (if any).
+    ///
+    /// A patch isn't added as a prototype of its own. Instead, each of its components is added to
+    /// the target prototype, replacing the target's component of the same type if it has one.
+    /// Components listed in [`remove`](Self::remove) are then removed from the target.
+    ///
+    /// Patches are applied once every prototype from the same
+    /// [layer](crate::data::ProtoDataOptions::layered) has been loaded, so they can patch prototypes
+    /// from their own layer or any earlier one.
+    ///
+    /// ```yaml
+    /// # mods/tougher_goblins/goblin.yaml
+    /// patch: Goblin
+    /// components:
+    ///   - type: Health
+    ///     value: 50
+    /// remove: [ Cowardly ]
+    /// ```
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub patch: Option<String>,
+    /// The `type` names of the components to remove from the prototype being patched.
+    ///
+    /// This is only used by patches (see [`patch`](Self::patch)).
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub remove: Vec<String>,
+    /// The `type` names of the components to exclude from this prototype's templates.
+    ///
+    /// Components of these types aren't inherited from any template, no matter how far up the
+    /// template hierarchy they're defined. This prototype's own components are always inserted.
+    /// Like [`templates`](Self::templates), this can be given as a list or a comma-separated string.
+    ///
+    /// ```yaml
+    /// name: Zombie
+    /// templates: NPC
+    /// exclude: [ Healthy ]
+    /// ```
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub exclude: Vec<String>,
+}
+
+/// Deserializes a prototype whose components are registered with `typetag`.
+///
+/// Components registered in a [`ProtoComponentRegistry`] (or inserted through reflection) are only
+/// known to a [`PrototypeSeed`] given that registry.
+#[cfg(feature = "typetag")]
+impl<'de> Deserialize<'de> for Prototype {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        PrototypeSeed::new(&ProtoComponentRegistry::default()).deserialize(deserializer)
+    }
+}
+
+/// Serializes the components of a [`Prototype`], which are named by `typetag` alone
+fn serialize_components<S: Serializer>(
+    components: &[Box<dyn ProtoComponent>],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(
+        components
+            .iter()
+            .map(|component| ComponentRef::unregistered(component.as_ref())),
+    )
+}
+
+const PROTOTYPE_FIELDS: &[&str] = &[
+    "name",
+    "local",
+    "templates",
+    "params",
+    "components",
+    "children",
+    "patch",
+    "remove",
+    "exclude",
+];
+
+/// Deserializes a [`Prototype`], using the given [`ProtoComponentRegistry`] for its components
+/// (and those of its inline children).
+///
+/// Prototypes loaded through [`ProtoData`] already use the registry of their
+/// [`ProtoDataOptions`](crate::data::ProtoDataOptions), so this is only needed when deserializing
+/// prototypes directly, such as in a custom [`ProtoDeserializer`](crate::data::ProtoDeserializer).
+/// See [`ProtoComponentRegistry`] for an example.
+#[derive(Copy, Clone)]
+pub struct PrototypeSeed<'a> {
+    registry: &'a ProtoComponentRegistry,
+}
+
+impl<'a> PrototypeSeed<'a> {
+    /// Creates a seed deserializing components with the given registry
+    pub fn new(registry: &'a ProtoComponentRegistry) -> Self {
+        Self { registry }
+    }
+}
+
+impl<'a, 'de> DeserializeSeed<'de> for PrototypeSeed<'a> {
+    type Value = Prototype;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_struct("Prototype", PROTOTYPE_FIELDS, self)
+    }
+}
+
+impl<'a, 'de> Visitor<'de> for PrototypeSeed<'a> {
+    type Value = Prototype;
+
+    fn expecting(&self, formatter: &mut Formatter) -> std::fmt::Result {
+        formatter.write_str("a prototype")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut proto = Prototype::default();
+        let mut seen = Vec::new();
+
+        while let Some(key) = map.next_key::<String>()? {
+            let field = match key.as_str() {
+                "template" => "templates",
+                key => match PROTOTYPE_FIELDS.iter().find(|field| **field == key) {
+                    Some(field) => field,
+                    None => {
+                        map.next_value::<de::IgnoredAny>()?;
+                        continue;
+                    }
+                },
+            };
+            if seen.contains(&field) {
+                return Err(de::Error::duplicate_field(field));
+            }
+            seen.push(field);
+
+            match field {
+                "name" => proto.name = map.next_value()?,
+                "local" => proto.local = map.next_value()?,
+                "templates" => proto.templates = map.next_value::<TemplateNames>()?.0,
+                "params" => proto.params = map.next_value()?,
+                "components" => {
+                    let seed = ComponentsSeed::in_prototype(self.registry);
+                    proto.components = map.next_value_seed(seed)?;
+                }
+                "children" => {
+                    proto.children = map.next_value_seed(ChildrenSeed::new(self.registry))?
+                }
+                "patch" => proto.patch = map.next_value()?,
+                "remove" => proto.remove = map.next_value()?,
+                _ => proto.exclude = map.next_value::<TemplateNames>()?.0,
+            }
+        }
+
+        // Components may only reference params if the prototype declares them
+        let declared = seen.contains(&"params");
+        params::finish_components(&mut proto, declared, self.registry)
+            .map_err(de::Error::custom)?;
+        // Reflected components may be named either way, but are known by their full type path
+        for name in proto.remove.iter_mut().chain(proto.exclude.iter_mut()) {
+            *name = self.registry.canonical_name(name);
+        }
+        Ok(proto)
+    }
+}
+
+/// A list of names read with [`deserialize_templates_list`]
+struct TemplateNames(Vec<String>);
+
+impl<'de> Deserialize<'de> for TemplateNames {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_templates_list(deserializer).map(TemplateNames)
+    }
 }
 
 impl Prototypical for Prototype {
@@ -222,6 +748,50 @@ impl Prototypical for Prototype {
         &self.templates
     }
 
+    fn rename(&mut self, name: String) {
+        self.name = name;
+    }
+
+    fn templates_mut(&mut self) -> Option<&mut Vec<String>> {
+        Some(&mut self.templates)
+    }
+
+    fn patch(&self) -> Option<&str> {
+        self.patch.as_deref()
+    }
+
+    fn patch_mut(&mut self) -> Option<&mut String> {
+        self.patch.as_mut()
+    }
+
+    fn removed_components(&self) -> &[String] {
+        &self.remove
+    }
+
+    fn excluded_components(&self) -> &[String] {
+        &self.exclude
+    }
+
+    fn params(&self) -> Option<&ProtoParams> {
+        Some(&self.params)
+    }
+
+    fn children(&self) -> &[ProtoChild] {
+        &self.children
+    }
+
+    fn children_mut(&mut self) -> Option<&mut Vec<ProtoChild>> {
+        Some(&mut self.children)
+    }
+
+    fn local(&self) -> Option<&str> {
+        self.local.as_deref()
+    }
+
+    fn components_mut(&mut self) -> Option<&mut Vec<Box<dyn ProtoComponent>>> {
+        Some(&mut self.components)
+    }
+
     fn iter_components(&self) -> Iter<'_, Box<dyn ProtoComponent>> {
         self.components.iter()
     }
@@ -254,6 +824,16 @@ impl Prototypical for Prototype {
 ///   > ```yaml
 ///   > templates: A, B, C # OR: "A, B, C"
 ///   > ```
+///
+/// When loading with [`ProtoDataOptions::namespacing`](crate::data::ProtoDataOptions::namespacing)
+/// enabled, each entry may also be a path relative to the prototype's own file:
+///
+///   > ```yaml
+///   > templates: ./npc, ../creatures/goblin, items/Sword
+///   > ```
+///
+/// Entries starting with `./` or `../` are resolved once the prototype's file is known (see
+/// [`resolve_template`]). Any other entry is treated as a fully qualified ID.
 pub fn deserialize_templates_list<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
 where
     D: Deserializer<'de>,
@@ -286,3 +866,48 @@ where
 
     deserializer.deserialize_any(TemplatesList)
 }
+
+/// Resolves a template reference made by a prototype loaded from the given file.
+///
+/// References starting with `./` or `../` are relative to the directory containing the file,
+/// while all other references are left as-is (apart from a leading `/`, which is removed).
+///
+/// # Arguments
+///
+/// * `template`: The template reference
+/// * `directory`: The ID of the directory containing the referencing file (such as `templates`)
+///
+/// returns: String
+///
+/// # Examples
+///
+/// ```
+/// use bevy_proto::resolve_template;
+///
+/// assert_eq!("templates/npc", resolve_template("./npc", "templates"));
+/// assert_eq!("items/Sword", resolve_template("../items/Sword", "templates"));
+/// assert_eq!("items/Sword", resolve_template("items/Sword", "templates"));
+/// assert_eq!("items/Sword", resolve_template("/items/Sword", "templates"));
+/// ```
+pub fn resolve_template(template: &str, directory: &str) -> String {
+    if !template.starts_with("./") && !template.starts_with("../") {
+        return template.trim_start_matches('/').to_string();
+    }
+
+    let mut segments = directory
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>();
+
+    for segment in template.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+    }
+
+    segments.join("/")
+}
@@ -0,0 +1,113 @@
+//! Contains [`ProtoRng`], the source of randomness for random values in prototypes.
+use std::sync::{Arc, Mutex};
+
+use bevy::prelude::Resource;
+use serde_yaml::{Mapping, Value};
+
+/// The key of a random integer or float between two bounds: `{ $range: [8, 12] }`
+pub(crate) const RANGE: &str = "$range";
+/// The key of a uniformly random choice from a list: `{ $choose: [Grok, Grak] }`
+pub(crate) const CHOOSE: &str = "$choose";
+/// The key of a weighted random choice: `{ $weighted: [{ weight: 3, value: Grok }] }`
+pub(crate) const WEIGHTED: &str = "$weighted";
+
+/// The random number generator used to evaluate random values (such as `{ $range: [8, 12] }`)
+/// when spawning prototypes.
+///
+/// The generator is stored as a resource, which [`ProtoData`](crate::data::ProtoData) shares.
+/// It's cheap to clone, with each clone sharing the same state. To make spawning deterministic
+/// (such as for replays or tests), either insert a seeded generator before adding the
+/// [`ProtoPlugin`](crate::ProtoPlugin), or reseed the existing one at any time.
+///
+/// # Examples
+///
+/// ```
+/// use bevy::prelude::*;
+/// use bevy_proto::prelude::*;
+///
+/// let mut app = App::new();
+/// app.insert_resource(ProtoRng::with_seed(42));
+///
+/// // Or later on:
+/// fn restart_replay(rng: Res<ProtoRng>) {
+///     rng.seed(42);
+/// }
+/// ```
+#[derive(Resource, Clone)]
+pub struct ProtoRng {
+    inner: Arc<Mutex<fastrand::Rng>>,
+}
+
+impl ProtoRng {
+    /// Creates a generator with a random seed
+    pub fn new() -> Self {
+        Self::from_rng(fastrand::Rng::new())
+    }
+
+    /// Creates a generator with the given seed
+    ///
+    /// Generators created with the same seed produce the same values.
+    pub fn with_seed(seed: u64) -> Self {
+        Self::from_rng(fastrand::Rng::with_seed(seed))
+    }
+
+    /// Reseeds the generator (affecting every clone)
+    pub fn seed(&self, seed: u64) {
+        self.inner.lock().unwrap().seed(seed);
+    }
+
+    /// Runs the given function with exclusive access to the underlying generator
+    pub(crate) fn with<R>(&self, func: impl FnOnce(&mut fastrand::Rng) -> R) -> R {
+        func(&mut self.inner.lock().unwrap())
+    }
+
+    fn from_rng(rng: fastrand::Rng) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(rng)),
+        }
+    }
+}
+
+impl Default for ProtoRng {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returns the key and argument of the given map if it's a random value (a map with a single
+/// key of `$range`, `$choose`, or `$weighted`)
+pub(crate) fn random_node(map: &Mapping) -> Option<(&'static str, &Value)> {
+    if map.len() != 1 {
+        return None;
+    }
+
+    [RANGE, CHOOSE, WEIGHTED]
+        .into_iter()
+        .find_map(|key| Some((key, map.get(key)?)))
+}
+
+/// Picks an index at random, with each index weighted by its entry in `weights`
+///
+/// returns: Option<usize> which is `None` if there are no positive weights
+pub(crate) fn pick_weighted(rng: &mut fastrand::Rng, weights: &[f64]) -> Option<usize> {
+    let total = weights.iter().filter(|weight| **weight > 0.0).sum::<f64>();
+    if total <= 0.0 || !total.is_finite() {
+        return None;
+    }
+
+    let mut roll = rng.f64() * total;
+    let mut last = None;
+    for (index, weight) in weights.iter().enumerate() {
+        if *weight <= 0.0 {
+            continue;
+        }
+        if roll < *weight {
+            return Some(index);
+        }
+        roll -= weight;
+        last = Some(index);
+    }
+
+    // Only reached due to rounding errors
+    last
+}
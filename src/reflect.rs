@@ -0,0 +1,179 @@
+//! Contains [`Reflected`], which allows any reflected Bevy component to be used in a prototype.
+use std::any::Any;
+
+use bevy::ecs::reflect::ReflectComponent;
+use bevy::ecs::world::World;
+use bevy::prelude::{AssetServer, Res};
+use bevy::reflect::serde::TypedReflectDeserializer;
+use bevy::reflect::std_traits::ReflectDefault;
+use bevy::reflect::{Reflect, TypeRegistration, TypeRegistryInternal as TypeRegistry};
+use serde::de::DeserializeSeed;
+use serde::ser::{self, SerializeStruct};
+use serde::{Serialize, Serializer};
+
+use crate::components::ProtoComponent;
+use crate::data::ProtoCommands;
+
+/// A component inserted using reflection rather than a dedicated [`ProtoComponent`] type.
+///
+/// This allows any component registered in the app's `TypeRegistry` (with `#[reflect(Component)]`)
+/// to be used in a prototype, including Bevy's own components such as `Transform` or `Visibility`.
+/// Any component `type` that isn't a registered [`ProtoComponent`] is looked up in the type
+/// registry, either by its full type path or (if it's unambiguous) by the type's name:
+///
+/// ```yaml
+/// name: "Hidden Marker"
+/// components:
+///   - type: Transform
+///     value:
+///       translation: { x: 0.0, y: 1.0, z: 0.0 }
+///   - type: bevy_render::view::visibility::Visibility
+///     value: Hidden
+/// ```
+///
+/// Any field that isn't given uses its default value, so long as the type also registers
+/// `#[reflect(Default)]`.
+///
+/// Each reflected component is known by its full type path, which is also what
+/// [`ProtoData::component_sources`](crate::data::ProtoData::component_sources) reports. Prototypes
+/// may [`exclude`](crate::Prototype::exclude) or patches may
+/// [`remove`](crate::Prototype::remove) them by either name.
+///
+/// The value is deserialized as soon as the prototype is, so a type that isn't registered (or a
+/// value that doesn't match it) is reported as a load error. This uses the type registry set with
+/// [`ProtoComponentRegistry::set_type_registry`](crate::ProtoComponentRegistry::set_type_registry),
+/// which the [`ProtoPlugin`](crate::ProtoPlugin) sets to the app's `AppTypeRegistry`.
+pub struct Reflected {
+    /// The full type path of the component
+    type_path: String,
+    /// The value as it was written, used to serialize the component again
+    value: serde_yaml::Value,
+    reflect_component: ReflectComponent,
+    /// The deserialized value (with any missing fields filled in)
+    reflected: Box<dyn Reflect>,
+}
+
+impl Reflected {
+    /// Deserializes the given value as the component with the given registration
+    pub(crate) fn new(
+        registration: &TypeRegistration,
+        value: serde_yaml::Value,
+        registry: &TypeRegistry,
+    ) -> Result<Self, String> {
+        let type_path = registration.type_name();
+        let reflect_component = registration
+            .data::<ReflectComponent>()
+            .ok_or_else(|| format!("type `{}` does not reflect `Component`", type_path))?
+            .clone();
+
+        let reflected = TypedReflectDeserializer::new(registration, registry)
+            .deserialize(&value)
+            .map_err(|err| format!("invalid value for `{}`: {}", type_path, err))?;
+
+        // Fill in any missing fields with their defaults
+        let reflected = match registration.data::<ReflectDefault>() {
+            Some(reflect_default) => {
+                let mut full = reflect_default.default();
+                full.apply(reflected.as_ref());
+                full
+            }
+            None => reflected,
+        };
+
+        Ok(Self {
+            type_path: type_path.to_string(),
+            value,
+            reflect_component,
+            reflected,
+        })
+    }
+
+    /// The full type path of the component
+    pub fn type_path(&self) -> &str {
+        &self.type_path
+    }
+
+    /// The deserialized value of the component
+    pub fn value(&self) -> &dyn Reflect {
+        self.reflected.as_ref()
+    }
+
+    /// The value as it was written
+    #[cfg(feature = "pack")]
+    pub(crate) fn written_value(&self) -> &serde_yaml::Value {
+        &self.value
+    }
+}
+
+impl ProtoComponent for Reflected {
+    fn insert_self(&self, commands: &mut ProtoCommands, _asset_server: &Res<AssetServer>) {
+        let entity = commands.raw_commands().id();
+        let reflect_component = self.reflect_component.clone();
+        let reflected = self.reflected.clone_value();
+        commands
+            .raw_commands()
+            .commands()
+            .add(move |world: &mut World| {
+                if let Some(mut entity) = world.get_entity_mut(entity) {
+                    reflect_component.insert(&mut entity, reflected.as_ref());
+                }
+            });
+    }
+
+    fn remove_self(&self, commands: &mut ProtoCommands) {
+        let entity = commands.raw_commands().id();
+        let reflect_component = self.reflect_component.clone();
+        commands
+            .raw_commands()
+            .commands()
+            .add(move |world: &mut World| {
+                if let Some(mut entity) = world.get_entity_mut(entity) {
+                    reflect_component.remove(&mut entity);
+                }
+            });
+    }
+
+    // Reflected components are never deserialized by `typetag` (see `registry::ComponentSeed`)
+    #[cfg(feature = "typetag")]
+    #[doc(hidden)]
+    fn typetag_name(&self) -> &'static str {
+        "Reflected"
+    }
+
+    #[cfg(feature = "typetag")]
+    #[doc(hidden)]
+    fn typetag_deserialize(&self) {}
+}
+
+impl Serialize for Reflected {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        // The value can only be read back by a self-describing format
+        if !serializer.is_human_readable() {
+            return Err(ser::Error::custom(format_args!(
+                "reflected component `{}` can't be stored in this format",
+                self.type_path
+            )));
+        }
+
+        let mut state = serializer.serialize_struct("ProtoComponent", 2)?;
+        state.serialize_field("type", &self.type_path)?;
+        state.serialize_field("value", &self.value)?;
+        state.end()
+    }
+}
+
+/// Returns the given component as a [`Reflected`] component, if it is one
+pub(crate) fn as_reflected(component: &dyn ProtoComponent) -> Option<&Reflected> {
+    let component: &dyn Any = component;
+    component.downcast_ref::<Reflected>()
+}
+
+/// Finds the registration of the type with the given full type path or (if unambiguous) name
+pub(crate) fn find_type<'a>(
+    name: &str,
+    registry: &'a TypeRegistry,
+) -> Option<&'a TypeRegistration> {
+    registry
+        .get_with_name(name)
+        .or_else(|| registry.get_with_short_name(name))
+}
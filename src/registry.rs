@@ -0,0 +1,788 @@
+//! Contains [`ProtoComponentRegistry`], which registers [`ProtoComponent`] types per app.
+use std::any::{Any, TypeId};
+use std::fmt::Formatter;
+use std::sync::{Arc, RwLock};
+
+use bevy::app::{App, AppTypeRegistry};
+use bevy::prelude::Resource;
+use bevy::utils::HashMap;
+use serde::de::value::UnitDeserializer;
+use serde::de::{self, DeserializeOwned, DeserializeSeed, MapAccess, SeqAccess, Visitor};
+use serde::ser::{self, SerializeStruct};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+#[cfg(feature = "typetag")]
+use serde::de::{
+    value::{StrDeserializer, StringDeserializer},
+    IntoDeserializer,
+};
+
+use crate::components::ProtoComponent;
+use crate::merge::{as_merged, MergedComponent};
+use crate::params::{self, as_parameterized, ParameterizedComponent};
+use crate::reflect::{self, as_reflected, Reflected};
+
+/// Deserializes a boxed component of a registered type
+type DeserializeFn = for<'de> fn(
+    &mut dyn erased_serde::Deserializer<'de>,
+) -> Result<Box<dyn ProtoComponent>, erased_serde::Error>;
+
+/// Gets a serializable reference to a component of a registered type
+type SerializeFn = fn(&dyn ProtoComponent) -> Option<&dyn erased_serde::Serialize>;
+
+struct ComponentRegistration {
+    deserialize: DeserializeFn,
+    serialize: SerializeFn,
+}
+
+#[derive(Default)]
+struct RegistryInner {
+    /// Maps Component Name -> Registration
+    registrations: HashMap<String, ComponentRegistration>,
+    /// Maps Component Type -> Component Name
+    names: HashMap<TypeId, String>,
+    /// The type registry used to find reflected components
+    type_registry: Option<AppTypeRegistry>,
+}
+
+impl RegistryInner {
+    /// The name components with the given `type` are known by: the full type path of a reflected
+    /// component, or the given name otherwise
+    fn canonical_name(&self, name: &str) -> String {
+        if self.registrations.contains_key(name) || is_typetag_name(name) {
+            return name.to_string();
+        }
+
+        self.type_registry
+            .as_ref()
+            .and_then(|types| {
+                let types = types.read();
+                reflect::find_type(name, &types)
+                    .map(|registration| registration.type_name().to_string())
+            })
+            .unwrap_or_else(|| name.to_string())
+    }
+}
+
+/// A registry of the [`ProtoComponent`] types that can be used in prototypes, and the `type`
+/// names they're given.
+///
+/// By default, components are registered globally through `typetag` (the `typetag` feature).
+/// This registry is an alternative which is scoped to a single app: components are registered with
+/// [`RegisterProtoComponent::register_proto_component`] and are only available to the prototypes
+/// loaded by that app. When the `typetag` feature is disabled, this is the only way to register
+/// components. Otherwise, the registry is checked first, before falling back to `typetag`.
+///
+/// The registry is stored both as a resource and in
+/// [`ProtoDataOptions::registry`](crate::data::ProtoDataOptions::registry). It's cheap
+/// to clone, with each clone sharing the same registrations.
+///
+/// Prototypes loaded through [`ProtoData`](crate::data::ProtoData) use the registry of their
+/// options. To deserialize prototypes (or components) directly, pass the registry along with a
+/// [`PrototypeSeed`](crate::PrototypeSeed) (or a [`ComponentSeed`]).
+///
+/// # Examples
+///
+/// Registries can also be used on their own, such as to test against a specific set of components:
+///
+/// ```
+/// use bevy::prelude::*;
+/// use bevy_proto::prelude::*;
+/// use bevy_proto::ProtoComponentRegistry;
+/// use serde::de::DeserializeSeed;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Clone, Serialize, Deserialize, ProtoComponent, Component)]
+/// struct Health(u16);
+///
+/// let registry = ProtoComponentRegistry::default();
+/// registry.register::<Health>("Hp");
+///
+/// let deserializer = serde_yaml::Deserializer::from_str("
+/// name: Goblin
+/// components:
+///   - type: Hp
+///     value: 10
+/// ");
+/// let prototype = PrototypeSeed::new(&registry).deserialize(deserializer);
+/// assert!(prototype.is_ok());
+/// ```
+#[derive(Resource, Clone, Default)]
+pub struct ProtoComponentRegistry {
+    internal: Arc<RwLock<RegistryInner>>,
+}
+
+impl ProtoComponentRegistry {
+    /// Registers a [`ProtoComponent`] using the given `type` name
+    ///
+    /// Registering another component with the same name replaces the existing one.
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: The name used as the component's `type` in prototype files
+    pub fn register<T>(&self, name: impl Into<String>)
+    where
+        T: ProtoComponent + Serialize + DeserializeOwned,
+    {
+        let name = name.into();
+        let mut inner = self.internal.write().unwrap();
+        // Each type and each name may only be registered once
+        if let Some(previous) = inner.names.remove(&TypeId::of::<T>()) {
+            inner.registrations.remove(&previous);
+        }
+        inner.names.retain(|_, existing| *existing != name);
+        inner.names.insert(TypeId::of::<T>(), name.clone());
+        inner.registrations.insert(
+            name,
+            ComponentRegistration {
+                deserialize: deserialize_boxed::<T>,
+                serialize: serialize_ref::<T>,
+            },
+        );
+    }
+
+    /// Returns true if a component with the given `type` name is registered
+    pub fn contains(&self, name: &str) -> bool {
+        self.internal
+            .read()
+            .unwrap()
+            .registrations
+            .contains_key(name)
+    }
+
+    /// The `type` names of every registered component, sorted alphabetically
+    pub fn names(&self) -> Vec<String> {
+        let inner = self.internal.read().unwrap();
+        let mut names = inner.registrations.keys().cloned().collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    /// The `type` name of the given component
+    ///
+    /// This is the name it was registered with, or (with the `typetag` feature) the name it was
+    /// registered with by `typetag`. [`Reflected`] components are named by their full type path.
+    pub fn name_of(&self, component: &dyn ProtoComponent) -> Option<String> {
+        if let Some(reflected) = as_reflected(component) {
+            return Some(reflected.type_path().to_string());
+        }
+        if let Some(merged) = as_merged(component) {
+            return Some(merged.name().to_string());
+        }
+        if let Some(parameterized) = as_parameterized(component) {
+            return Some(parameterized.name().to_string());
+        }
+
+        let any: &dyn Any = component;
+        if let Some(name) = self.internal.read().unwrap().names.get(&any.type_id()) {
+            return Some(name.clone());
+        }
+
+        #[cfg(feature = "typetag")]
+        return Some(component.typetag_name().to_string());
+        #[cfg(not(feature = "typetag"))]
+        None
+    }
+
+    /// The name components with the given `type` are known by (see [`name_of`](Self::name_of))
+    ///
+    /// This is the full type path for a [`Reflected`] component given by the type's name, and the
+    /// given name for anything else.
+    pub fn canonical_name(&self, name: &str) -> String {
+        self.internal.read().unwrap().canonical_name(name)
+    }
+
+    /// Sets the type registry used to find [`Reflected`] components
+    ///
+    /// The [`ProtoPlugin`](crate::ProtoPlugin) sets this to the app's [`AppTypeRegistry`]. Without
+    /// one, every component `type` must be a registered [`ProtoComponent`].
+    pub fn set_type_registry(&self, type_registry: AppTypeRegistry) {
+        self.internal.write().unwrap().type_registry = Some(type_registry);
+    }
+
+    /// Registers every component of the given registry in this one
+    ///
+    /// # Arguments
+    ///
+    /// * `other`: The registry to copy the registrations from
+    pub fn extend(&self, other: &ProtoComponentRegistry) {
+        if Arc::ptr_eq(&self.internal, &other.internal) {
+            return;
+        }
+
+        let other = other.internal.read().unwrap();
+        let mut inner = self.internal.write().unwrap();
+        if inner.type_registry.is_none() {
+            inner.type_registry = other.type_registry.clone();
+        }
+        for (type_id, name) in other.names.iter() {
+            let registration = &other.registrations[name];
+            inner.names.insert(*type_id, name.clone());
+            inner.registrations.insert(
+                name.clone(),
+                ComponentRegistration {
+                    deserialize: registration.deserialize,
+                    serialize: registration.serialize,
+                },
+            );
+        }
+    }
+}
+
+/// Adds [`ProtoComponent`] registration to [`App`].
+pub trait RegisterProtoComponent {
+    /// Registers a [`ProtoComponent`] in this app's [`ProtoComponentRegistry`] using the given
+    /// `type` name.
+    ///
+    /// Components should be registered before adding the [`ProtoPlugin`](crate::ProtoPlugin),
+    /// since prototypes are loaded as soon as it's added (unless loading asynchronously).
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: The name used as the component's `type` in prototype files
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bevy::prelude::*;
+    /// use bevy_proto::prelude::*;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Clone, Serialize, Deserialize, ProtoComponent, Component)]
+    /// struct Health {
+    ///     max: u16,
+    /// }
+    ///
+    /// let mut app = App::new();
+    /// app.register_proto_component::<Health>("Health");
+    /// ```
+    fn register_proto_component<T>(&mut self, name: impl Into<String>) -> &mut Self
+    where
+        T: ProtoComponent + Serialize + DeserializeOwned;
+}
+
+impl RegisterProtoComponent for App {
+    fn register_proto_component<T>(&mut self, name: impl Into<String>) -> &mut Self
+    where
+        T: ProtoComponent + Serialize + DeserializeOwned,
+    {
+        self.world
+            .get_resource_or_insert_with(ProtoComponentRegistry::default)
+            .register::<T>(name);
+        self
+    }
+}
+
+fn deserialize_boxed<T: ProtoComponent + DeserializeOwned>(
+    deserializer: &mut dyn erased_serde::Deserializer<'_>,
+) -> Result<Box<dyn ProtoComponent>, erased_serde::Error> {
+    Ok(Box::new(erased_serde::deserialize::<T>(deserializer)?))
+}
+
+fn serialize_ref<T: ProtoComponent + Serialize>(
+    component: &dyn ProtoComponent,
+) -> Option<&dyn erased_serde::Serialize> {
+    let component: &dyn Any = component;
+    component
+        .downcast_ref::<T>()
+        .map(|component| component as &dyn erased_serde::Serialize)
+}
+
+/// The `type` name of a [`ProtoComponent`] registered with `typetag`.
+///
+/// Types deriving [`ProtoComponent`](bevy_proto_derive::ProtoComponent) submit their name
+/// automatically. A manual `#[typetag::serde]` implementation should submit its own, so that its
+/// name is known (such as when generating a schema):
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_proto::prelude::*;
+/// # use serde::{Deserialize, Serialize};
+/// use bevy_proto::registry::TypetagName;
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Inventory(Vec<String>);
+///
+/// #[typetag::serde]
+/// impl ProtoComponent for Inventory {
+///     fn insert_self(&self, commands: &mut ProtoCommands, asset_server: &Res<AssetServer>) {}
+/// }
+///
+/// bevy_proto::registry::inventory::submit! {
+///     TypetagName::new("Inventory")
+/// }
+/// ```
+///
+/// This requires the `typetag` feature.
+#[cfg(feature = "typetag")]
+pub struct TypetagName(&'static str);
+
+#[cfg(feature = "typetag")]
+impl TypetagName {
+    /// Creates the entry for the component registered with `typetag` under the given name
+    pub const fn new(name: &'static str) -> Self {
+        Self(name)
+    }
+
+    /// The `type` name of the component
+    pub fn name(&self) -> &'static str {
+        self.0
+    }
+}
+
+#[cfg(feature = "typetag")]
+inventory::collect!(TypetagName);
+
+#[cfg(feature = "typetag")]
+#[doc(hidden)]
+pub use inventory;
+
+/// The `type` names of every [`ProtoComponent`] registered with `typetag` (see [`TypetagName`])
+#[cfg(feature = "typetag")]
+pub(crate) fn typetag_names() -> impl Iterator<Item = &'static str> {
+    inventory::iter::<TypetagName>
+        .into_iter()
+        .map(TypetagName::name)
+}
+
+/// Returns true if a [`ProtoComponent`] is registered with `typetag` under the given `type` name
+fn is_typetag_name(name: &str) -> bool {
+    #[cfg(feature = "typetag")]
+    return typetag_names().any(|registered| registered == name);
+    #[cfg(not(feature = "typetag"))]
+    {
+        let _ = name;
+        false
+    }
+}
+
+// === Serde === //
+
+/// Deserializes the given `value` of the component with the given `type` name, using the given
+/// [`ProtoComponentRegistry`] (and `typetag`, if enabled)
+///
+/// Unlike [`ComponentSeed`], this never checks the value for references to params.
+pub(crate) fn deserialize_value(
+    registry: &ProtoComponentRegistry,
+    name: &str,
+    value: serde_yaml::Value,
+) -> Result<Box<dyn ProtoComponent>, String> {
+    ValueSeed {
+        registry,
+        tag: name.to_string(),
+    }
+    .deserialize(value)
+    .map_err(|err| err.to_string())
+}
+
+/// Serializes the `value` of a single component, using the given [`ProtoComponentRegistry`]
+/// (and `typetag`, if enabled).
+pub(crate) fn component_value(
+    registry: &ProtoComponentRegistry,
+    component: &dyn ProtoComponent,
+) -> Result<serde_yaml::Value, String> {
+    let mut serialized = serde_yaml::to_value(ComponentRef::new(registry, component))
+        .map_err(|err| err.to_string())?;
+    Ok(serialized
+        .get_mut("value")
+        .map(std::mem::take)
+        .unwrap_or_default())
+}
+
+const FIELDS: &[&str] = &["type", "value", "merge"];
+const BOTH_VALUE_AND_MERGE: &str = "a component can't have both a `value` and a `merge`";
+
+/// Serializes a component as a `type`/`value` pair
+pub(crate) struct ComponentRef<'a> {
+    /// The registry naming the registered components (if any)
+    registry: Option<&'a ProtoComponentRegistry>,
+    component: &'a dyn ProtoComponent,
+}
+
+impl<'a> ComponentRef<'a> {
+    pub(crate) fn new(
+        registry: &'a ProtoComponentRegistry,
+        component: &'a dyn ProtoComponent,
+    ) -> Self {
+        Self {
+            registry: Some(registry),
+            component,
+        }
+    }
+
+    /// Creates a reference to a component which is named by `typetag` alone
+    pub(crate) fn unregistered(component: &'a dyn ProtoComponent) -> Self {
+        Self {
+            registry: None,
+            component,
+        }
+    }
+}
+
+impl<'a> Serialize for ComponentRef<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if let Some(reflected) = as_reflected(self.component) {
+            return reflected.serialize(serializer);
+        }
+        if let Some(merged) = as_merged(self.component) {
+            return merged.serialize(serializer);
+        }
+        if let Some(parameterized) = as_parameterized(self.component) {
+            return parameterized.serialize(serializer);
+        }
+
+        let registered = self.registry.and_then(|registry| {
+            let inner = registry.internal.read().unwrap();
+            let component: &dyn Any = self.component;
+            let name = inner.names.get(&component.type_id())?;
+            Some((name.clone(), inner.registrations[name].serialize))
+        });
+
+        if let Some((name, serialize)) = registered {
+            let value = serialize(self.component)
+                .ok_or_else(|| ser::Error::custom("registered component has the wrong type"))?;
+            let mut state = serializer.serialize_struct("ProtoComponent", 2)?;
+            state.serialize_field("type", &name)?;
+            state.serialize_field("value", value)?;
+            return state.end();
+        }
+
+        #[cfg(feature = "typetag")]
+        return Serialize::serialize(self.component, serializer);
+        #[cfg(not(feature = "typetag"))]
+        Err(ser::Error::custom(
+            "component is not registered in the ProtoComponentRegistry",
+        ))
+    }
+}
+
+/// Deserializes a single component from a `type`/`value` pair, using the given
+/// [`ProtoComponentRegistry`] (and `typetag`, if enabled).
+///
+/// # Examples
+///
+/// ```
+/// use bevy::prelude::*;
+/// use bevy_proto::prelude::*;
+/// use bevy_proto::registry::ComponentSeed;
+/// use serde::de::DeserializeSeed;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Clone, Serialize, Deserialize, ProtoComponent, Component)]
+/// struct Health(u16);
+///
+/// let registry = ProtoComponentRegistry::default();
+/// registry.register::<Health>("Hp");
+///
+/// let deserializer = serde_yaml::Deserializer::from_str("{ type: Hp, value: 10 }");
+/// let component = ComponentSeed::new(&registry).deserialize(deserializer);
+/// assert!(component.is_ok());
+/// ```
+#[derive(Copy, Clone)]
+pub struct ComponentSeed<'a> {
+    registry: &'a ProtoComponentRegistry,
+    /// Whether the `value` may reference the params of the prototype being deserialized
+    in_prototype: bool,
+}
+
+impl<'a> ComponentSeed<'a> {
+    /// Creates a seed deserializing components with the given registry
+    pub fn new(registry: &'a ProtoComponentRegistry) -> Self {
+        Self {
+            registry,
+            in_prototype: false,
+        }
+    }
+
+    /// Creates a seed for the components of a [`Prototype`](crate::Prototype), whose values are
+    /// held until the whole prototype has been read if they may reference its params (see
+    /// [`params::finish_components`])
+    pub(crate) fn in_prototype(registry: &'a ProtoComponentRegistry) -> Self {
+        Self {
+            registry,
+            in_prototype: true,
+        }
+    }
+}
+
+impl<'a, 'de> DeserializeSeed<'de> for ComponentSeed<'a> {
+    type Value = Box<dyn ProtoComponent>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        // Values are only checked for references when they can be read back as YAML values
+        let visitor = ComponentVisitor {
+            registry: self.registry,
+            buffer: self.in_prototype && deserializer.is_human_readable(),
+        };
+        deserializer.deserialize_struct("ProtoComponent", FIELDS, visitor)
+    }
+}
+
+/// Deserializes a list of components, each as a `type`/`value` pair (see [`ComponentSeed`]).
+#[derive(Copy, Clone)]
+pub struct ComponentsSeed<'a>(ComponentSeed<'a>);
+
+impl<'a> ComponentsSeed<'a> {
+    /// Creates a seed deserializing components with the given registry
+    pub fn new(registry: &'a ProtoComponentRegistry) -> Self {
+        Self(ComponentSeed::new(registry))
+    }
+
+    /// Creates a seed for the components of a [`Prototype`](crate::Prototype) (see
+    /// [`ComponentSeed::in_prototype`])
+    pub(crate) fn in_prototype(registry: &'a ProtoComponentRegistry) -> Self {
+        Self(ComponentSeed::in_prototype(registry))
+    }
+}
+
+impl<'a, 'de> DeserializeSeed<'de> for ComponentsSeed<'a> {
+    type Value = Vec<Box<dyn ProtoComponent>>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'a, 'de> Visitor<'de> for ComponentsSeed<'a> {
+    type Value = Vec<Box<dyn ProtoComponent>>;
+
+    fn expecting(&self, formatter: &mut Formatter) -> std::fmt::Result {
+        formatter.write_str("a list of components")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut components = Vec::with_capacity(seq.size_hint().unwrap_or_default());
+        while let Some(component) = seq.next_element_seed(self.0)? {
+            components.push(component);
+        }
+        Ok(components)
+    }
+}
+
+struct ComponentVisitor<'a> {
+    registry: &'a ProtoComponentRegistry,
+    /// Whether to read each `value` as a YAML value first, to check it for references to params
+    buffer: bool,
+}
+
+impl<'a> ComponentVisitor<'a> {
+    fn seed(&self, tag: String) -> ValueSeed<'a> {
+        ValueSeed {
+            registry: self.registry,
+            tag,
+        }
+    }
+}
+
+impl<'a, 'de> Visitor<'de> for ComponentVisitor<'a> {
+    type Value = Box<dyn ProtoComponent>;
+
+    fn expecting(&self, formatter: &mut Formatter) -> std::fmt::Result {
+        formatter.write_str("a component with a `type` and an optional `value`")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let tag = seq
+            .next_element::<String>()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        let seed = self.seed(self.registry.canonical_name(&tag));
+        seq.next_element_seed(seed)?
+            .ok_or_else(|| de::Error::invalid_length(1, &self))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut tag = None;
+        // Only used if the `value` comes before the `type`
+        let mut buffered = None;
+        // The fields to merge into the inherited value (if any)
+        let mut merged = None;
+
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "type" => {
+                    let name = self.registry.canonical_name(&map.next_value::<String>()?);
+                    if buffered.is_none() && merged.is_none() {
+                        let seed = self.seed(name.clone());
+                        let component = match map.next_key::<String>()? {
+                            Some(key) if key == "value" && self.buffer => {
+                                parameterized(seed, map.next_value()?)?
+                            }
+                            Some(key) if key == "value" => map.next_value_seed(seed)?,
+                            Some(key) if key == "merge" => Box::new(MergedComponent::new(
+                                name,
+                                map.next_value::<serde_yaml::Value>()?,
+                            )),
+                            Some(key) => return Err(de::Error::unknown_field(&key, FIELDS)),
+                            None => seed.deserialize(UnitDeserializer::<A::Error>::new())?,
+                        };
+                        skip_rest(&mut map)?;
+                        return Ok(component);
+                    }
+                    tag = Some(name);
+                }
+                "value" if merged.is_none() => {
+                    buffered = Some(map.next_value::<serde_yaml::Value>()?)
+                }
+                "merge" if buffered.is_none() => {
+                    merged = Some(map.next_value::<serde_yaml::Value>()?)
+                }
+                "value" | "merge" => return Err(de::Error::custom(BOTH_VALUE_AND_MERGE)),
+                key => return Err(de::Error::unknown_field(key, FIELDS)),
+            }
+        }
+
+        let tag = tag.ok_or_else(|| de::Error::missing_field("type"))?;
+        if let Some(merged) = merged {
+            return Ok(Box::new(MergedComponent::new(tag, merged)));
+        }
+        let value = buffered.unwrap_or(serde_yaml::Value::Null);
+        if self.buffer {
+            return parameterized(self.seed(tag), value);
+        }
+        self.seed(tag).deserialize(value).map_err(de::Error::custom)
+    }
+}
+
+/// Deserializes the given `value`, unless it may reference the prototype's params (in which case
+/// it's held until the whole prototype has been read, see [`params::finish_components`])
+fn parameterized<E: de::Error>(
+    seed: ValueSeed,
+    value: serde_yaml::Value,
+) -> Result<Box<dyn ProtoComponent>, E> {
+    if params::has_references(&value) {
+        return Ok(Box::new(ParameterizedComponent::new(seed.tag, value)));
+    }
+    seed.deserialize(value).map_err(de::Error::custom)
+}
+
+/// Ensures no other fields follow a component's `type` and `value` (or `merge`)
+fn skip_rest<'de, A: MapAccess<'de>>(map: &mut A) -> Result<(), A::Error> {
+    match map.next_key::<String>()?.as_deref() {
+        Some("value" | "merge") => Err(de::Error::custom(BOTH_VALUE_AND_MERGE)),
+        Some(key) => Err(de::Error::unknown_field(key, FIELDS)),
+        None => Ok(()),
+    }
+}
+
+/// Deserializes the `value` of the component with the given `type` name
+struct ValueSeed<'a> {
+    registry: &'a ProtoComponentRegistry,
+    tag: String,
+}
+
+impl<'a, 'de> DeserializeSeed<'de> for ValueSeed<'a> {
+    type Value = Box<dyn ProtoComponent>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        let (registered, types) = {
+            let inner = self.registry.internal.read().unwrap();
+            let registered = inner
+                .registrations
+                .get(&self.tag)
+                .map(|registration| registration.deserialize);
+            (registered, inner.type_registry.clone())
+        };
+
+        if let Some(deserialize) = registered {
+            let mut erased = <dyn erased_serde::Deserializer>::erase(deserializer);
+            return deserialize(&mut erased).map_err(de::Error::custom);
+        }
+
+        // Anything else may be a component from the type registry
+        let types = types.filter(|_| !is_typetag_name(&self.tag));
+        if let Some(types) = types {
+            let types = types.read();
+            if let Some(registration) = reflect::find_type(&self.tag, &types) {
+                let value = serde_yaml::Value::deserialize(deserializer)?;
+                return Reflected::new(registration, value, &types)
+                    .map(|component| Box::new(component) as Box<dyn ProtoComponent>)
+                    .map_err(de::Error::custom);
+            }
+        }
+
+        #[cfg(feature = "typetag")]
+        return Box::<dyn ProtoComponent>::deserialize(TaggedDeserializer {
+            tag: Some(self.tag),
+            value: Some(deserializer),
+        });
+        #[cfg(not(feature = "typetag"))]
+        Err(unknown_component(&self.tag, &self.registry.names()))
+    }
+}
+
+/// Creates the error for an unregistered component `type`, matching the one reported by `typetag`
+#[cfg(not(feature = "typetag"))]
+fn unknown_component<E: de::Error>(tag: &str, names: &[String]) -> E {
+    let names = names
+        .iter()
+        .map(|name| format!("`{}`", name))
+        .collect::<Vec<_>>();
+    match names.len() {
+        0 => E::custom(format_args!(
+            "unknown variant `{}`, there are no variants",
+            tag
+        )),
+        _ => E::custom(format_args!(
+            "unknown variant `{}`, expected one of {}",
+            tag,
+            names.join(", ")
+        )),
+    }
+}
+
+/// A deserializer that recombines a component's `type` with its `value` so it can be
+/// deserialized by `typetag`
+#[cfg(feature = "typetag")]
+struct TaggedDeserializer<D> {
+    tag: Option<String>,
+    value: Option<D>,
+}
+
+#[cfg(feature = "typetag")]
+impl<'de, D: Deserializer<'de>> Deserializer<'de> for TaggedDeserializer<D> {
+    type Error = D::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_map(self)
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+#[cfg(feature = "typetag")]
+impl<'de, D: Deserializer<'de>> MapAccess<'de> for TaggedDeserializer<D> {
+    type Error = D::Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error> {
+        let key = if self.tag.is_some() {
+            "type"
+        } else if self.value.is_some() {
+            "value"
+        } else {
+            return Ok(None);
+        };
+        let key: StrDeserializer<Self::Error> = key.into_deserializer();
+        seed.deserialize(key).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, Self::Error> {
+        if let Some(tag) = self.tag.take() {
+            let tag: StringDeserializer<Self::Error> = tag.into_deserializer();
+            return seed.deserialize(tag);
+        }
+        match self.value.take() {
+            Some(value) => seed.deserialize(value),
+            None => Err(de::Error::custom("value is missing")),
+        }
+    }
+}
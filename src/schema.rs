@@ -0,0 +1,534 @@
+//! Provides [`ProtoSchemaGenerator`], which generates a [JSON Schema](https://json-schema.org)
+//! describing prototype files.
+//!
+//! Editors (such as VS Code with the YAML extension) can use this schema to validate prototype
+//! files and autocomplete component types as they're written.
+//!
+//! Every registered [`ProtoComponent`](crate::ProtoComponent) is listed in the schema. To also describe the `value` of a
+//! component, derive [`JsonSchema`] for it (using [`schemars`](https://docs.rs/schemars)) and
+//! either add the `#[proto_comp(schema)]` attribute when deriving `ProtoComponent`, or register it
+//! with [`ProtoSchemaGenerator::register`]:
+//!
+//! ```
+//! use bevy::prelude::*;
+//! use bevy_proto::prelude::*;
+//! use bevy_proto::schema::ProtoSchemaGenerator;
+//! use schemars::JsonSchema;
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Clone, Serialize, Deserialize, JsonSchema, ProtoComponent, Component)]
+//! #[proto_comp(schema)]
+//! struct Health {
+//!     max: u16,
+//! }
+//!
+//! let schema = ProtoSchemaGenerator::new().to_json();
+//! assert!(schema.contains("Health"));
+//! ```
+//!
+//! Components inserted through reflection (see [`Reflected`](crate::Reflected)) are described
+//! with [`ProtoSchemaGenerator::register_reflected`].
+//!
+//! This module requires the `schema` feature.
+use std::any::TypeId;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use bevy::ecs::reflect::ReflectComponent;
+use bevy::reflect::{TypeInfo, TypeRegistryInternal as TypeRegistry, VariantInfo};
+
+use schemars::gen::{SchemaGenerator, SchemaSettings};
+use schemars::schema::{
+    ArrayValidation, InstanceType, Metadata, ObjectValidation, RootSchema, Schema, SchemaObject,
+    SubschemaValidation,
+};
+use schemars::JsonSchema;
+
+use crate::registry::typetag_names;
+
+#[doc(hidden)]
+pub use inventory;
+
+/// Creates the schema of a component's `value`.
+type SchemaFn = fn(&mut SchemaGenerator) -> Schema;
+
+/// The schema of a [`ProtoComponent`](crate::ProtoComponent)'s `value`, registered through `#[proto_comp(schema)]`.
+pub struct ComponentSchema {
+    name: &'static str,
+    schema: SchemaFn,
+}
+
+impl ComponentSchema {
+    /// Creates a new [`ComponentSchema`] for the component registered with the given `type` name.
+    pub const fn new<T: JsonSchema>(name: &'static str) -> Self {
+        Self {
+            name,
+            schema: subschema_for::<T>,
+        }
+    }
+
+    /// The `type` name of the component
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+inventory::collect!(ComponentSchema);
+
+fn subschema_for<T: JsonSchema>(gen: &mut SchemaGenerator) -> Schema {
+    gen.subschema_for::<T>()
+}
+
+/// Generates a JSON Schema for prototype files.
+///
+/// The schema accepts either a single prototype or a list of them, where each prototype has a
+/// `name`, its `templates`, and a list of `components`. Each component is one of the registered
+/// [`ProtoComponent`](crate::ProtoComponent) types, with the schema of its `value` (if one was registered).
+///
+/// # Examples
+///
+/// ```no_run
+/// use bevy_proto::schema::ProtoSchemaGenerator;
+///
+/// ProtoSchemaGenerator::new()
+///     .write("assets/prototypes/schema.json")
+///     .expect("schema should be written");
+/// ```
+pub struct ProtoSchemaGenerator {
+    /// Maps Component Name -> Value Schema
+    components: BTreeMap<String, Option<SchemaFn>>,
+    /// Maps Type Path -> Value Schema, for reflected components
+    reflected: BTreeMap<String, Schema>,
+}
+
+impl Default for ProtoSchemaGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProtoSchemaGenerator {
+    /// Creates a generator containing every registered [`ProtoComponent`](crate::ProtoComponent), along with any value
+    /// schemas registered through `#[proto_comp(schema)]`.
+    pub fn new() -> Self {
+        let mut components = typetag_names()
+            .map(|name| (name.to_string(), None))
+            .collect::<BTreeMap<_, _>>();
+
+        for component in inventory::iter::<ComponentSchema> {
+            components.insert(component.name.to_string(), Some(component.schema));
+        }
+
+        Self {
+            components,
+            reflected: BTreeMap::new(),
+        }
+    }
+
+    /// Registers the schema of a component's `value`
+    ///
+    /// This is only needed for components that don't use `#[proto_comp(schema)]`,
+    /// such as those implementing [`ProtoComponent`](crate::ProtoComponent) manually.
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: The `type` name of the component
+    ///
+    /// returns: &mut ProtoSchemaGenerator
+    pub fn register<T: JsonSchema>(&mut self, name: impl Into<String>) -> &mut Self {
+        self.components
+            .insert(name.into(), Some(subschema_for::<T> as SchemaFn));
+        self
+    }
+
+    /// Registers every component in the given type registry which reflects `Component`, so they
+    /// can be used as [`Reflected`](crate::Reflected) components
+    ///
+    /// Each is listed under its full type path, with the schema of its `value` described from its
+    /// reflected type information. Fields are never required, since they may be filled in with
+    /// their defaults.
+    ///
+    /// # Arguments
+    ///
+    /// * `registry`: The app's type registry (such as the one in its `AppTypeRegistry`)
+    ///
+    /// returns: &mut ProtoSchemaGenerator
+    pub fn register_reflected(&mut self, registry: &TypeRegistry) -> &mut Self {
+        for registration in registry.iter() {
+            if registration.data::<ReflectComponent>().is_none() {
+                continue;
+            }
+            self.reflected.insert(
+                registration.type_name().to_string(),
+                reflected_schema(registration.type_info(), registry, 0),
+            );
+        }
+        self
+    }
+
+    /// Generates the schema
+    pub fn generate(&self) -> RootSchema {
+        let settings = SchemaSettings::draft07();
+        let meta_schema = settings.meta_schema.clone();
+        let mut gen = settings.into_generator();
+
+        // === Components === //
+        let variants = self
+            .components
+            .iter()
+            .map(|(name, schema)| {
+                let value = schema.map(|schema| schema(&mut gen));
+                component_schema(name, value)
+            })
+            .chain(
+                self.reflected
+                    .iter()
+                    .map(|(name, value)| component_schema(name, Some(value.clone()))),
+            )
+            .collect();
+        let component = SchemaObject {
+            metadata: metadata("A component to insert into the spawned entity"),
+            subschemas: Some(Box::new(SubschemaValidation {
+                one_of: Some(variants),
+                ..Default::default()
+            })),
+            ..Default::default()
+        };
+
+        // === Prototype === //
+        let templates = Schema::Object(SchemaObject {
+            metadata: metadata(
+                "The templates of this prototype, as a list or a comma-separated string",
+            ),
+            subschemas: Some(Box::new(SubschemaValidation {
+                any_of: Some(vec![
+                    instance(InstanceType::String),
+                    array_of(instance(InstanceType::String)),
+                ]),
+                ..Default::default()
+            })),
+            ..Default::default()
+        });
+
+        let mut properties = BTreeMap::new();
+        properties.insert(
+            String::from("name"),
+            Schema::Object(SchemaObject {
+                metadata: metadata("The name of this prototype (defaults to the file name)"),
+                instance_type: Some(InstanceType::String.into()),
+                ..Default::default()
+            }),
+        );
+        properties.insert(
+            String::from("local"),
+            Schema::Object(SchemaObject {
+                metadata: metadata(
+                    "The local name of the spawned entity, which other entities in the same hierarchy can reference (as `@name`)",
+                ),
+                instance_type: Some(InstanceType::String.into()),
+                ..Default::default()
+            }),
+        );
+        properties.insert(String::from("templates"), templates.clone());
+        properties.insert(String::from("template"), templates);
+        properties.insert(
+            String::from("params"),
+            Schema::Object(SchemaObject {
+                metadata: metadata(
+                    "The params this prototype's components can reference (as `$name`), mapped to their default values",
+                ),
+                instance_type: Some(InstanceType::Object.into()),
+                ..Default::default()
+            }),
+        );
+        properties.insert(
+            String::from("components"),
+            array_of(reference("ProtoComponent")),
+        );
+        properties.insert(
+            String::from("children"),
+            described(
+                "The child entities to spawn with this prototype, each given as the name of a prototype or an inline prototype",
+                array_of(Schema::Object(SchemaObject {
+                    subschemas: Some(Box::new(SubschemaValidation {
+                        any_of: Some(vec![
+                            instance(InstanceType::String),
+                            reference("Prototype"),
+                        ]),
+                        ..Default::default()
+                    })),
+                    ..Default::default()
+                })),
+            ),
+        );
+        properties.insert(
+            String::from("exclude"),
+            Schema::Object(SchemaObject {
+                metadata: metadata(
+                    "The types of the components to exclude from this prototype's templates, as a list or a comma-separated string",
+                ),
+                subschemas: Some(Box::new(SubschemaValidation {
+                    any_of: Some(vec![
+                        instance(InstanceType::String),
+                        array_of(instance(InstanceType::String)),
+                    ]),
+                    ..Default::default()
+                })),
+                ..Default::default()
+            }),
+        );
+        properties.insert(
+            String::from("patch"),
+            Schema::Object(SchemaObject {
+                metadata: metadata("The name of the prototype this patches (if any)"),
+                instance_type: Some(InstanceType::String.into()),
+                ..Default::default()
+            }),
+        );
+        properties.insert(
+            String::from("remove"),
+            described(
+                "The types of the components this patch removes",
+                array_of(instance(InstanceType::String)),
+            ),
+        );
+
+        let prototype = SchemaObject {
+            metadata: metadata("A prototype, used to spawn entities"),
+            instance_type: Some(InstanceType::Object.into()),
+            object: Some(Box::new(ObjectValidation {
+                properties: properties.into_iter().collect(),
+                ..Default::default()
+            })),
+            ..Default::default()
+        };
+
+        let mut definitions = gen.take_definitions();
+        definitions.insert(String::from("Prototype"), prototype.into());
+        definitions.insert(String::from("ProtoComponent"), component.into());
+
+        // === Root === //
+        let schema = SchemaObject {
+            metadata: Some(Box::new(Metadata {
+                title: Some(String::from("Prototype")),
+                description: Some(String::from("A prototype or a list of prototypes")),
+                ..Default::default()
+            })),
+            subschemas: Some(Box::new(SubschemaValidation {
+                any_of: Some(vec![
+                    reference("Prototype"),
+                    array_of(reference("Prototype")),
+                ]),
+                ..Default::default()
+            })),
+            ..Default::default()
+        };
+
+        RootSchema {
+            meta_schema,
+            schema,
+            definitions,
+        }
+    }
+
+    /// Generates the schema as pretty-printed JSON
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self.generate()).expect("schema should be serializable")
+    }
+
+    /// Generates the schema and writes it to the file at the given path
+    pub fn write(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        std::fs::write(path, self.to_json())
+    }
+}
+
+/// The schema of a single component, with the given `type` name and `value` schema
+fn component_schema(name: &str, value: Option<Schema>) -> Schema {
+    let mut object = ObjectValidation::default();
+    object.required.insert(String::from("type"));
+    object.properties.insert(
+        String::from("type"),
+        Schema::Object(SchemaObject {
+            const_value: Some(name.into()),
+            ..Default::default()
+        }),
+    );
+    if let Some(value) = value {
+        object.properties.insert(String::from("value"), value);
+    }
+    object.properties.insert(
+        String::from("merge"),
+        Schema::Object(SchemaObject {
+            metadata: metadata("Fields to merge into the value inherited from the templates"),
+            ..Default::default()
+        }),
+    );
+
+    Schema::Object(SchemaObject {
+        metadata: Some(Box::new(Metadata {
+            title: Some(name.to_string()),
+            ..Default::default()
+        })),
+        instance_type: Some(InstanceType::Object.into()),
+        object: Some(Box::new(object)),
+        ..Default::default()
+    })
+}
+
+fn metadata(description: &str) -> Option<Box<Metadata>> {
+    Some(Box::new(Metadata {
+        description: Some(description.to_string()),
+        ..Default::default()
+    }))
+}
+
+/// Adds the given description to an object schema
+fn described(description: &str, schema: Schema) -> Schema {
+    match schema {
+        Schema::Object(object) => Schema::Object(SchemaObject {
+            metadata: metadata(description),
+            ..object
+        }),
+        schema => schema,
+    }
+}
+
+fn instance(instance_type: InstanceType) -> Schema {
+    Schema::Object(SchemaObject {
+        instance_type: Some(instance_type.into()),
+        ..Default::default()
+    })
+}
+
+fn array_of(items: Schema) -> Schema {
+    Schema::Object(SchemaObject {
+        instance_type: Some(InstanceType::Array.into()),
+        array: Some(Box::new(ArrayValidation {
+            items: Some(items.into()),
+            ..Default::default()
+        })),
+        ..Default::default()
+    })
+}
+
+fn reference(definition: &str) -> Schema {
+    Schema::new_ref(format!("#/definitions/{}", definition))
+}
+
+/// How deeply nested types are described before their values are left unconstrained
+const MAX_REFLECTED_DEPTH: usize = 8;
+
+/// The schema of a reflected value with the given type information
+fn reflected_schema(info: &TypeInfo, registry: &TypeRegistry, depth: usize) -> Schema {
+    if depth > MAX_REFLECTED_DEPTH {
+        return Schema::Bool(true);
+    }
+    let field = |type_id: TypeId| match registry.get_type_info(type_id) {
+        Some(info) => reflected_schema(info, registry, depth + 1),
+        None => Schema::Bool(true),
+    };
+
+    match info {
+        TypeInfo::Struct(info) => object_of(
+            info.iter()
+                .map(|named| (named.name().to_string(), field(named.type_id()))),
+        ),
+        TypeInfo::TupleStruct(info) => tuple_of(
+            info.iter()
+                .map(|unnamed| field(unnamed.type_id()))
+                .collect(),
+        ),
+        TypeInfo::Tuple(info) => tuple_of(
+            info.iter()
+                .map(|unnamed| field(unnamed.type_id()))
+                .collect(),
+        ),
+        TypeInfo::List(info) => array_of(field(info.item_type_id())),
+        TypeInfo::Array(info) => array_of(field(info.item_type_id())),
+        TypeInfo::Map(info) => Schema::Object(SchemaObject {
+            instance_type: Some(InstanceType::Object.into()),
+            object: Some(Box::new(ObjectValidation {
+                additional_properties: Some(Box::new(field(info.value_type_id()))),
+                ..Default::default()
+            })),
+            ..Default::default()
+        }),
+        TypeInfo::Enum(info) => {
+            // Unit variants are written as their name, and others as a map from their name to
+            // their fields
+            let variants = info
+                .iter()
+                .map(|variant| match variant {
+                    VariantInfo::Unit(unit) => Schema::Object(SchemaObject {
+                        const_value: Some(unit.name().into()),
+                        ..Default::default()
+                    }),
+                    VariantInfo::Struct(variant) => object_of(std::iter::once((
+                        variant.name().to_string(),
+                        object_of(
+                            variant
+                                .iter()
+                                .map(|named| (named.name().to_string(), field(named.type_id()))),
+                        ),
+                    ))),
+                    VariantInfo::Tuple(variant) => {
+                        let fields = variant
+                            .iter()
+                            .map(|unnamed| field(unnamed.type_id()))
+                            .collect::<Vec<_>>();
+                        let value = match <[Schema; 1]>::try_from(fields) {
+                            Ok([value]) => value,
+                            Err(fields) => tuple_of(fields),
+                        };
+                        object_of(std::iter::once((variant.name().to_string(), value)))
+                    }
+                })
+                .collect();
+            Schema::Object(SchemaObject {
+                subschemas: Some(Box::new(SubschemaValidation {
+                    one_of: Some(variants),
+                    ..Default::default()
+                })),
+                ..Default::default()
+            })
+        }
+        TypeInfo::Value(info) => match info.type_name() {
+            "bool" => instance(InstanceType::Boolean),
+            "f32" | "f64" => instance(InstanceType::Number),
+            "i8" | "i16" | "i32" | "i64" | "i128" | "isize" | "u8" | "u16" | "u32" | "u64"
+            | "u128" | "usize" => instance(InstanceType::Integer),
+            "char" | "alloc::string::String" | "alloc::borrow::Cow<str>" => {
+                instance(InstanceType::String)
+            }
+            _ => Schema::Bool(true),
+        },
+        TypeInfo::Dynamic(_) => Schema::Bool(true),
+    }
+}
+
+/// An object with the given (optional) properties
+fn object_of(properties: impl Iterator<Item = (String, Schema)>) -> Schema {
+    Schema::Object(SchemaObject {
+        instance_type: Some(InstanceType::Object.into()),
+        object: Some(Box::new(ObjectValidation {
+            properties: properties.collect(),
+            ..Default::default()
+        })),
+        ..Default::default()
+    })
+}
+
+/// An array with exactly the given items
+fn tuple_of(items: Vec<Schema>) -> Schema {
+    let len = items.len() as u32;
+    Schema::Object(SchemaObject {
+        instance_type: Some(InstanceType::Array.into()),
+        array: Some(Box::new(ArrayValidation {
+            items: Some(items.into()),
+            min_items: Some(len),
+            max_items: Some(len),
+            ..Default::default()
+        })),
+        ..Default::default()
+    })
+}
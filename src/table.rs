@@ -0,0 +1,423 @@
+//! Contains [`ProtoTable`], a weighted table of prototypes to spawn.
+use std::path::Path;
+
+use bevy::ecs::prelude::Commands;
+use bevy::prelude::{AssetServer, Entity, Res};
+use serde::{Deserialize, Serialize};
+
+use crate::data::ProtoData;
+use crate::random::{pick_weighted, ProtoRng};
+
+/// The suffix of the file stem used by table files (such as `goblin_loot.table.yaml`)
+const TABLE_SUFFIX: &str = ".table";
+
+/// A table which picks prototypes to spawn at random, such as for loot drops or encounters.
+///
+/// Tables are loaded from the same directories as prototypes, from files whose names end in
+/// `.table` before the extension (such as `goblin_loot.table.yaml`). Like prototype files, these
+/// may contain a single table or a list of them. A file's sole table may leave out its `name` to be
+/// named after the file (without the `.table`).
+///
+/// Each time a table is rolled, every [`guaranteed`](Self::guaranteed) entry is used, followed by
+/// [`rolls`](Self::rolls) picks from the weighted [`entries`](Self::entries). An entry may refer to
+/// a prototype, another table (which is rolled in turn), or nothing at all.
+///
+/// ```yaml
+/// # assets/prototypes/goblin_loot.table.yaml
+/// name: GoblinLoot
+/// rolls: [1, 2]
+/// guaranteed:
+///   - prototype: Bones
+/// entries:
+///   - prototype: Gold
+///     weight: 10
+///     count: [1, 3]
+///   - table: RareLoot
+///     weight: 1
+///   # Drops nothing
+///   - weight: 5
+/// ```
+///
+/// The prototypes and tables referenced by each table are checked once every file has been
+/// loaded, with any problems recorded in [`ProtoLoadErrors`](crate::errors::ProtoLoadErrors).
+/// Tables are included in a [`ProtoPack`](crate::pack::ProtoPack) along with the prototypes.
+///
+/// # Examples
+///
+/// ```
+/// use bevy::prelude::*;
+/// use bevy_proto::prelude::*;
+///
+/// fn drop_loot(mut commands: Commands, data: Res<ProtoData>, asset_server: Res<AssetServer>) {
+///     let table = data.get_table("GoblinLoot").expect("Table doesn't exist!");
+///
+///     let entities = table.spawn(&mut commands, &data, &asset_server, data.rng());
+///     info!("Dropped {} items", entities.len());
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProtoTable {
+    /// The name of this table.
+    ///
+    /// When loaded from a file, this defaults to the file's stem without `.table` (such as
+    /// `goblin_loot` for `goblin_loot.table.yaml`).
+    #[serde(default)]
+    pub name: String,
+    /// The number of times to pick from the weighted [`entries`](Self::entries) (defaults to 1).
+    #[serde(default)]
+    pub rolls: TableCount,
+    /// The entries used every time this table is rolled (their weights are ignored).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub guaranteed: Vec<TableEntry>,
+    /// The entries to pick from, according to their weights.
+    #[serde(default)]
+    pub entries: Vec<TableEntry>,
+}
+
+/// A single entry of a [`ProtoTable`].
+///
+/// An entry with neither a [`prototype`](Self::prototype) nor a [`table`](Self::table) spawns
+/// nothing when picked.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TableEntry {
+    /// The name of the prototype to spawn (if any).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prototype: Option<String>,
+    /// The name of the table to roll (if any).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub table: Option<String>,
+    /// The chance of picking this entry, relative to the weights of the other entries
+    /// (defaults to 1).
+    #[serde(default = "default_weight")]
+    pub weight: f64,
+    /// The number of times the prototype is spawned (or the table is rolled) when this entry
+    /// is used (defaults to 1).
+    #[serde(default)]
+    pub count: TableCount,
+}
+
+/// A number of times to do something, given either as a fixed number (`2`) or as an inclusive
+/// range to pick from at random (`[1, 3]`).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum TableCount {
+    /// Exactly this many times.
+    Fixed(u32),
+    /// A random number of times between the two bounds (inclusive).
+    Range(u32, u32),
+}
+
+impl Default for TableCount {
+    fn default() -> Self {
+        Self::Fixed(1)
+    }
+}
+
+impl TableCount {
+    /// Picks the number of times, using the given generator for ranges
+    fn pick(self, rng: &mut fastrand::Rng) -> u32 {
+        match self {
+            Self::Fixed(count) => count,
+            Self::Range(min, max) if min <= max => rng.u32(min..=max),
+            // Invalid ranges are reported when loaded
+            Self::Range(..) => 0,
+        }
+    }
+}
+
+fn default_weight() -> f64 {
+    1.0
+}
+
+impl ProtoTable {
+    /// Rolls this table, returning the names of the prototypes to spawn (in order)
+    ///
+    /// Nested tables are rolled in turn. Any prototype or table which doesn't exist is skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `data`: The prototype data containing the nested tables
+    /// * `rng`: The generator used to pick entries (such as [`ProtoData::rng`])
+    ///
+    /// returns: Vec<String>
+    pub fn roll(&self, data: &ProtoData, rng: &ProtoRng) -> Vec<String> {
+        let mut rolled = Vec::new();
+        rng.with(|rng| self.roll_into(data, rng, &mut vec![self.name.as_str()], &mut rolled));
+        rolled
+    }
+
+    /// Rolls this table and spawns each of the prototypes rolled
+    ///
+    /// # Arguments
+    ///
+    /// * `commands`: The world `Commands`
+    /// * `data`: The prototype data in this world
+    /// * `asset_server`: The asset server
+    /// * `rng`: The generator used to pick entries (such as [`ProtoData::rng`])
+    ///
+    /// returns: Vec<Entity> containing the spawned entities
+    pub fn spawn(
+        &self,
+        commands: &mut Commands,
+        data: &Res<ProtoData>,
+        asset_server: &Res<AssetServer>,
+        rng: &ProtoRng,
+    ) -> Vec<Entity> {
+        self.roll(data, rng)
+            .iter()
+            .filter_map(|name| data.get_prototype(name))
+            .map(|proto| proto.spawn(commands, data, asset_server).id())
+            .collect()
+    }
+
+    fn roll_into<'a>(
+        &'a self,
+        data: &'a ProtoData,
+        rng: &mut fastrand::Rng,
+        traversed: &mut Vec<&'a str>,
+        rolled: &mut Vec<String>,
+    ) {
+        for entry in &self.guaranteed {
+            entry.use_into(data, rng, traversed, rolled);
+        }
+
+        let weights = self
+            .entries
+            .iter()
+            .map(|entry| entry.weight)
+            .collect::<Vec<_>>();
+        for _ in 0..self.rolls.pick(rng) {
+            if let Some(index) = pick_weighted(rng, &weights) {
+                self.entries[index].use_into(data, rng, traversed, rolled);
+            }
+        }
+    }
+}
+
+impl TableEntry {
+    fn use_into<'a>(
+        &'a self,
+        data: &'a ProtoData,
+        rng: &mut fastrand::Rng,
+        traversed: &mut Vec<&'a str>,
+        rolled: &mut Vec<String>,
+    ) {
+        let count = self.count.pick(rng);
+
+        if let Some(prototype) = &self.prototype {
+            if data.get_prototype(prototype).is_some() {
+                rolled.extend((0..count).map(|_| prototype.clone()));
+            }
+        }
+
+        if let Some(name) = &self.table {
+            // Cycles are reported when loaded
+            if traversed.contains(&name.as_str()) {
+                return;
+            }
+
+            if let Some(table) = data.get_table(name) {
+                traversed.push(table.name.as_str());
+                for _ in 0..count {
+                    table.roll_into(data, rng, traversed, rolled);
+                }
+                traversed.pop();
+            }
+        }
+    }
+}
+
+/// Returns true if the file at the given path contains tables rather than prototypes
+pub(crate) fn is_table_path(path: &Path) -> bool {
+    path.file_stem()
+        .and_then(|stem| stem.to_str())
+        .is_some_and(|stem| stem.ends_with(TABLE_SUFFIX))
+}
+
+/// The default name of the tables in the file at the given path
+pub(crate) fn table_name(path: &Path) -> String {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    stem.strip_suffix(TABLE_SUFFIX).unwrap_or(&stem).to_string()
+}
+
+/// Finds every problem with the tables in the given [`ProtoData`]
+///
+/// returns: Vec<(&str, String)> containing the name of each offending table along with a
+/// description of the problem
+pub(crate) fn find_invalid_tables(data: &ProtoData) -> Vec<(&str, String)> {
+    let mut invalid = Vec::new();
+
+    for table in data.tables.values() {
+        let name = table.name.as_str();
+        if let TableCount::Range(min, max) = table.rolls {
+            if min > max {
+                invalid.push((
+                    name,
+                    format!("`rolls` has a minimum of {} but a maximum of {}", min, max),
+                ));
+            }
+        }
+
+        for entry in table.guaranteed.iter().chain(&table.entries) {
+            if entry.prototype.is_some() && entry.table.is_some() {
+                invalid.push((
+                    name,
+                    String::from("an entry can't have both a `prototype` and a `table`"),
+                ));
+            }
+            if let Some(prototype) = &entry.prototype {
+                if data.get_prototype(prototype).is_none() {
+                    invalid.push((name, format!("prototype `{}` does not exist", prototype)));
+                }
+            }
+            if let Some(nested) = &entry.table {
+                if data.get_table(nested).is_none() {
+                    invalid.push((name, format!("table `{}` does not exist", nested)));
+                }
+            }
+            if !(entry.weight >= 0.0 && entry.weight.is_finite()) {
+                invalid.push((
+                    name,
+                    format!("the weight {} must be a non-negative number", entry.weight),
+                ));
+            }
+            if let TableCount::Range(min, max) = entry.count {
+                if min > max {
+                    invalid.push((
+                        name,
+                        format!(
+                            "a `count` has a minimum of {} but a maximum of {}",
+                            min, max
+                        ),
+                    ));
+                }
+            }
+        }
+
+        if contains_table(data, table, name, &mut Vec::new()) {
+            invalid.push((name, String::from("the table (eventually) contains itself")));
+        }
+    }
+
+    invalid.sort();
+    return invalid;
+
+    /// Returns true if the given table (or any table nested within it) contains the target table
+    fn contains_table<'a>(
+        data: &'a ProtoData,
+        table: &'a ProtoTable,
+        target: &str,
+        traversed: &mut Vec<&'a str>,
+    ) -> bool {
+        for entry in table.guaranteed.iter().chain(&table.entries) {
+            let nested = match entry.table.as_deref() {
+                Some(nested) => nested,
+                None => continue,
+            };
+            if nested == target {
+                return true;
+            }
+            if traversed.contains(&nested) {
+                continue;
+            }
+
+            traversed.push(nested);
+            if let Some(nested) = data.get_table(nested) {
+                if contains_table(data, nested, target, traversed) {
+                    return true;
+                }
+            }
+        }
+
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use bevy::asset::FileAssetIo;
+    use bevy::prelude::{FromWorld, World};
+
+    use super::*;
+    use crate::data::ProtoDataOptions;
+    use crate::errors::{ProtoLoadErrorKind, ProtoLoadErrors};
+
+    /// Loads the given files, returning the loaded data and any errors
+    fn load(name: &str, files: &[(&str, &str)]) -> (ProtoData, ProtoLoadErrors) {
+        let dir = std::env::temp_dir()
+            .join(format!("bevy_proto_{}", std::process::id()))
+            .join(name);
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for (file, contents) in files {
+            fs::write(dir.join(file), contents).unwrap();
+        }
+
+        let mut world = World::new();
+        world.insert_resource(AssetServer::new(FileAssetIo::new(&dir, false)));
+        world.insert_resource(ProtoDataOptions {
+            directories: vec![dir.to_string_lossy().to_string()],
+            ..Default::default()
+        });
+        let data = ProtoData::from_world(&mut world);
+        let _ = fs::remove_dir_all(&dir);
+        (data, world.remove_resource::<ProtoLoadErrors>().unwrap())
+    }
+
+    #[test]
+    fn missing_prototype() {
+        let (data, errors) = load(
+            "table_missing_prototype",
+            &[
+                ("gold.yaml", "name: Gold\n"),
+                (
+                    "loot.table.yaml",
+                    "rolls: 4\nguaranteed:\n  - prototype: Bones\nentries:\n  - prototype: Gold\n",
+                ),
+            ],
+        );
+
+        assert_eq!(errors.len(), 1);
+        let error = errors.iter().next().unwrap();
+        assert!(error.path.ends_with("loot.table.yaml"));
+        assert!(matches!(
+            &error.kind,
+            ProtoLoadErrorKind::InvalidTable { table, reason }
+                if table == "loot" && reason == "prototype `Bones` does not exist"
+        ));
+
+        // The missing prototype is skipped when rolling
+        let table = data.get_table("loot").unwrap();
+        assert_eq!(table.roll(&data, &ProtoRng::with_seed(0)), vec!["Gold"; 4]);
+    }
+
+    #[test]
+    fn zero_weights() {
+        let (data, errors) = load(
+            "table_zero_weights",
+            &[
+                ("gold.yaml", "name: Gold\n"),
+                ("bones.yaml", "name: Bones\n"),
+                (
+                    "loot.table.yaml",
+                    "rolls: 3\nguaranteed:\n  - prototype: Bones\n    weight: 0\nentries:\n  - prototype: Gold\n    weight: 0\n  - weight: 0\n",
+                ),
+            ],
+        );
+        assert!(errors.is_empty());
+
+        // Entries with no weight are never picked, while guaranteed entries ignore their weights
+        let table = data.get_table("loot").unwrap();
+        let rng = ProtoRng::with_seed(0);
+        for _ in 0..10 {
+            assert_eq!(table.roll(&data, &rng), vec!["Bones"]);
+        }
+    }
+}
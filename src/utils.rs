@@ -1,12 +1,15 @@
 use indexmap::IndexSet;
 use std::ops::Add;
 
+use crate::data::ProtoData;
+
 /// Generates a string displaying a dependency cycle
 ///
 /// # Arguments
 ///
 /// * `template_name`: The name of the offending template
 /// * `traversed`: The set of traversed templates
+/// * `data`: The [`ProtoData`] containing the prototypes, used to look up where each was defined
 ///
 /// returns: String
 ///
@@ -20,16 +23,20 @@ use std::ops::Add;
 /// traversed.insert("B");
 /// traversed.insert("C");
 ///
-/// let tree = make_cycle_tree("B", &traversed);
+/// let tree = make_cycle_tree("B", &traversed, &data);
 /// println!("{}", tree);
-/// // Output: 'A' -> 'B' -> 'C' -> 'B'
+/// // Output: 'A' (assets/prototypes/a.yaml) -> 'B' (assets/prototypes/b.yaml) -> 'C' (assets/prototypes/c.yaml) -> 'B' (assets/prototypes/b.yaml)
 /// ```
-pub(crate) fn make_cycle_tree(template_name: &str, traversed: &IndexSet<&str>) -> String {
+pub(crate) fn make_cycle_tree(
+    template_name: &str,
+    traversed: &IndexSet<&str>,
+    data: &ProtoData,
+) -> String {
     traversed
         .iter()
-        .map(|n| format!("'{}' -> ", n))
+        .map(|n| format!("{} -> ", data.describe(n)))
         .collect::<String>()
-        .add(&format!("'{}'", template_name))
+        .add(&data.describe(template_name))
 }
 
 /// Handles a dependency cycle by panicking
@@ -38,14 +45,15 @@ pub(crate) fn make_cycle_tree(template_name: &str, traversed: &IndexSet<&str>) -
 ///
 /// * `template_name`: The name of the offending template
 /// * `traversed`: The set of traversed templates
+/// * `data`: The [`ProtoData`](crate::data::ProtoData) containing the prototypes
 ///
 #[cfg(feature = "no_cycles")]
 macro_rules! handle_cycle {
-	($template_name: ident, $traversed: ident) => {{
-		handle_cycle!($template_name, $traversed, "");
+	($template_name: ident, $traversed: ident, $data: ident) => {{
+		handle_cycle!($template_name, $traversed, $data, "");
 	}};
-	($template_name: ident, $traversed: ident, $($arg:tt)*) => {{
-		let tree = crate::utils::make_cycle_tree($template_name, $traversed);
+	($template_name: ident, $traversed: ident, $data: ident, $($arg:tt)*) => {{
+		let tree = crate::utils::make_cycle_tree($template_name, $traversed, $data);
 		panic!(
 			"{} {}\n\t{} {}",
 			"Found a circular dependency in the following prototypes:",
@@ -62,14 +70,15 @@ macro_rules! handle_cycle {
 ///
 /// * `template_name`: The name of the offending template
 /// * `traversed`: The set of traversed templates
+/// * `data`: The [`ProtoData`](crate::data::ProtoData) containing the prototypes
 ///
 #[cfg(not(feature = "no_cycles"))]
 macro_rules! handle_cycle {
-	($template_name: ident, $traversed: ident) => {{
-		handle_cycle!($template_name, $traversed, "");
+	($template_name: ident, $traversed: ident, $data: ident) => {{
+		handle_cycle!($template_name, $traversed, $data, "");
 	}};
-	($template_name: ident, $traversed: ident, $($arg:tt)*) => {{
-		let tree = crate::utils::make_cycle_tree($template_name, $traversed);
+	($template_name: ident, $traversed: ident, $data: ident, $($arg:tt)*) => {{
+		let tree = crate::utils::make_cycle_tree($template_name, $traversed, $data);
 		bevy::log::warn!(
 			"{} {}\n\t{} {}",
 			"Found a circular dependency in the following prototypes:",
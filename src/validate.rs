@@ -0,0 +1,714 @@
+//! Provides a validator for prototype files, meant to catch broken prototype data in CI.
+//!
+//! The validator reads prototype files exactly as [`ProtoData`] does, then reports:
+//! - Files that could not be read or parsed (including unknown component types)
+//! - Templates that don't exist
+//! - Children referencing prototypes that don't exist
+//! - Circular template dependencies
+//! - Prototypes with duplicate names (following [`ProtoDataOptions::duplicate_policy`])
+//! - Components declared with `merge` whose merged value can't be deserialized
+//! - Excluded components which aren't provided by any template (as a warning)
+//! - Patches whose target does not exist in their own or an earlier layer
+//! - Prototypes which can't be spawned with the default values of their params (as a warning)
+//! - Components referencing entities (with a [`ProtoEntityRef`](crate::ProtoEntityRef)) by a local
+//!   name which is never spawned with them
+//! - [`ProtoTable`](crate::ProtoTable)s referencing prototypes or tables that don't exist (along
+//!   with any other invalid table)
+//!
+//! Since [`ProtoComponent`](crate::components::ProtoComponent) types are registered with `typetag`,
+//! the validator only knows about the components linked into the running binary. Games should
+//! therefore call [`run`] from a small binary of their own which depends on the crate defining
+//! their components. Components registered per app should be added to
+//! [`ProtoDataOptions::registry`] instead. Likewise, reflected components (see
+//! [`Reflected`](crate::Reflected)) are only known if the registry is given the type registry
+//! containing them with [`set_type_registry`](crate::ProtoComponentRegistry::set_type_registry):
+//!
+//! ```no_run
+//! use std::process::ExitCode;
+//! use bevy_proto::data::ProtoDataOptions;
+//!
+//! fn main() -> ExitCode {
+//!     // Reference your components so they're linked in, e.g.:
+//!     // my_game::components::register();
+//!
+//!     let options = ProtoDataOptions {
+//!         directories: vec![String::from("assets/prototypes")],
+//!         ..Default::default()
+//!     };
+//!     // Or register them explicitly, e.g.:
+//!     // options.registry.register::<my_game::Health>("Health");
+//!     // options.registry.set_type_registry(my_game::type_registry());
+//!
+//!     bevy_proto::validate::run(options)
+//! }
+//! ```
+//!
+//! This module requires the `validate` feature.
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use serde::Serialize;
+
+use crate::children::{inline_child, ProtoChild};
+use crate::data::{
+    collect_paths, find_cycles, find_unused_exclusions, read_path, read_tables, DuplicatePolicy,
+    ProtoData, ProtoDataOptions, ProtoSource,
+};
+use crate::entity_ref::find_missing_refs;
+use crate::errors::ProtoDeserializeError;
+use crate::params::ProtoParams;
+use crate::table::{find_invalid_tables, is_table_path};
+
+const USAGE: &str = "\
+Validates prototype files, exiting with a nonzero status if any errors are found.
+
+USAGE:
+    <validator> [OPTIONS] [DIRECTORY]...
+
+ARGS:
+    [DIRECTORY]...    The prototype directories to check (defaults to the configured directories)
+
+OPTIONS:
+    -r, --recursive         Also check files in subdirectories
+        --namespacing       Identify prototypes by their path (see `ProtoDataOptions::namespacing`)
+        --format <FORMAT>   The output format: `human` (default) or `json`
+        --deny-warnings     Exit with a nonzero status if there are any warnings
+    -h, --help              Print this message";
+
+/// How serious a [`Diagnostic`] is.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// The prototype data is broken.
+    Error,
+    /// The prototype data works, but is likely not what was intended.
+    Warning,
+}
+
+/// The kind of problem described by a [`Diagnostic`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiagnosticKind {
+    /// A file or directory could not be read.
+    Io,
+    /// A file could not be parsed.
+    Parse,
+    /// A component's `type` does not match any registered [`ProtoComponent`](crate::components::ProtoComponent).
+    UnknownComponent,
+    /// A prototype uses a template which does not exist.
+    MissingTemplate,
+    /// A prototype's children reference a prototype which does not exist.
+    MissingChild,
+    /// A prototype's templates (eventually) refer back to itself.
+    Cycle,
+    /// Several prototypes share the same name.
+    Duplicate,
+    /// A component's `merge` can't be merged with the value it inherits.
+    InvalidMerge,
+    /// A prototype excludes a component which none of its templates provide.
+    UnusedExclusion,
+    /// A patch targets a prototype which does not exist in its own or an earlier layer.
+    MissingPatchTarget,
+    /// A prototype can't be spawned with the default values of its params.
+    InvalidParams,
+    /// A table references a prototype or table which does not exist (or is otherwise invalid).
+    InvalidTable,
+    /// A component references an entity by a local name which is never spawned with it.
+    MissingEntityRef,
+}
+
+impl Display for DiagnosticKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Io => "io",
+            Self::Parse => "parse",
+            Self::UnknownComponent => "unknown_component",
+            Self::MissingTemplate => "missing_template",
+            Self::MissingChild => "missing_child",
+            Self::Cycle => "cycle",
+            Self::Duplicate => "duplicate",
+            Self::InvalidMerge => "invalid_merge",
+            Self::UnusedExclusion => "unused_exclusion",
+            Self::MissingPatchTarget => "missing_patch_target",
+            Self::InvalidParams => "invalid_params",
+            Self::InvalidTable => "invalid_table",
+            Self::MissingEntityRef => "missing_entity_ref",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A single problem found by [`validate`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    /// How serious the problem is.
+    pub severity: Severity,
+    /// The kind of problem.
+    pub kind: DiagnosticKind,
+    /// A human-readable description of the problem.
+    pub message: String,
+    /// The file containing the problem, if any.
+    pub path: Option<PathBuf>,
+    /// The index of the prototype within its file, if known.
+    pub document: Option<usize>,
+    /// The line (starting at 1) at which the problem occurred, if known.
+    pub line: Option<usize>,
+    /// The column (starting at 1) at which the problem occurred, if known.
+    pub column: Option<usize>,
+    /// The name of the prototype containing the problem, if known.
+    pub prototype: Option<String>,
+}
+
+impl Diagnostic {
+    /// Creates a new diagnostic with no location information.
+    fn new(severity: Severity, kind: DiagnosticKind, message: impl Into<String>) -> Self {
+        Self {
+            severity,
+            kind,
+            message: message.into(),
+            path: None,
+            document: None,
+            line: None,
+            column: None,
+            prototype: None,
+        }
+    }
+
+    /// Sets the file in which this problem occurred.
+    fn at_path(mut self, path: &Path) -> Self {
+        self.path = Some(path.to_path_buf());
+        self
+    }
+
+    /// Sets the location of the table in which this problem occurred.
+    fn in_table(mut self, source: Option<&ProtoSource>) -> Self {
+        if let Some(source) = source {
+            self.path = Some(source.path.clone());
+            self.document = Some(source.document);
+        }
+        self
+    }
+
+    /// Sets the prototype in which this problem occurred (along with its location).
+    fn in_prototype(mut self, name: &str, source: Option<&ProtoSource>) -> Self {
+        self.prototype = Some(name.to_string());
+        if let Some(source) = source {
+            self.path = Some(source.path.clone());
+            self.document = Some(source.document);
+        }
+        self
+    }
+
+    /// Creates a diagnostic for a file that failed to deserialize.
+    fn from_deserialize_error(path: &Path, document: usize, err: ProtoDeserializeError) -> Self {
+        // Unknown `type` tags are reported by typetag as unknown enum variants
+        let kind = if err.message.contains("unknown variant") {
+            DiagnosticKind::UnknownComponent
+        } else {
+            DiagnosticKind::Parse
+        };
+
+        let mut diagnostic = Self::new(Severity::Error, kind, err.to_string()).at_path(path);
+        diagnostic.document = Some(document);
+        diagnostic.line = err.line;
+        diagnostic.column = err.column;
+        diagnostic
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(f, "{}[{}]", severity, self.kind)?;
+
+        if let Some(path) = &self.path {
+            write!(f, " {}", path.display())?;
+            match (self.line, self.column, self.document) {
+                (Some(line), Some(column), _) => write!(f, ":{}:{}", line, column)?,
+                (_, _, Some(document)) if document > 0 => write!(f, " (document {})", document)?,
+                _ => {}
+            }
+        }
+
+        write!(f, ": {}", self.message)
+    }
+}
+
+/// The results of [`validate`].
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct ValidationReport {
+    /// The number of prototypes that were loaded successfully.
+    pub prototypes: usize,
+    /// Every problem that was found, in the order they were found.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl ValidationReport {
+    /// The number of errors found
+    pub fn error_count(&self) -> usize {
+        self.count(Severity::Error)
+    }
+
+    /// The number of warnings found
+    pub fn warning_count(&self) -> usize {
+        self.count(Severity::Warning)
+    }
+
+    /// Returns true if any errors were found
+    pub fn has_errors(&self) -> bool {
+        self.error_count() > 0
+    }
+
+    /// Formats this report as pretty-printed JSON
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("report should be serializable")
+    }
+
+    fn count(&self, severity: Severity) -> usize {
+        self.diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == severity)
+            .count()
+    }
+
+    fn push(&mut self, diagnostic: Diagnostic) {
+        self.diagnostics.push(diagnostic);
+    }
+}
+
+impl Display for ValidationReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for diagnostic in &self.diagnostics {
+            writeln!(f, "{}", diagnostic)?;
+        }
+        writeln!(
+            f,
+            "Checked {} prototype(s): {} error(s), {} warning(s)",
+            self.prototypes,
+            self.error_count(),
+            self.warning_count()
+        )
+    }
+}
+
+/// Loads the prototype files described by the given options and reports any problems found.
+///
+/// Unlike [`ProtoData`], the prototypes are never prepared, so no `World` or `AssetServer` is needed.
+///
+/// # Arguments
+///
+/// * `options`: The options describing which prototype files to check
+///
+/// returns: ValidationReport
+///
+/// # Examples
+///
+/// ```
+/// use bevy_proto::data::ProtoDataOptions;
+/// use bevy_proto::validate::validate;
+///
+/// let report = validate(&ProtoDataOptions {
+///     directories: vec![String::from("assets/prototypes")],
+///     ..Default::default()
+/// });
+///
+/// for diagnostic in &report.diagnostics {
+///     println!("{}", diagnostic);
+/// }
+/// ```
+pub fn validate(options: &ProtoDataOptions) -> ValidationReport {
+    let mut report = ValidationReport::default();
+    let mut data = ProtoData::empty();
+    data.registry = options.registry.clone();
+    // Maps Prototype Name -> Earliest Layer
+    let mut layers = HashMap::<String, usize>::new();
+    let mut patches = Vec::new();
+
+    // === Read Files === //
+    let mut paths = Vec::new();
+    let mut dir_errors = Vec::new();
+    for directory in &options.directories {
+        collect_paths(options, Path::new(directory), &mut paths, &mut dir_errors);
+    }
+    for err in dir_errors {
+        let message = err.kind.to_string();
+        report
+            .push(Diagnostic::new(Severity::Error, DiagnosticKind::Io, message).at_path(&err.path));
+    }
+
+    for path in paths {
+        if is_table_path(&path) {
+            read_table_file(options, &path, &mut data, &mut report);
+            continue;
+        }
+
+        let results = match read_path(options, &path) {
+            Ok(results) => results,
+            Err(err) => {
+                let message = format!("could not be read: {}", err);
+                report.push(
+                    Diagnostic::new(Severity::Error, DiagnosticKind::Io, message).at_path(&path),
+                );
+                continue;
+            }
+        };
+
+        for (document, result) in results.into_iter().enumerate() {
+            let proto = match result {
+                Ok(proto) => proto,
+                Err(err) => {
+                    report.push(Diagnostic::from_deserialize_error(&path, document, err));
+                    continue;
+                }
+            };
+
+            let name = proto.name().to_string();
+            let source =
+                ProtoSource::new(path.clone(), document).with_layer(options.layer_of(&path));
+
+            if let Some(target) = proto.patch() {
+                patches.push((name, target.to_string(), source));
+                continue;
+            }
+
+            layers
+                .entry(name.clone())
+                .and_modify(|layer| *layer = (*layer).min(source.layer))
+                .or_insert(source.layer);
+
+            // With layering, later layers replace the prototypes of earlier ones silently
+            let duplicate = data
+                .source_of(&name)
+                .filter(|original| !(options.layered && original.layer < source.layer));
+
+            if let Some(original) = duplicate {
+                let severity = match options.duplicate_policy {
+                    DuplicatePolicy::Error => Some(Severity::Error),
+                    DuplicatePolicy::WarnKeepFirst | DuplicatePolicy::WarnKeepLast => {
+                        Some(Severity::Warning)
+                    }
+                    DuplicatePolicy::Override => None,
+                };
+                if let Some(severity) = severity {
+                    let message =
+                        format!("prototype `{}` was already defined in {}", name, original);
+                    report.push(
+                        Diagnostic::new(severity, DiagnosticKind::Duplicate, message)
+                            .in_prototype(&name, Some(&source)),
+                    );
+                }
+                if !options.duplicate_policy.replaces_original() {
+                    continue;
+                }
+            }
+
+            data.prototypes.insert(name.clone(), proto);
+            data.sources.insert(name, source);
+        }
+    }
+
+    report.prototypes = data.prototypes.len();
+
+    // === Check Patches === //
+    for (name, target, source) in patches {
+        let exists = layers
+            .get(&target)
+            .is_some_and(|layer| *layer <= source.layer);
+        if !exists {
+            let message = format!(
+                "patch `{}` targets prototype `{}`, which does not exist in this or an earlier layer",
+                name, target
+            );
+            report.push(
+                Diagnostic::new(Severity::Error, DiagnosticKind::MissingPatchTarget, message)
+                    .in_prototype(&name, Some(&source)),
+            );
+        }
+    }
+
+    // === Check Templates === //
+    let mut names = data.prototypes.keys().collect::<Vec<_>>();
+    names.sort();
+    for name in names {
+        let proto = &data.prototypes[name];
+        for template in proto.templates() {
+            if data.get_prototype(template).is_none() {
+                let message = format!(
+                    "prototype `{}` uses template `{}`, which does not exist",
+                    name, template
+                );
+                report.push(
+                    Diagnostic::new(Severity::Error, DiagnosticKind::MissingTemplate, message)
+                        .in_prototype(name, data.source_of(name)),
+                );
+            }
+        }
+    }
+
+    // === Check Children === //
+    let mut names = data.prototypes.keys().collect::<Vec<_>>();
+    names.sort();
+    for name in names {
+        let mut missing = Vec::new();
+        find_missing_children(data.prototypes[name].children(), &data, &mut missing);
+        for child in missing {
+            let message = format!(
+                "prototype `{}` has child `{}`, which does not exist",
+                name, child
+            );
+            report.push(
+                Diagnostic::new(Severity::Error, DiagnosticKind::MissingChild, message)
+                    .in_prototype(name, data.source_of(name)),
+            );
+        }
+    }
+
+    // === Check Cycles === //
+    let mut cycles = find_cycles(&data)
+        .into_iter()
+        .map(|(template, traversed)| {
+            let start = traversed.first().copied().unwrap_or(template);
+            let tree = crate::utils::make_cycle_tree(template, &traversed, &data);
+            (start, tree)
+        })
+        .collect::<Vec<_>>();
+    cycles.sort();
+    for (start, tree) in cycles {
+        let message = format!("found a circular dependency: {}", tree);
+        report.push(
+            Diagnostic::new(Severity::Error, DiagnosticKind::Cycle, message)
+                .in_prototype(start, data.source_of(start)),
+        );
+    }
+
+    // === Check Merges === //
+    let mut merges = Vec::new();
+    for (name, path, index, result) in crate::merge::resolve_all(&data) {
+        match result {
+            Ok(component) => {
+                // Resolved so that any entity references within it can be checked
+                let merged = data
+                    .prototypes
+                    .get(&name)
+                    .and_then(|proto| inline_child(proto.as_ref(), &path))
+                    .and_then(|target| target.iter_components().nth(index))
+                    .and_then(|component| crate::merge::as_merged(component.as_ref()));
+                if let Some(merged) = merged {
+                    merged.set_resolved(Some(component));
+                }
+            }
+            Err(err) => merges.push((name, err.to_string())),
+        }
+    }
+    merges.sort();
+    for (name, message) in merges {
+        report.push(
+            Diagnostic::new(Severity::Error, DiagnosticKind::InvalidMerge, message)
+                .in_prototype(&name, data.source_of(&name)),
+        );
+    }
+
+    // === Check Params === //
+    // Params without a default may be intended to always be given, so these are only warnings
+    let mut names = data.prototypes.keys().collect::<Vec<_>>();
+    names.sort();
+    for name in names {
+        let proto = data.prototypes[name].as_ref();
+        if let Err(err) = crate::params::resolve_all(proto, &data, &ProtoParams::default()) {
+            let message = format!(
+                "prototype `{}` can't be spawned without params: {} (in component `{}`)",
+                name, err.kind, err.component
+            );
+            report.push(
+                Diagnostic::new(Severity::Warning, DiagnosticKind::InvalidParams, message)
+                    .in_prototype(name, data.source_of(name)),
+            );
+        }
+    }
+
+    // === Check Entity References === //
+    for (name, local) in find_missing_refs(&data) {
+        let message = format!(
+            "prototype `{}` references an entity named `@{}`, which is never spawned with it",
+            name, local
+        );
+        report.push(
+            Diagnostic::new(Severity::Error, DiagnosticKind::MissingEntityRef, message)
+                .in_prototype(name, data.source_of(name)),
+        );
+    }
+
+    // === Check Tables === //
+    for (name, message) in find_invalid_tables(&data) {
+        let message = format!("table `{}` is invalid: {}", name, message);
+        report.push(
+            Diagnostic::new(Severity::Error, DiagnosticKind::InvalidTable, message)
+                .in_table(data.table_sources.get(name)),
+        );
+    }
+
+    // === Check Exclusions === //
+    let mut unused = find_unused_exclusions(&data);
+    unused.sort();
+    for (name, excluded) in unused {
+        let message = format!(
+            "prototype `{}` excludes `{}`, but none of its templates provide that component",
+            name, excluded
+        );
+        report.push(
+            Diagnostic::new(Severity::Warning, DiagnosticKind::UnusedExclusion, message)
+                .in_prototype(name, data.source_of(name)),
+        );
+    }
+
+    report
+}
+
+/// Finds the prototypes referenced by the given children (or their inline children) which don't
+/// exist
+fn find_missing_children<'a>(
+    children: &'a [ProtoChild],
+    data: &ProtoData,
+    missing: &mut Vec<&'a str>,
+) {
+    for child in children {
+        match child {
+            ProtoChild::Reference(name) => {
+                if data.get_prototype(name).is_none() {
+                    missing.push(name);
+                }
+            }
+            ProtoChild::Inline(proto) => find_missing_children(&proto.children, data, missing),
+        }
+    }
+}
+
+/// Reads the tables in the given file into `data`, reporting any that can't be read (or that
+/// share a name with an earlier table)
+fn read_table_file(
+    options: &ProtoDataOptions,
+    path: &Path,
+    data: &mut ProtoData,
+    report: &mut ValidationReport,
+) {
+    let results = match read_tables(options, path) {
+        Ok(results) => results,
+        Err(err) => {
+            let message = format!("could not be read: {}", err);
+            report
+                .push(Diagnostic::new(Severity::Error, DiagnosticKind::Io, message).at_path(path));
+            return;
+        }
+    };
+
+    for (document, result) in results.into_iter().enumerate() {
+        let table = match result {
+            Ok(table) => table,
+            Err(err) => {
+                report.push(Diagnostic::from_deserialize_error(path, document, err));
+                continue;
+            }
+        };
+
+        let source = ProtoSource::new(path, document).with_layer(options.layer_of(path));
+        let duplicate = data
+            .table_sources
+            .get(&table.name)
+            .filter(|original| !(options.layered && original.layer < source.layer));
+
+        if let Some(original) = duplicate {
+            let severity = match options.duplicate_policy {
+                DuplicatePolicy::Error => Some(Severity::Error),
+                DuplicatePolicy::WarnKeepFirst | DuplicatePolicy::WarnKeepLast => {
+                    Some(Severity::Warning)
+                }
+                DuplicatePolicy::Override => None,
+            };
+            if let Some(severity) = severity {
+                let message = format!("table `{}` was already defined in {}", table.name, original);
+                report.push(
+                    Diagnostic::new(severity, DiagnosticKind::Duplicate, message)
+                        .in_table(Some(&source)),
+                );
+            }
+            if !options.duplicate_policy.replaces_original() {
+                continue;
+            }
+        }
+
+        data.table_sources.insert(table.name.clone(), source);
+        data.tables.insert(table.name.clone(), table);
+    }
+}
+
+/// The output format used by [`run`]
+enum OutputFormat {
+    Human,
+    Json,
+}
+
+/// Runs the validator as a command-line program, using the arguments given to the current process.
+///
+/// Directories given on the command line replace those in `options`. The report is printed to
+/// stdout, and the returned [`ExitCode`] is nonzero if any errors were found (or if the arguments
+/// were invalid). Run with `--help` for the full list of arguments.
+///
+/// # Arguments
+///
+/// * `options`: The options to use for any setting not given on the command line
+///
+/// returns: ExitCode
+pub fn run(mut options: ProtoDataOptions) -> ExitCode {
+    let mut format = OutputFormat::Human;
+    let mut deny_warnings = false;
+    let mut directories = Vec::new();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-r" | "--recursive" => options.recursive_loading = true,
+            "--namespacing" => options.namespacing = true,
+            "--deny-warnings" => deny_warnings = true,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return ExitCode::SUCCESS;
+            }
+            "--format" => {
+                format = match args.next().as_deref() {
+                    Some("human") => OutputFormat::Human,
+                    Some("json") => OutputFormat::Json,
+                    _ => {
+                        eprintln!("error: `--format` expects either `human` or `json`");
+                        return ExitCode::from(2);
+                    }
+                }
+            }
+            arg if arg.starts_with('-') => {
+                eprintln!("error: unknown option `{}`\n\n{}", arg, USAGE);
+                return ExitCode::from(2);
+            }
+            directory => directories.push(directory.to_string()),
+        }
+    }
+
+    if !directories.is_empty() {
+        options.directories = directories;
+    }
+
+    let report = validate(&options);
+    match format {
+        OutputFormat::Human => print!("{}", report),
+        OutputFormat::Json => println!("{}", report.to_json()),
+    }
+
+    if report.has_errors() || (deny_warnings && report.warning_count() > 0) {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}